@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS password_reset_tokens;
+DROP INDEX IF EXISTS users_email_index;
+ALTER TABLE users DROP COLUMN IF EXISTS email;
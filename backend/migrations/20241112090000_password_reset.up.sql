@@ -0,0 +1,18 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN email varchar(254) default null;
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_index on users (email);
+
+CREATE TABLE IF NOT EXISTS password_reset_tokens (
+  token_hash varchar(90) PRIMARY KEY,
+  user_id uuid NOT NULL,
+  expires timestamptz NOT NULL,
+  used boolean NOT NULL DEFAULT false,
+  created timestamp NOT NULL default current_timestamp,
+  modified timestamp NOT NULL default current_timestamp,
+  CONSTRAINT fk_password_reset_tokens_users
+      FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        on delete cascade
+);
+
+CREATE TRIGGER update_password_reset_tokens_modtime BEFORE UPDATE ON password_reset_tokens FOR EACH ROW EXECUTE PROCEDURE  update_modified_column();
@@ -0,0 +1,29 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN totp_secret varchar(64) default null;
+ALTER TABLE users ADD COLUMN totp_enabled boolean NOT NULL default false;
+
+CREATE TABLE IF NOT EXISTS user_recovery_codes (
+  code_hash varchar(90) NOT NULL,
+  user_id uuid NOT NULL,
+  used boolean NOT NULL DEFAULT false,
+  created timestamp NOT NULL default current_timestamp,
+  modified timestamp NOT NULL default current_timestamp,
+  CONSTRAINT u_user_recovery_codes UNIQUE (user_id, code_hash),
+  CONSTRAINT fk_user_recovery_codes_users
+      FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        on delete cascade
+);
+
+CREATE TABLE IF NOT EXISTS login_challenges (
+  token_hash varchar(90) PRIMARY KEY,
+  user_id uuid NOT NULL,
+  expires timestamptz NOT NULL,
+  created timestamp NOT NULL default current_timestamp,
+  CONSTRAINT fk_login_challenges_users
+      FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        on delete cascade
+);
+
+CREATE TRIGGER update_user_recovery_codes_modtime BEFORE UPDATE ON user_recovery_codes FOR EACH ROW EXECUTE PROCEDURE  update_modified_column();
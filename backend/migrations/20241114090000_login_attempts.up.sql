@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS login_attempts (
+  kind varchar(10) NOT NULL,
+  key varchar(90) NOT NULL,
+  failures int NOT NULL default 0,
+  locked_until timestamptz default null,
+  created timestamp NOT NULL default current_timestamp,
+  modified timestamp NOT NULL default current_timestamp,
+  PRIMARY KEY (kind, key)
+);
+
+CREATE TRIGGER update_login_attempts_modtime BEFORE UPDATE ON login_attempts FOR EACH ROW EXECUTE PROCEDURE  update_modified_column();
@@ -0,0 +1,4 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS oidc_logins;
+DROP INDEX IF EXISTS users_oidc_subject_index;
+ALTER TABLE users DROP COLUMN IF EXISTS oidc_subject;
@@ -0,0 +1,11 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN oidc_subject varchar(255) default null;
+CREATE UNIQUE INDEX IF NOT EXISTS users_oidc_subject_index on users (oidc_subject);
+
+CREATE TABLE IF NOT EXISTS oidc_logins (
+  state_hash varchar(90) PRIMARY KEY,
+  pkce_verifier varchar(200) NOT NULL,
+  nonce varchar(200) NOT NULL,
+  expires timestamptz NOT NULL,
+  created timestamp NOT NULL default current_timestamp
+);
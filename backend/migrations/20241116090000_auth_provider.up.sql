@@ -0,0 +1,2 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN auth_provider varchar(20) NOT NULL default 'local';
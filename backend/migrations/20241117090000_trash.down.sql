@@ -0,0 +1,10 @@
+-- Add down migration script here
+DELETE FROM tasks WHERE deleted IS NOT NULL;
+DELETE FROM project_columns WHERE deleted IS NOT NULL;
+DELETE FROM labels WHERE deleted IS NOT NULL;
+DELETE FROM states WHERE deleted IS NOT NULL;
+ALTER TABLE projects DROP COLUMN IF EXISTS archived;
+ALTER TABLE tasks DROP COLUMN IF EXISTS deleted;
+ALTER TABLE project_columns DROP COLUMN IF EXISTS deleted;
+ALTER TABLE labels DROP COLUMN IF EXISTS deleted;
+ALTER TABLE states DROP COLUMN IF EXISTS deleted;
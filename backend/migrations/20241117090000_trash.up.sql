@@ -0,0 +1,6 @@
+-- Add up migration script here
+ALTER TABLE projects ADD COLUMN archived timestamptz default null;
+ALTER TABLE tasks ADD COLUMN deleted timestamptz default null;
+ALTER TABLE project_columns ADD COLUMN deleted timestamptz default null;
+ALTER TABLE labels ADD COLUMN deleted timestamptz default null;
+ALTER TABLE states ADD COLUMN deleted timestamptz default null;
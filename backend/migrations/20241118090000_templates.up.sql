@@ -0,0 +1,14 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS project_templates (
+  created timestamp NOT NULL default current_timestamp,
+  modified timestamp default current_timestamp,
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4(),
+  name varchar(64) NOT NULL,
+  owner_id uuid default null,
+  published boolean NOT NULL default false,
+  structure jsonb NOT NULL,
+  CONSTRAINT fk_project_templates_owner
+      FOREIGN KEY(owner_id)
+        REFERENCES users(id)
+        on delete set null
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+ALTER TABLE tasks ALTER COLUMN description TYPE varchar(4096) USING left(description, 4096);
+ALTER TABLE tasks RENAME COLUMN description TO descriptsion;
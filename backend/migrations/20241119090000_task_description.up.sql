@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE tasks RENAME COLUMN descriptsion TO description;
+ALTER TABLE tasks ALTER COLUMN description TYPE text;
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS users_feed_token_index;
+ALTER TABLE users DROP COLUMN IF EXISTS feed_token_hash;
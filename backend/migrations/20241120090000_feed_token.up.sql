@@ -0,0 +1,3 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN feed_token_hash varchar(90) default null;
+CREATE UNIQUE INDEX IF NOT EXISTS users_feed_token_index on users (feed_token_hash);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS dav_tasks;
+DROP TABLE IF EXISTS personal_tokens;
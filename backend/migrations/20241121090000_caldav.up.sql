@@ -0,0 +1,30 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS personal_tokens (
+  created timestamptz NOT NULL default now(),
+  id uuid PRIMARY KEY DEFAULT uuid_generate_v4(),
+  user_id uuid NOT NULL,
+  name varchar(64) NOT NULL,
+  token_hash varchar(90) NOT NULL UNIQUE,
+  last_used timestamptz default null,
+  CONSTRAINT fk_personal_tokens_users
+      FOREIGN KEY(user_id)
+        REFERENCES users(id)
+        on delete cascade
+);
+
+-- resource name and UID of tasks a caldav client created, the others use their id
+CREATE TABLE IF NOT EXISTS dav_tasks (
+  task_id uuid PRIMARY KEY,
+  project_id uuid NOT NULL,
+  name varchar(255) NOT NULL,
+  uid varchar(255) NOT NULL,
+  CONSTRAINT u_dav_tasks_name UNIQUE (project_id, name),
+  CONSTRAINT fk_dav_tasks_tasks
+      FOREIGN KEY(task_id)
+        REFERENCES tasks(id)
+        on delete cascade,
+  CONSTRAINT fk_dav_tasks_projects
+      FOREIGN KEY(project_id)
+        REFERENCES projects(id)
+        on delete cascade
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP INDEX IF EXISTS users_email_index;
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_index on users (email);
@@ -0,0 +1,7 @@
+-- Add up migration script here
+-- addresses only differing in case were different accounts so far, the oldest keeps it
+UPDATE users u SET email=null WHERE EXISTS (
+  SELECT 1 FROM users o WHERE lower(o.email)=lower(u.email) AND (o.created, o.id) < (u.created, u.id)
+);
+DROP INDEX IF EXISTS users_email_index;
+CREATE UNIQUE INDEX IF NOT EXISTS users_email_index on users (lower(email));
@@ -0,0 +1,203 @@
+use std::io::Read;
+
+use sqlx::migrate::Migrate;
+use sqlx::{Pool, Postgres};
+
+use crate::config::{Command, Config, InviteCommand, MigrateCommand, UserCommand};
+use crate::data::models::UserModel;
+use crate::data::user_data::{
+    check_username_available, create_user, delete_user_sessions_by_user, get_user_by_name,
+    update_user,
+};
+use crate::data::MIGRATOR;
+use crate::error::{bad_request, not_found, Result};
+use crate::handler::export_handler::{handle_export_project, handle_import_project};
+use crate::handler::user_handler::handle_create_invites;
+use crate::messages::export_messages::ProjectExportMessage;
+use crate::messages::user_messages::CreateUserMessage;
+use crate::util::generate_token;
+use crate::{connect_pool, load_tls_config};
+
+/// Runs a maintenance command, everything except serve
+pub async fn run_command(config: &Config, command: Command) -> Result<()> {
+    match command {
+        Command::Serve => Err(bad_request("serve is not a maintenance command")),
+        Command::CheckConfig => check_config(config).await,
+        Command::Migrate { action } => migrate(&connect_pool(config).await?, action).await,
+        Command::User { action } => user(&connect_pool(config).await?, action).await,
+        Command::Invite {
+            action: InviteCommand::Create { count },
+        } => {
+            for code in handle_create_invites(&connect_pool(config).await?, count).await? {
+                println!("{code}");
+            }
+            Ok(())
+        }
+        Command::Export { project_id, output } => {
+            let pool = connect_pool(config).await?;
+            let export = handle_export_project(&mut *pool.acquire().await?, project_id).await?;
+            let json = serde_json::to_string_pretty(&export)?;
+            match output {
+                Some(path) => std::fs::write(path, json)?,
+                None => println!("{json}"),
+            }
+            Ok(())
+        }
+        Command::Import { file, owner } => {
+            let json = match file {
+                Some(path) => std::fs::read_to_string(path)?,
+                None => {
+                    let mut s = String::new();
+                    std::io::stdin().read_to_string(&mut s)?;
+                    s
+                }
+            };
+            let msg: ProjectExportMessage = serde_json::from_str(&json)?;
+            let pool = connect_pool(config).await?;
+            let mut t = pool.begin().await?;
+            let owner = get_user_by_name(&mut t, &owner)
+                .await?
+                .ok_or_else(|| not_found("Owner not found"))?;
+            let project = handle_import_project(&mut t, &owner, msg).await?;
+            t.commit().await?;
+            println!("Imported project {}", project.id);
+            Ok(())
+        }
+    }
+}
+
+async fn check_config(config: &Config) -> Result<()> {
+    // loading already validated the settings
+    println!("settings ok");
+    if load_tls_config(config)?.is_some() {
+        println!("tls ok");
+    }
+    let pool = connect_pool(config).await?;
+    sqlx::query("select 1").execute(&pool).await?;
+    println!("database ok");
+    Ok(())
+}
+
+async fn migrate(pool: &Pool<Postgres>, action: MigrateCommand) -> Result<()> {
+    let migrator = &MIGRATOR;
+    match action {
+        MigrateCommand::Up => {
+            migrator.run(pool).await?;
+            println!("migrations applied");
+        }
+        MigrateCommand::Down { steps } => {
+            let mut applied = applied_versions(pool).await?;
+            applied.sort_unstable();
+            // everything newer than the target gets reverted
+            let target = match applied.len().checked_sub(steps + 1) {
+                Some(i) => applied[i],
+                None => 0,
+            };
+            migrator.undo(pool, target).await?;
+            println!("reverted to version {target}");
+        }
+        MigrateCommand::Status => {
+            let applied = applied_versions(pool).await?;
+            for m in migrator
+                .iter()
+                .filter(|m| !m.migration_type.is_down_migration())
+            {
+                let state = if applied.contains(&m.version) {
+                    "applied"
+                } else {
+                    "pending"
+                };
+                println!("{state:8} {} {}", m.version, m.description);
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn applied_versions(pool: &Pool<Postgres>) -> Result<Vec<i64>> {
+    let mut conn = pool.acquire().await?;
+    conn.ensure_migrations_table().await?;
+    Ok(conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|m| m.version)
+        .collect())
+}
+
+async fn user(pool: &Pool<Postgres>, action: UserCommand) -> Result<()> {
+    let mut t = pool.begin().await?;
+    match action {
+        UserCommand::Create {
+            username,
+            email,
+            admin,
+            password_stdin,
+        } => {
+            if !check_username_available(&mut t, &username).await? {
+                return Err(bad_request("Username not available"));
+            }
+            let password = password(password_stdin)?;
+            let mut msg = CreateUserMessage::new(username, password.clone(), admin);
+            msg.email = email;
+            let mut user = UserModel::from(msg);
+            create_user(&mut t, &mut user).await?;
+            println!("created user {} ({})", user.username, user.id);
+            if !password_stdin {
+                println!("password: {password}");
+            }
+        }
+        UserCommand::ResetPassword {
+            username,
+            password_stdin,
+        } => {
+            let mut user = find_user(&mut t, &username).await?;
+            user.has_local_password()?;
+            let password = password(password_stdin)?;
+            user.change_password(&password);
+            update_user(&mut t, &user).await?;
+            delete_user_sessions_by_user(&mut t, user.id).await?;
+            println!("password of {} reset, sessions ended", user.username);
+            if !password_stdin {
+                println!("password: {password}");
+            }
+        }
+        UserCommand::Promote { username, revoke } => {
+            let mut user = find_user(&mut t, &username).await?;
+            user.is_admin = !revoke;
+            update_user(&mut t, &user).await?;
+            println!("{} is_admin={}", user.username, user.is_admin);
+        }
+        UserCommand::Disable { username, enable } => {
+            let mut user = find_user(&mut t, &username).await?;
+            user.enabled = enable;
+            update_user(&mut t, &user).await?;
+            if !enable {
+                delete_user_sessions_by_user(&mut t, user.id).await?;
+            }
+            println!("{} enabled={}", user.username, user.enabled);
+        }
+    }
+    t.commit().await?;
+    Ok(())
+}
+
+async fn find_user(conn: &mut sqlx::PgConnection, username: &str) -> Result<UserModel> {
+    get_user_by_name(conn, username)
+        .await?
+        .ok_or_else(|| not_found("User not found"))
+}
+
+/// Reads the first line of stdin or generates a password
+fn password(from_stdin: bool) -> Result<String> {
+    if !from_stdin {
+        return Ok(generate_token(12));
+    }
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    let password = line.trim_end_matches(['\n', '\r']);
+    if password.is_empty() {
+        return Err(bad_request("Password must not be empty"));
+    }
+    Ok(password.to_owned())
+}
@@ -1,20 +1,93 @@
-use clap::Parser;
+use std::{fmt::Display, net::IpAddr, path::PathBuf, str::FromStr};
 
-#[derive(Parser)]
+use clap::{CommandFactory, Parser, Subcommand};
+use sqlx::postgres::PgConnectOptions;
+
+use crate::logging::LogFormat;
+
+#[derive(Parser, Clone)]
 #[command(name="foxboard", version="0.1", about="personal kanban board for zoomy creatures", long_about = None)]
 pub struct Config {
+    /// TOML file with settings, used for everything not set by flag or env var
+    #[arg(long, env = "FOXB_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Full connection url, alternative to the separate db_* settings
+    #[arg(long, env = "FOXB_DATABASE_URL")]
+    pub database_url: Option<String>,
+    /// Database host, may include the port as host:port
     #[arg(long, env = "FOXB_DB_HOST")]
-    pub db_host: String,
+    pub db_host: Option<String>,
+    #[arg(long, env = "FOXB_DB_PORT")]
+    pub db_port: Option<u16>,
     #[arg(long, env = "FOXB_DB_USER")]
-    pub db_user: String,
+    pub db_user: Option<String>,
     #[arg(long, env = "FOXB_DB_PASSWORD")]
-    pub db_password: String,
+    pub db_password: Option<String>,
     #[arg(long, env = "FOXB_DB_DB")]
-    pub db_db: String,
+    pub db_db: Option<String>,
+    /// Max connections in the database pool
+    #[arg(long, env = "FOXB_DB_MAX_CONNECTIONS", default_value_t = 20)]
+    pub db_max_connections: u32,
+    /// Connections the pool keeps open even when idle
+    #[arg(long, env = "FOXB_DB_MIN_CONNECTIONS", default_value_t = 0)]
+    pub db_min_connections: u32,
+    /// Seconds to wait for a free connection before a request fails
+    #[arg(long, env = "FOXB_DB_ACQUIRE_TIMEOUT", default_value_t = 30)]
+    pub db_acquire_timeout: u64,
+    /// Seconds after which idle connections above the minimum get closed
+    #[arg(long, env = "FOXB_DB_IDLE_TIMEOUT", default_value_t = 600)]
+    pub db_idle_timeout: u64,
+
+    /// Addresses to listen on
+    #[arg(
+        long,
+        env = "FOXB_LISTEN",
+        value_delimiter = ',',
+        default_value = "127.0.0.1"
+    )]
+    pub listen: Vec<String>,
+    #[arg(long, env = "FOXB_PORT", default_value_t = 8080)]
+    pub port: u16,
+    /// Number of worker threads, defaults to the number of cpus
+    #[arg(long, env = "FOXB_WORKERS")]
+    pub workers: Option<usize>,
+    /// PEM certificate chain, serves https together with tls_key
+    #[arg(long, env = "FOXB_TLS_CERT")]
+    pub tls_cert: Option<PathBuf>,
+    /// PEM private key for tls_cert
+    #[arg(long, env = "FOXB_TLS_KEY")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Origins allowed to call the api from another origin, e.g. http://localhost:3000, or *
+    #[arg(long, env = "FOXB_CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Vec<String>,
+    #[arg(
+        long,
+        env = "FOXB_CORS_METHODS",
+        value_delimiter = ',',
+        default_value = "GET,POST,PUT,DELETE"
+    )]
+    pub cors_methods: Vec<String>,
+    /// Allow cookies and auth headers on cross origin requests, needs explicit origins
+    #[arg(long, env = "FOXB_CORS_CREDENTIALS", action = clap::ArgAction::SetTrue)]
+    pub cors_credentials: bool,
+    #[arg(
+        long,
+        env = "FOXB_CONTENT_SECURITY_POLICY",
+        default_value = "default-src 'self'; img-src 'self' data:; style-src 'self' 'unsafe-inline'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'"
+    )]
+    pub content_security_policy: String,
+
+    /// Built frontend (frontend/dist) to serve, with the embed-frontend feature the embedded one is used if not set
+    #[arg(long, env = "FOXB_FRONTEND_DIR")]
+    pub frontend_dir: Option<PathBuf>,
+
+    /// Admin account created on first start if it doesn't exist
     #[arg(long, env = "FOXB_ADMIN_USER")]
-    pub admin_user: String,
+    pub admin_user: Option<String>,
     #[arg(long, env = "FOXB_DB_ADMIN_INITIAL_PASSWORD")]
-    pub admin_initial_password: String,
+    pub admin_initial_password: Option<String>,
 
     #[arg(long, env = "FOXB_SMTP_USER")]
     pub smtp_user: Option<String>,
@@ -22,10 +95,493 @@ pub struct Config {
     pub smtp_host: Option<String>,
     #[arg(long, env = "FOXB_SMTP_PASSWORD")]
     pub smtp_password: Option<String>,
-    /// Optional name to operate on
-    pub name: Option<String>,
+    #[arg(
+        long,
+        env = "FOXB_SMTP_FROM",
+        default_value = "foxboard <noreply@localhost>"
+    )]
+    pub smtp_from: String,
+
+    /// Base url of the frontend, used to build links in mails
+    #[arg(long, env = "FOXB_PUBLIC_URL", default_value = "http://localhost:3000")]
+    pub public_url: String,
+    /// Minutes a password reset token stays valid
+    #[arg(long, env = "FOXB_PASSWORD_RESET_TTL", default_value_t = 30)]
+    pub password_reset_ttl: i64,
+    /// Failed logins per username or ip before it gets locked
+    #[arg(long, env = "FOXB_LOGIN_MAX_ATTEMPTS", default_value_t = 5)]
+    pub login_max_attempts: i32,
+    /// Seconds of the first lockout, doubled with every further failure
+    #[arg(long, env = "FOXB_LOGIN_LOCKOUT_SECONDS", default_value_t = 30)]
+    pub login_lockout_seconds: i64,
+    /// Reverse proxies whose X-Forwarded-For header names the client ip, e.g. 127.0.0.1
+    #[arg(long, env = "FOXB_TRUSTED_PROXIES", value_delimiter = ',')]
+    pub trusted_proxies: Vec<IpAddr>,
+    /// Days deleted tasks, columns, labels and states stay in the trash, 0 keeps them forever
+    #[arg(long, env = "FOXB_TRASH_RETENTION_DAYS", default_value_t = 30)]
+    pub trash_retention_days: i64,
+    /// Issuer url of an OpenID Connect provider, enables oidc login
+    #[arg(long, env = "FOXB_OIDC_ISSUER")]
+    pub oidc_issuer: Option<String>,
+    #[arg(long, env = "FOXB_OIDC_CLIENT_ID")]
+    pub oidc_client_id: Option<String>,
+    #[arg(long, env = "FOXB_OIDC_CLIENT_SECRET")]
+    pub oidc_client_secret: Option<String>,
+    /// Url the provider redirects back to, usually a frontend page posting code and state to /api/login/oidc/callback
+    #[arg(long, env = "FOXB_OIDC_REDIRECT_URL")]
+    pub oidc_redirect_url: Option<String>,
+    /// Claim checked for admin rights, e.g. "groups"
+    #[arg(long, env = "FOXB_OIDC_ADMIN_CLAIM")]
+    pub oidc_admin_claim: Option<String>,
+    /// Value the admin claim has to be or contain
+    #[arg(long, env = "FOXB_OIDC_ADMIN_VALUE")]
+    pub oidc_admin_value: Option<String>,
+    /// Create unknown oidc users on first login
+    #[arg(long, env = "FOXB_OIDC_AUTO_PROVISION", default_value_t = true, action = clap::ArgAction::Set)]
+    pub oidc_auto_provision: bool,
+    /// Link the first oidc login to an unlinked account with the same verified email, only safe
+    /// if the provider can't hand out addresses that belong to someone else
+    #[arg(long, env = "FOXB_OIDC_LINK_EMAIL", action = clap::ArgAction::SetTrue)]
+    pub oidc_link_email: bool,
+
+    /// Providers checked in order on password login, "local" and/or "ldap"
+    #[arg(
+        long,
+        env = "FOXB_AUTH_PROVIDERS",
+        value_delimiter = ',',
+        default_value = "local"
+    )]
+    pub auth_providers: Vec<String>,
+    /// e.g. ldap://localhost:389 or ldaps://ldap.example.com
+    #[arg(long, env = "FOXB_LDAP_URL")]
+    pub ldap_url: Option<String>,
+    #[arg(long, env = "FOXB_LDAP_BASE_DN")]
+    pub ldap_base_dn: Option<String>,
+    /// Filter to find the user, {username} is replaced with the escaped login name
+    #[arg(
+        long,
+        env = "FOXB_LDAP_USER_FILTER",
+        default_value = "(uid={username})"
+    )]
+    pub ldap_user_filter: String,
+    /// Account used to search for users, searches anonymously if not set
+    #[arg(long, env = "FOXB_LDAP_BIND_DN")]
+    pub ldap_bind_dn: Option<String>,
+    #[arg(long, env = "FOXB_LDAP_BIND_PASSWORD")]
+    pub ldap_bind_password: Option<String>,
+    /// DN of the group whose members are admins
+    #[arg(long, env = "FOXB_LDAP_ADMIN_GROUP")]
+    pub ldap_admin_group: Option<String>,
+    #[arg(long, env = "FOXB_LDAP_EMAIL_ATTRIBUTE", default_value = "mail")]
+    pub ldap_email_attribute: String,
+
+    /// Bearer token required for /metrics, open if not set
+    #[arg(long, env = "FOXB_METRICS_TOKEN")]
+    pub metrics_token: Option<String>,
+
+    /// Serve the Swagger UI for /openapi.json under /docs/
+    #[arg(long, env = "FOXB_API_DOCS", action = clap::ArgAction::SetTrue)]
+    pub api_docs: bool,
+
+    /// Log filter like "info" or "foxboard=debug,sqlx=warn", ignored with --debug
+    #[arg(long, env = "FOXB_LOG_LEVEL")]
+    pub log_level: Option<String>,
+    #[arg(long, env = "FOXB_LOG_FORMAT", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
 
     /// Turn debugging information on
     #[arg(short, long, env="FOXB_DEBUG", action = clap::ArgAction::SetTrue)]
     pub debug: bool,
+
+    /// What to do, runs the server if not given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum Command {
+    /// Run the server, migrating the database first
+    Serve,
+    /// Apply, revert or list database migrations
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateCommand,
+    },
+    /// Manage user accounts
+    User {
+        #[command(subcommand)]
+        action: UserCommand,
+    },
+    /// Manage invite codes for self registration
+    Invite {
+        #[command(subcommand)]
+        action: InviteCommand,
+    },
+    /// Write a project with its columns, labels, states and tasks as JSON
+    Export {
+        project_id: uuid::Uuid,
+        /// File to write to, stdout if not given
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Create a project from a JSON export
+    Import {
+        /// File to read from, stdin if not given
+        file: Option<PathBuf>,
+        /// Username of the new owner
+        #[arg(long)]
+        owner: String,
+    },
+    /// Validate the settings, the tls files and the database connection
+    CheckConfig,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum MigrateCommand {
+    /// Apply all pending migrations
+    Up,
+    /// Revert the latest migrations
+    Down {
+        #[arg(default_value_t = 1)]
+        steps: usize,
+    },
+    /// List applied and pending migrations
+    Status,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum UserCommand {
+    /// Create a local user, prints a generated password unless --password-stdin is given
+    Create {
+        username: String,
+        #[arg(long)]
+        email: Option<String>,
+        #[arg(long)]
+        admin: bool,
+        /// Read the password from the first line of stdin
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Set a new password and end all sessions, prints a generated password unless --password-stdin is given
+    ResetPassword {
+        username: String,
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Give a user admin rights
+    Promote {
+        username: String,
+        /// Take admin rights away instead
+        #[arg(long)]
+        revoke: bool,
+    },
+    /// Disable a user and end all sessions
+    Disable {
+        username: String,
+        /// Enable the user again instead
+        #[arg(long)]
+        enable: bool,
+    },
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum InviteCommand {
+    /// Create invite codes and print them
+    Create {
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+    },
+}
+
+/// A setting that is missing or doesn't make sense, named by its config key and env var
+#[derive(Debug)]
+pub struct ConfigError {
+    pub setting: String,
+    pub msg: String,
+}
+
+impl ConfigError {
+    pub fn new(setting: &str, msg: &str) -> Self {
+        let setting = match env_name(setting) {
+            Some(env) => format!("{setting} ({env})"),
+            None => setting.to_owned(),
+        };
+        Self {
+            setting,
+            msg: msg.to_owned(),
+        }
+    }
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid setting {}: {}", self.setting, self.msg)
+    }
+}
+
+fn env_name(setting: &str) -> Option<String> {
+    Config::command()
+        .get_arguments()
+        .find(|a| a.get_id() == setting)
+        .and_then(|a| a.get_env())
+        .map(|e| e.to_string_lossy().into_owned())
+}
+
+impl Config {
+    /// Reads the settings from flags, env vars and the config file, in that order of precedence.
+    /// Env vars ending in _FILE are read from the named file, for docker secrets.
+    pub fn load() -> Result<Self, ConfigError> {
+        if let Some(path) = config_file_path() {
+            apply_config_file(&path)?;
+        }
+        apply_secret_files()?;
+        let config = Config::parse();
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if let Some(url) = &self.database_url {
+            PgConnectOptions::from_str(url)
+                .map_err(|e| ConfigError::new("database_url", &e.to_string()))?;
+        } else {
+            for (name, value) in [
+                ("db_host", &self.db_host),
+                ("db_user", &self.db_user),
+                ("db_password", &self.db_password),
+                ("db_db", &self.db_db),
+            ] {
+                if value.is_none() {
+                    return Err(ConfigError::new(
+                        name,
+                        "required unless database_url is set",
+                    ));
+                }
+            }
+        }
+        if matches!(self.command, None | Some(Command::Serve)) {
+            if self.admin_user.is_none() {
+                return Err(ConfigError::new("admin_user", "required to run the server"));
+            }
+            if self.admin_initial_password.is_none() {
+                return Err(ConfigError::new(
+                    "admin_initial_password",
+                    "required to run the server",
+                ));
+            }
+        }
+        if self.db_max_connections == 0 {
+            return Err(ConfigError::new("db_max_connections", "must be at least 1"));
+        }
+        if self.db_min_connections > self.db_max_connections {
+            return Err(ConfigError::new(
+                "db_min_connections",
+                "must not be larger than db_max_connections",
+            ));
+        }
+        if self.listen.is_empty() {
+            return Err(ConfigError::new("listen", "needs at least one address"));
+        }
+        match (&self.tls_cert, &self.tls_key) {
+            (Some(_), None) => return Err(ConfigError::new("tls_key", "required with tls_cert")),
+            (None, Some(_)) => return Err(ConfigError::new("tls_cert", "required with tls_key")),
+            _ => {}
+        }
+        for origin in &self.cors_origins {
+            if origin == "*" {
+                if self.cors_credentials {
+                    return Err(ConfigError::new(
+                        "cors_origins",
+                        "* can't be combined with cors_credentials",
+                    ));
+                }
+                continue;
+            }
+            let valid = openidconnect::url::Url::parse(origin)
+                .is_ok_and(|u| u.origin().ascii_serialization() == *origin);
+            if !valid {
+                return Err(ConfigError::new(
+                    "cors_origins",
+                    &format!("'{origin}' is not an origin like https://example.com"),
+                ));
+            }
+        }
+        for method in &self.cors_methods {
+            if actix_web::http::Method::from_bytes(method.trim().as_bytes()).is_err() {
+                return Err(ConfigError::new(
+                    "cors_methods",
+                    &format!("unknown method '{method}'"),
+                ));
+            }
+        }
+        if let Some(dir) = &self.frontend_dir {
+            if !dir.join("index.html").is_file() {
+                return Err(ConfigError::new(
+                    "frontend_dir",
+                    &format!("{} contains no index.html", dir.display()),
+                ));
+            }
+        }
+        self.smtp_from
+            .parse::<lettre::message::Mailbox>()
+            .map_err(|e| ConfigError::new("smtp_from", &e.to_string()))?;
+        if self.login_max_attempts < 1 {
+            return Err(ConfigError::new("login_max_attempts", "must be at least 1"));
+        }
+        if self.oidc_issuer.is_some() {
+            if self.oidc_client_id.is_none() {
+                return Err(ConfigError::new(
+                    "oidc_client_id",
+                    "required with oidc_issuer",
+                ));
+            }
+            if self.oidc_redirect_url.is_none() {
+                return Err(ConfigError::new(
+                    "oidc_redirect_url",
+                    "required with oidc_issuer",
+                ));
+            }
+        }
+        for provider in &self.auth_providers {
+            match provider.trim() {
+                "local" => {}
+                "ldap" => {
+                    if self.ldap_url.is_none() {
+                        return Err(ConfigError::new(
+                            "ldap_url",
+                            "required for the ldap provider",
+                        ));
+                    }
+                    if self.ldap_base_dn.is_none() {
+                        return Err(ConfigError::new(
+                            "ldap_base_dn",
+                            "required for the ldap provider",
+                        ));
+                    }
+                }
+                other => {
+                    return Err(ConfigError::new(
+                        "auth_providers",
+                        &format!("unknown provider '{other}'"),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Connection options from database_url or the separate db_* settings.
+    /// Building them field by field avoids escaping problems with special characters.
+    pub fn db_connect_options(&self) -> Result<PgConnectOptions, ConfigError> {
+        if let Some(url) = &self.database_url {
+            return PgConnectOptions::from_str(url)
+                .map_err(|e| ConfigError::new("database_url", &e.to_string()));
+        }
+        let missing = |name| ConfigError::new(name, "required unless database_url is set");
+        let host = self.db_host.as_deref().ok_or_else(|| missing("db_host"))?;
+        let mut options = PgConnectOptions::new()
+            .username(self.db_user.as_deref().ok_or_else(|| missing("db_user"))?)
+            .password(
+                self.db_password
+                    .as_deref()
+                    .ok_or_else(|| missing("db_password"))?,
+            )
+            .database(self.db_db.as_deref().ok_or_else(|| missing("db_db"))?);
+        options = match host.rsplit_once(':').map(|(h, p)| (h, p.parse::<u16>())) {
+            Some((h, Ok(port))) if !h.contains(':') => options.host(h).port(port),
+            _ => options.host(host),
+        };
+        if let Some(port) = self.db_port {
+            options = options.port(port);
+        }
+        Ok(options)
+    }
+}
+
+/// Config file from --config or FOXB_CONFIG, looked up before clap runs
+fn config_file_path() -> Option<PathBuf> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--config" {
+            return args.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--config=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    std::env::var_os("FOXB_CONFIG").map(PathBuf::from)
+}
+
+/// Puts the settings of the file into the env vars that aren't set yet, so flags and
+/// env vars keep precedence. Keys are the setting names, tables prefix their keys
+/// (`[db] host` is `db_host`) and keys ending in `_file` name a file to read the value from.
+fn apply_config_file(path: &PathBuf) -> Result<(), ConfigError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| ConfigError::new("config", &format!("{}: {e}", path.display())))?;
+    let table: toml::Table = content
+        .parse()
+        .map_err(|e| ConfigError::new("config", &format!("{}: {e}", path.display())))?;
+    let mut settings = Vec::new();
+    for (key, value) in table {
+        match value {
+            toml::Value::Table(t) => {
+                settings.extend(t.into_iter().map(|(k, v)| (format!("{key}_{k}"), v)))
+            }
+            v => settings.push((key, v)),
+        }
+    }
+    for (key, value) in settings {
+        let (setting, suffix) = match key.strip_suffix("_file") {
+            Some(s) if env_name(s).is_some() => (s, "_FILE"),
+            _ => (key.as_str(), ""),
+        };
+        let env = env_name(setting)
+            .filter(|_| setting != "config")
+            .ok_or_else(|| {
+                ConfigError::new(&key, &format!("unknown setting in {}", path.display()))
+            })?;
+        let value = match value {
+            toml::Value::String(s) => s,
+            toml::Value::Array(a) => a
+                .into_iter()
+                .map(|v| match v {
+                    toml::Value::String(s) => s,
+                    v => v.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(","),
+            toml::Value::Table(_) => {
+                return Err(ConfigError::new(&key, "tables can only be nested once"))
+            }
+            v => v.to_string(),
+        };
+        let env = format!("{env}{suffix}");
+        if std::env::var_os(&env).is_none() {
+            std::env::set_var(env, value);
+        }
+    }
+    Ok(())
+}
+
+/// Sets FOXB_X from the file named in FOXB_X_FILE, unless FOXB_X is set
+fn apply_secret_files() -> Result<(), ConfigError> {
+    for arg in Config::command().get_arguments() {
+        let Some(env) = arg.get_env().map(|e| e.to_string_lossy().into_owned()) else {
+            continue;
+        };
+        let Some(path) = std::env::var_os(format!("{env}_FILE")) else {
+            continue;
+        };
+        if std::env::var_os(&env).is_some() {
+            continue;
+        }
+        let value = std::fs::read_to_string(&path).map_err(|e| {
+            ConfigError::new(
+                arg.get_id().as_str(),
+                &format!("{env}_FILE {}: {e}", PathBuf::from(&path).display()),
+            )
+        })?;
+        std::env::set_var(env, value.trim_end_matches(['\n', '\r']));
+    }
+    Ok(())
 }
@@ -0,0 +1,41 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::models::DavTaskModel;
+use crate::error::Result;
+
+pub async fn get_dav_tasks(conn: &mut PgConnection, project_id: Uuid) -> Result<Vec<DavTaskModel>> {
+    let tasks = sqlx::query_as("select * from dav_tasks where project_id=$1")
+        .bind(project_id)
+        .fetch_all(conn)
+        .await?;
+    Ok(tasks)
+}
+
+pub async fn create_dav_task(conn: &mut PgConnection, task: &DavTaskModel) -> Result<()> {
+    sqlx::query("insert into dav_tasks (task_id, project_id, name, uid) values($1, $2, $3, $4)")
+        .bind(task.task_id)
+        .bind(task.project_id)
+        .bind(&task.name)
+        .bind(&task.uid)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// When the tasks were last changed, or created if never
+pub async fn get_task_stamps(
+    conn: &mut PgConnection,
+    task_ids: &[Uuid],
+) -> Result<HashMap<Uuid, DateTime<Utc>>> {
+    let stamps: Vec<(Uuid, DateTime<Utc>)> = sqlx::query_as(
+        "select id, coalesce(modified, created)::timestamptz from tasks where id=any($1)",
+    )
+    .bind(task_ids)
+    .fetch_all(conn)
+    .await?;
+    Ok(stamps.into_iter().collect())
+}
@@ -1,4 +1,12 @@
+use sqlx::migrate::Migrator;
+
+pub mod dav_data;
 pub mod models;
 pub mod project_data;
+pub mod system_data;
 pub mod task_data;
+pub mod template_data;
 pub mod user_data;
+
+/// The migrations embedded in the binary
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
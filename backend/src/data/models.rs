@@ -1,21 +1,42 @@
-use super::user_data::get_user_from_session;
+use super::user_data::{get_user_from_session, update_user_session};
 use crate::{
-    error::{auth_error, not_found, unauthorized, AppError, Result},
+    error::{auth_error, bad_request, crypt_error, unauthorized, AppError, Result},
     messages::user_messages::CreateUserMessage,
 };
 use actix_web::{web, FromRequest};
 use argon2::Config;
 use rand::{rngs::OsRng, RngCore};
 use sqlx::{postgres::PgRow, types::Uuid, FromRow, Pool, Postgres, Row};
-use std::{future::Future, ops::Deref, pin::Pin};
+use std::{
+    future::Future,
+    ops::Deref,
+    pin::Pin,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use totp_rs::{Algorithm, Secret, TOTP};
+
+pub const TOTP_ISSUER: &str = "foxboard";
+pub const LOCAL_AUTH_PROVIDER: &str = "local";
+/// Minutes a user has to enter the second factor after a successful password check
+pub const LOGIN_CHALLENGE_TTL: i64 = 5;
+/// Minutes a user has to finish the login at the oidc provider
+pub const OIDC_LOGIN_TTL: i64 = 10;
 
 #[derive(sqlx::FromRow, Default, Debug)]
 pub struct UserModel {
     pub(crate) username: String,
     pub(crate) password_hash: String,
-    // pub(crate) email: String,
+    pub(crate) email: Option<String>,
+    /// The email was set by an admin or came from the directory or oidc provider
+    pub(crate) email_verified: bool,
     pub(crate) id: Uuid,
     pub(crate) is_admin: bool,
+    pub(crate) enabled: bool,
+    pub(crate) totp_secret: Option<String>,
+    pub(crate) totp_enabled: bool,
+    /// Where the account comes from: "local", "ldap" or "oidc"
+    pub(crate) auth_provider: String,
 }
 
 impl UserModel {
@@ -35,6 +56,17 @@ impl UserModel {
         }
     }
 
+    /// Only local accounts have a usable password, the others sign in through their provider
+    pub fn has_local_password(&self) -> Result<()> {
+        if self.auth_provider != LOCAL_AUTH_PROVIDER {
+            return Err(bad_request(&format!(
+                "Accounts from {} sign in there and have no password here",
+                self.auth_provider
+            )));
+        }
+        Ok(())
+    }
+
     pub fn check_password(&self, password: &str) -> Result<bool> {
         Ok(argon2::verify_encoded(
             &self.password_hash,
@@ -42,6 +74,37 @@ impl UserModel {
         )?)
     }
 
+    /// Builds the totp generator from the stored secret, if one was enrolled
+    pub fn totp(&self) -> Result<Option<TOTP>> {
+        match &self.totp_secret {
+            Some(secret) => Ok(Some(TOTP::new(
+                Algorithm::SHA1,
+                6,
+                1,
+                30,
+                Secret::Encoded(secret.clone())
+                    .to_bytes()
+                    .map_err(|e| crypt_error(&e.to_string()))?,
+                Some(TOTP_ISSUER.to_owned()),
+                self.username.clone(),
+            )?)),
+            None => Ok(None),
+        }
+    }
+
+    /// The time step the code belongs to if it is valid, see `use_totp_step`
+    pub fn check_totp(&self, code: &str) -> Result<Option<i64>> {
+        let Some(mut totp) = self.totp()? else {
+            return Ok(None);
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() / totp.step;
+        let skew = totp.skew as u64;
+        totp.skew = 0;
+        Ok((now - skew..=now + skew)
+            .find(|step| totp.check(code, step * totp.step))
+            .map(|step| step as i64))
+    }
+
     pub fn perms(&self) -> Option<Permissions> {
         if self.is_admin {
             Some(Permissions::Admin)
@@ -51,6 +114,18 @@ impl UserModel {
     }
 }
 
+/// Verifies the password against a throwaway hash, so logins for unknown
+/// usernames take as long as ones with a wrong password
+pub fn dummy_password_check(password: &str) {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    let hash = DUMMY_HASH.get_or_init(|| {
+        let mut salt = [0u8; 12];
+        OsRng.fill_bytes(&mut salt);
+        argon2::hash_encoded(b"dummy password", &salt, &Config::default()).unwrap()
+    });
+    let _ = argon2::verify_encoded(hash, password.as_bytes());
+}
+
 impl Ressource for UserModel {
     fn get_permissions(&self, user_id: Uuid) -> Option<Permissions> {
         if self.id == user_id {
@@ -71,15 +146,24 @@ impl From<CreateUserMessage> for UserModel {
         Self {
             username: value.username,
             password_hash: hash,
+            // only admins create users
+            email_verified: value.email.is_some(),
+            email: value.email,
             id: Uuid::nil(),
             is_admin: value.is_admin.unwrap_or(false),
+            enabled: true,
+            totp_secret: None,
+            totp_enabled: false,
+            auth_provider: LOCAL_AUTH_PROVIDER.to_owned(),
         }
     }
 }
 
+/// The user of a valid session, the session's user agent and address are refreshed on the way
 #[derive(Debug)]
 pub struct AuthenticatedUser {
     pub user: UserModel,
+    /// The session token, without the "Bearer " prefix
     pub token: String,
 }
 
@@ -108,16 +192,30 @@ impl FromRequest for AuthenticatedUser {
             .get("Authorization")
             .ok_or(auth_error("Auth header missing"))
             .cloned();
+        let user_agent = req.headers().get("User-Agent").cloned();
+        let ip_addr = req.peer_addr();
         Box::pin(async move {
             let pool = pool_res?;
             let h = header?;
             let token = h.to_str().map_err(|e| auth_error(&e.to_string()))?;
-            if !token.starts_with("Bearer ") {
-                Err(auth_error("Doesnt start with 'Bearer '"))?;
-            }
-            let user = get_user_from_session(&mut *pool.acquire().await?, &token[7..])
+            let token = token
+                .strip_prefix("Bearer ")
+                .ok_or(auth_error("Doesnt start with 'Bearer '"))?;
+            let mut conn = pool.acquire().await?;
+            let user = get_user_from_session(&mut conn, token)
                 .await?
-                .ok_or(not_found("Could not find user by token"))?;
+                .ok_or(auth_error("Session invalid or expired"))?;
+            if !user.enabled {
+                Err(auth_error("Account disabled"))?;
+            }
+            tracing::Span::current().record("user_id", tracing::field::display(user.id));
+            let user_agent = user_agent.ok_or(bad_request("Missing User Agent Header"))?;
+            let ip_addr = ip_addr.ok_or(bad_request("IP Address Missing"))?;
+            update_user_session(
+                &mut conn,
+                UserSessionModel::new(token, user_agent.to_str()?, ip_addr.to_string(), user.id),
+            )
+            .await?;
             Ok(Self {
                 user,
                 token: token.to_owned(),
@@ -146,12 +244,83 @@ impl UserSessionModel {
     }
 }
 
+#[derive(sqlx::FromRow, Default, Debug)]
+pub struct PasswordResetModel {
+    pub token_hash: String,
+    pub user_id: Uuid,
+    pub expires: chrono::DateTime<chrono::Utc>,
+}
+
+impl PasswordResetModel {
+    pub fn new(token_hash: String, user_id: Uuid, ttl_minutes: i64) -> Self {
+        Self {
+            token_hash,
+            user_id,
+            expires: chrono::Utc::now() + chrono::Duration::minutes(ttl_minutes),
+        }
+    }
+}
+
+#[derive(sqlx::FromRow, Default, Debug)]
+pub struct LoginChallengeModel {
+    pub token_hash: String,
+    pub user_id: Uuid,
+    pub expires: chrono::DateTime<chrono::Utc>,
+}
+
+impl LoginChallengeModel {
+    pub fn new(token_hash: String, user_id: Uuid) -> Self {
+        Self {
+            token_hash,
+            user_id,
+            expires: chrono::Utc::now() + chrono::Duration::minutes(LOGIN_CHALLENGE_TTL),
+        }
+    }
+}
+
+/// A started oidc login waiting for the provider to redirect back
+#[derive(sqlx::FromRow, Default, Debug)]
+pub struct OidcLoginModel {
+    pub state_hash: String,
+    pub pkce_verifier: String,
+    pub nonce: String,
+    pub expires: chrono::DateTime<chrono::Utc>,
+}
+
+impl OidcLoginModel {
+    pub fn new(state_hash: String, pkce_verifier: String, nonce: String) -> Self {
+        Self {
+            state_hash,
+            pkce_verifier,
+            nonce,
+            expires: chrono::Utc::now() + chrono::Duration::minutes(OIDC_LOGIN_TTL),
+        }
+    }
+}
+
+/// What a failed login is tracked against
+#[derive(Debug, Clone, Copy)]
+pub enum LoginAttemptKind {
+    User,
+    Ip,
+}
+
+impl LoginAttemptKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Ip => "ip",
+        }
+    }
+}
+
 #[derive(sqlx::FromRow, Default, Debug)]
 pub struct ProjectModel {
     pub name: String,
     pub public: bool,
     pub id: Uuid,
     pub owner_id: Uuid,
+    pub archived: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl Ressource for ProjectModel {
@@ -177,6 +346,7 @@ pub struct ProjectColumnModel {
 #[derive(sqlx::FromRow, Default, Debug)]
 pub struct TaskModel {
     pub title: String,
+    pub description: Option<String>,
     pub id: Uuid,
     pub project_id: Uuid,
     pub column_id: Option<Uuid>,
@@ -189,18 +359,13 @@ pub struct TaskModel {
     pub task_type: i32,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub enum TaskType {
+    #[default]
     Standard,
     Repeatable,
 }
 
-impl Default for TaskType {
-    fn default() -> Self {
-        Self::Standard
-    }
-}
-
 #[derive(sqlx::FromRow, Default, Debug)]
 pub struct LabelModel {
     pub name: String,
@@ -215,13 +380,63 @@ pub struct StateModel {
     pub project_id: Uuid,
 }
 
-#[derive(PartialEq, PartialOrd, Eq, Ord)]
+/// A named token for clients that can't log in, like caldav apps
+#[derive(sqlx::FromRow, Default, Debug)]
+pub struct PersonalTokenModel {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_hash: String,
+    pub created: chrono::DateTime<chrono::Utc>,
+    pub last_used: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Resource name and UID a caldav client gave the task it created
+#[derive(sqlx::FromRow, Default, Debug, Clone)]
+pub struct DavTaskModel {
+    pub task_id: Uuid,
+    pub project_id: Uuid,
+    pub name: String,
+    pub uid: String,
+}
+
+/// A saved project structure new projects can start from. Only the owner sees it
+/// until an admin publishes it.
+#[derive(sqlx::FromRow, Default, Debug)]
+pub struct TemplateModel {
+    pub name: String,
+    pub id: Uuid,
+    pub owner_id: Option<Uuid>,
+    pub published: bool,
+    /// A [`ProjectExportMessage`](crate::messages::export_messages::ProjectExportMessage) as JSON
+    pub structure: String,
+}
+
+impl TemplateModel {
+    pub fn can_edit(&self, user: &UserModel) -> bool {
+        user.is_admin || self.owner_id == Some(user.id)
+    }
+}
+
+/// A deleted task, column, label or state, waiting in the trash of its project
+#[derive(sqlx::FromRow, Debug)]
+pub struct TrashModel {
+    /// "task", "column", "label" or "state"
+    pub kind: String,
+    pub id: Uuid,
+    pub name: String,
+    pub deleted: chrono::DateTime<chrono::Utc>,
+}
+
+/// Ordered from least to most, so a check reads `perms >= Permissions::Editor`
+#[derive(PartialEq, PartialOrd, Eq, Ord, Default, Debug, Clone, Copy)]
 pub enum Permissions {
-    Admin,
-    Owner,
-    Editor,
-    Reader,
+    #[default]
     None,
+    Reader,
+    Editor,
+    Owner,
+    Admin,
 }
 
 impl Permissions {
@@ -234,18 +449,26 @@ impl Permissions {
             _ => Self::None,
         }
     }
+
+    /// The encoding of [`Permissions::from_i32`], 5 is what project_permissions defaults to
+    pub fn to_i32(self) -> i32 {
+        match self {
+            Self::Admin => 0,
+            Self::Owner => 1,
+            Self::Editor => 2,
+            Self::Reader => 3,
+            Self::None => 5,
+        }
+    }
 }
 impl FromRow<'_, PgRow> for Permissions {
     fn from_row(row: &PgRow) -> sqlx::Result<Self> {
-        Ok(Self::from_i32(row.try_get(0)?))
+        // null when the user is neither owner nor member
+        let perm: Option<i32> = row.try_get(0)?;
+        Ok(perm.map(Self::from_i32).unwrap_or_default())
     }
 }
 
-impl Default for Permissions {
-    fn default() -> Self {
-        Self::None
-    }
-}
 pub trait Ressource {
     fn get_permissions(&self, user_id: Uuid) -> Option<Permissions>;
 }
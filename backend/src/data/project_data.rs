@@ -1,4 +1,6 @@
-use super::models::{LabelModel, Permissions, ProjectColumnModel, ProjectModel, StateModel};
+use super::models::{
+    LabelModel, Permissions, ProjectColumnModel, ProjectModel, StateModel, TrashModel,
+};
 use crate::{
     error::Result,
     util::{from_pg_rows, Pagination},
@@ -27,31 +29,51 @@ pub async fn get_project(conn: &mut PgConnection, id: Uuid) -> Result<Option<Pro
     Ok(proj)
 }
 
+/// Projects the user owns or can read publicly, either the active or the archived ones
 pub async fn get_projects(
     conn: &mut PgConnection,
     owner_id: Uuid,
+    archived: bool,
     pag: Pagination,
 ) -> Result<(i64, Vec<ProjectModel>)> {
     let r= sqlx::query(
-        "select *, count(*) over() as full_count from projects where owner_id=$1 or public=true order by created ASC limit $2 offset $3",
+        "select *, count(*) over() as full_count from projects where (owner_id=$1 or public=true) and (archived is not null)=$4 order by created ASC limit $2 offset $3",
     )
     .bind(owner_id)
     .bind(pag.count)
     .bind(pag.count * (pag.page - 1))
+    .bind(archived)
     .fetch_all(conn)
     .await?;
     from_pg_rows(&r, "full_count")
 }
 
+/// Projects the user owns or is a member of, without archived ones
+pub async fn get_member_projects(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<ProjectModel>> {
+    let projects = sqlx::query_as(
+        "select p.* from projects p left join project_permissions pp on pp.project_id=p.id and pp.user_id=$1
+            where (p.owner_id=$1 or pp.perm<=3) and p.archived is null order by p.created",
+    )
+    .bind(user_id)
+    .fetch_all(conn)
+    .await?;
+    Ok(projects)
+}
+
 pub async fn list_projects(
     conn: &mut PgConnection,
+    archived: bool,
     pag: Pagination,
 ) -> Result<(i64, Vec<ProjectModel>)> {
     let r= sqlx::query(
-        "select *, count(*) over() as full_count from projects order by created ASC limit $1 offset $2",
+        "select *, count(*) over() as full_count from projects where (archived is not null)=$3 order by created ASC limit $1 offset $2",
     )
     .bind(pag.count)
     .bind(pag.count * (pag.page - 1))
+    .bind(archived)
     .fetch_all(conn)
     .await?;
     from_pg_rows(&r, "full_count")
@@ -68,6 +90,22 @@ pub async fn update_project(conn: &mut PgConnection, proj: &mut ProjectModel) ->
     Ok(())
 }
 
+/// Archives the project now or brings it back
+pub async fn archive_project(
+    conn: &mut PgConnection,
+    proj: &mut ProjectModel,
+    archive: bool,
+) -> Result<()> {
+    proj.archived = sqlx::query_scalar(
+        "update projects set archived=case when $1 then coalesce(archived, now()) end where id=$2 returning archived",
+    )
+    .bind(archive)
+    .bind(proj.id)
+    .fetch_one(conn)
+    .await?;
+    Ok(())
+}
+
 pub async fn delete_project(conn: &mut PgConnection, id: Uuid) -> Result<()> {
     sqlx::query("delete from projects where id=$1")
         .bind(id)
@@ -91,6 +129,43 @@ pub async fn get_project_permission(
     )
 }
 
+/// Usernames and roles of the members, the owner isn't one
+pub async fn get_project_members(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+) -> Result<Vec<(String, Permissions)>> {
+    let members: Vec<(String, i32)> = sqlx::query_as(
+        "select u.username, pp.perm from project_permissions pp join users u on u.id=pp.user_id
+            where pp.project_id=$1 order by u.username",
+    )
+    .bind(project_id)
+    .fetch_all(conn)
+    .await?;
+    Ok(members
+        .into_iter()
+        .map(|(name, perm)| (name, Permissions::from_i32(perm)))
+        .collect())
+}
+
+/// Gives the user a role on the project, unless they already have one
+pub async fn add_project_member(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    user_id: Uuid,
+    perms: Permissions,
+) -> Result<()> {
+    sqlx::query(
+        "insert into project_permissions (perm, user_id, project_id) values ($1, $2, $3)
+            on conflict (user_id, project_id) do nothing",
+    )
+    .bind(perms.to_i32())
+    .bind(user_id)
+    .bind(project_id)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
 pub async fn create_column(conn: &mut PgConnection, col: &mut ProjectColumnModel) -> Result<()> {
     let id: Uuid = sqlx::query_scalar(
         "INSERT INTO project_columns (name, card_limit, index, project_id) values($1, $2, $3, $4) returning id",
@@ -116,56 +191,87 @@ pub async fn create_label(conn: &mut PgConnection, lab: &mut LabelModel) -> Resu
     Ok(())
 }
 
-pub async fn get_column(conn: &mut PgConnection, id: Uuid) -> Result<Option<ProjectColumnModel>> {
-    let col = sqlx::query_as("Select * FROM project_columns where id=$1")
-        .bind(id)
-        .fetch_optional(conn)
-        .await?;
+pub async fn get_column(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    id: Uuid,
+) -> Result<Option<ProjectColumnModel>> {
+    let col = sqlx::query_as(
+        "Select * FROM project_columns where id=$1 and project_id=$2 and deleted is null",
+    )
+    .bind(id)
+    .bind(project_id)
+    .fetch_optional(conn)
+    .await?;
     Ok(col)
 }
 
-pub async fn get_label(conn: &mut PgConnection, id: Uuid) -> Result<Option<LabelModel>> {
-    let lab = sqlx::query_as("Select * FROM labels where id=$1")
-        .bind(id)
-        .fetch_optional(conn)
-        .await?;
+pub async fn get_label(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    id: Uuid,
+) -> Result<Option<LabelModel>> {
+    let lab =
+        sqlx::query_as("Select * FROM labels where id=$1 and project_id=$2 and deleted is null")
+            .bind(id)
+            .bind(project_id)
+            .fetch_optional(conn)
+            .await?;
     Ok(lab)
 }
 
 pub async fn update_column(conn: &mut PgConnection, col: &ProjectColumnModel) -> Result<()> {
-    sqlx::query("UPDATE project_columns SET name=$1, index=$2, card_limit=$3 where id=$4")
-        .bind(&col.name)
-        .bind(col.index)
-        .bind(col.card_limit)
-        .bind(col.id)
-        .execute(conn)
-        .await?;
+    sqlx::query(
+        "UPDATE project_columns SET name=$1, index=$2, card_limit=$3 where id=$4 and project_id=$5 and deleted is null",
+    )
+    .bind(&col.name)
+    .bind(col.index)
+    .bind(col.card_limit)
+    .bind(col.id)
+    .bind(col.project_id)
+    .execute(conn)
+    .await?;
     Ok(())
 }
 
 pub async fn update_label(conn: &mut PgConnection, lab: &LabelModel) -> Result<()> {
-    sqlx::query("UPDATE labels SET name=$1 where id=$2")
+    sqlx::query("UPDATE labels SET name=$1 where id=$2 and project_id=$3 and deleted is null")
         .bind(&lab.name)
         .bind(lab.id)
+        .bind(lab.project_id)
         .execute(conn)
         .await?;
     Ok(())
 }
 
-pub async fn delete_column(conn: &mut PgConnection, column_id: Uuid) -> Result<()> {
-    sqlx::query("delete from project_columns where id=$1")
+/// Moves the column to the trash, returns false if it doesn't exist in the project
+pub async fn delete_column(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    column_id: Uuid,
+) -> Result<bool> {
+    let res = sqlx::query("update project_columns set deleted=now() where id=$1 and project_id=$2 and deleted is null")
         .bind(column_id)
+        .bind(project_id)
         .execute(conn)
         .await?;
-    Ok(())
+    Ok(res.rows_affected() > 0)
 }
 
-pub async fn delete_label(conn: &mut PgConnection, label_id: Uuid) -> Result<()> {
-    sqlx::query("delete from labels where id=$1")
-        .bind(label_id)
-        .execute(conn)
-        .await?;
-    Ok(())
+/// Moves the label to the trash, returns false if it doesn't exist in the project
+pub async fn delete_label(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    label_id: Uuid,
+) -> Result<bool> {
+    let res = sqlx::query(
+        "update labels set deleted=now() where id=$1 and project_id=$2 and deleted is null",
+    )
+    .bind(label_id)
+    .bind(project_id)
+    .execute(conn)
+    .await?;
+    Ok(res.rows_affected() > 0)
 }
 
 pub async fn get_columns(
@@ -173,7 +279,7 @@ pub async fn get_columns(
     project_id: Uuid,
 ) -> Result<Vec<ProjectColumnModel>> {
     let columns = sqlx::query_as(
-        "select * from project_columns where project_id=$1 order by index, created ASC",
+        "select * from project_columns where project_id=$1 and deleted is null order by index, created ASC",
     )
     .bind(project_id)
     .fetch_all(conn)
@@ -182,10 +288,12 @@ pub async fn get_columns(
 }
 
 pub async fn get_labels(conn: &mut PgConnection, project_id: Uuid) -> Result<Vec<LabelModel>> {
-    let labels = sqlx::query_as("select * from labels where project_id=$1 order by created asc")
-        .bind(project_id)
-        .fetch_all(conn)
-        .await?;
+    let labels = sqlx::query_as(
+        "select * from labels where project_id=$1 and deleted is null order by created asc",
+    )
+    .bind(project_id)
+    .fetch_all(conn)
+    .await?;
     Ok(labels)
 }
 
@@ -199,32 +307,146 @@ pub async fn create_state(conn: &mut PgConnection, state: &mut StateModel) -> Re
     state.id = id;
     Ok(())
 }
-pub async fn get_state(conn: &mut PgConnection, id: Uuid) -> Result<Option<StateModel>> {
-    let state = sqlx::query_as("Select * FROM states where id=$1")
-        .bind(id)
-        .fetch_optional(conn)
-        .await?;
+pub async fn get_state(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    id: Uuid,
+) -> Result<Option<StateModel>> {
+    let state =
+        sqlx::query_as("Select * FROM states where id=$1 and project_id=$2 and deleted is null")
+            .bind(id)
+            .bind(project_id)
+            .fetch_optional(conn)
+            .await?;
     Ok(state)
 }
 pub async fn update_state(conn: &mut PgConnection, state: &StateModel) -> Result<()> {
-    sqlx::query("UPDATE states SET name=$1 where id=$2")
+    sqlx::query("UPDATE states SET name=$1 where id=$2 and project_id=$3 and deleted is null")
         .bind(&state.name)
         .bind(state.id)
+        .bind(state.project_id)
         .execute(conn)
         .await?;
     Ok(())
 }
-pub async fn delete_state(conn: &mut PgConnection, state_id: Uuid) -> Result<()> {
-    sqlx::query("delete from states where id=$1")
-        .bind(state_id)
-        .execute(conn)
-        .await?;
-    Ok(())
+/// Moves the state to the trash, returns false if it doesn't exist in the project
+pub async fn delete_state(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    state_id: Uuid,
+) -> Result<bool> {
+    let res = sqlx::query(
+        "update states set deleted=now() where id=$1 and project_id=$2 and deleted is null",
+    )
+    .bind(state_id)
+    .bind(project_id)
+    .execute(conn)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+/// Number of the given labels that belong to the project
+pub async fn count_project_labels(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    label_ids: &[Uuid],
+) -> Result<i64> {
+    let count = sqlx::query_scalar(
+        "select count(*) from labels where project_id=$1 and id = any($2) and deleted is null",
+    )
+    .bind(project_id)
+    .bind(label_ids)
+    .fetch_one(conn)
+    .await?;
+    Ok(count)
 }
+
 pub async fn get_states(conn: &mut PgConnection, project_id: Uuid) -> Result<Vec<StateModel>> {
-    let states = sqlx::query_as("select * from states where project_id=$1 order by created asc")
-        .bind(project_id)
-        .fetch_all(conn)
-        .await?;
+    let states = sqlx::query_as(
+        "select * from states where project_id=$1 and deleted is null order by created asc",
+    )
+    .bind(project_id)
+    .fetch_all(conn)
+    .await?;
     Ok(states)
 }
+
+/// Everything deleted in the project, newest first. Subtasks deleted along with their
+/// parent are left out, they come back with it.
+pub async fn get_trash(conn: &mut PgConnection, project_id: Uuid) -> Result<Vec<TrashModel>> {
+    let trash = sqlx::query_as(
+        "select 'task' as kind, t.id, t.title as name, t.deleted from tasks t
+            left join tasks p on p.id=t.parent_id
+            where t.project_id=$1 and t.deleted is not null and p.deleted is distinct from t.deleted
+        union all select 'column', id, name, deleted from project_columns where project_id=$1 and deleted is not null
+        union all select 'label', id, name, deleted from labels where project_id=$1 and deleted is not null
+        union all select 'state', id, name, deleted from states where project_id=$1 and deleted is not null
+        order by deleted desc",
+    )
+    .bind(project_id)
+    .fetch_all(conn)
+    .await?;
+    Ok(trash)
+}
+
+/// Takes the column out of the trash, returns false if it isn't in there
+pub async fn restore_column(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    column_id: Uuid,
+) -> Result<bool> {
+    let res = sqlx::query(
+        "update project_columns set deleted=null where id=$1 and project_id=$2 and deleted is not null",
+    )
+    .bind(column_id)
+    .bind(project_id)
+    .execute(conn)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Takes the label out of the trash, returns false if it isn't in there
+pub async fn restore_label(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    label_id: Uuid,
+) -> Result<bool> {
+    let res = sqlx::query(
+        "update labels set deleted=null where id=$1 and project_id=$2 and deleted is not null",
+    )
+    .bind(label_id)
+    .bind(project_id)
+    .execute(conn)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Takes the state out of the trash, returns false if it isn't in there
+pub async fn restore_state(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    state_id: Uuid,
+) -> Result<bool> {
+    let res = sqlx::query(
+        "update states set deleted=null where id=$1 and project_id=$2 and deleted is not null",
+    )
+    .bind(state_id)
+    .bind(project_id)
+    .execute(conn)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
+/// Removes everything that has been in a trash for more than `days`, returns the number of rows
+pub async fn purge_trash(conn: &mut PgConnection, days: i64) -> Result<u64> {
+    let mut purged = 0;
+    for table in ["tasks", "project_columns", "labels", "states"] {
+        purged += sqlx::query(&format!(
+            "delete from {table} where deleted < now() - make_interval(days => $1)"
+        ))
+        .bind(days as i32)
+        .execute(&mut *conn)
+        .await?
+        .rows_affected();
+    }
+    Ok(purged)
+}
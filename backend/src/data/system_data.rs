@@ -0,0 +1,17 @@
+use sqlx::PgConnection;
+
+use crate::error::Result;
+
+pub async fn ping(conn: &mut PgConnection) -> Result<()> {
+    sqlx::query("select 1").execute(conn).await?;
+    Ok(())
+}
+
+/// Newest successfully applied migration, fails if migrations never ran
+pub async fn get_latest_migration(conn: &mut PgConnection) -> Result<Option<i64>> {
+    Ok(
+        sqlx::query_scalar("select max(version) from _sqlx_migrations where success")
+            .fetch_one(conn)
+            .await?,
+    )
+}
@@ -17,9 +17,10 @@ pub async fn create_task(conn: &mut PgConnection, model: &mut TaskModel) -> Resu
             project_id,
             state_id,
             task_type,
-            assignee_id
-        ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-        where id=$11 returning id",
+            assignee_id,
+            description
+        ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        returning id",
     )
     .bind(&model.title)
     .bind(model.parent_id)
@@ -31,7 +32,7 @@ pub async fn create_task(conn: &mut PgConnection, model: &mut TaskModel) -> Resu
     .bind(model.state_id)
     .bind(model.task_type)
     .bind(model.assignee_id)
-    .bind(model.id)
+    .bind(&model.description)
     .fetch_one(conn)
     .await?;
     model.id = id;
@@ -50,8 +51,9 @@ pub async fn update_task(conn: &mut PgConnection, model: &mut TaskModel) -> Resu
             project_id=$7,
             state_id=$8,
             task_type=$9,
-            assignee_id=$10
-        where id=$11",
+            assignee_id=$10,
+            description=$11
+        where id=$12",
     )
     .bind(&model.title)
     .bind(model.parent_id)
@@ -63,6 +65,7 @@ pub async fn update_task(conn: &mut PgConnection, model: &mut TaskModel) -> Resu
     .bind(model.state_id)
     .bind(model.task_type)
     .bind(model.assignee_id)
+    .bind(&model.description)
     .bind(model.id)
     .execute(conn)
     .await?;
@@ -70,32 +73,109 @@ pub async fn update_task(conn: &mut PgConnection, model: &mut TaskModel) -> Resu
 }
 
 pub async fn get_task(conn: &mut PgConnection, task_id: Uuid) -> Result<Option<TaskModel>> {
-    let task = sqlx::query_as("select * from tasks where id=$1")
+    let task = sqlx::query_as("select * from tasks where id=$1 and deleted is null")
         .bind(task_id)
         .fetch_optional(conn)
         .await?;
     Ok(task)
 }
 
+/// Whether `other` is below the task, following the subtasks down
+pub async fn is_subtask_of(conn: &mut PgConnection, other: Uuid, task_id: Uuid) -> Result<bool> {
+    let below = sqlx::query_scalar(
+        "with recursive below(id) as (
+            select id from tasks where parent_id=$1
+            union
+            select t.id from tasks t join below b on t.parent_id=b.id
+        ) select exists(select 1 from below where id=$2)",
+    )
+    .bind(task_id)
+    .bind(other)
+    .fetch_one(conn)
+    .await?;
+    Ok(below)
+}
+
+/// Moves the task and its subtasks to the trash, they share the deletion time
 pub async fn delete_task(conn: &mut PgConnection, task_id: Uuid) -> Result<()> {
-    sqlx::query("delete from tasks where id=$1")
-        .bind(task_id)
-        .execute(conn)
-        .await?;
+    sqlx::query(
+        "with recursive subtasks as (
+            select id from tasks where id=$1
+            union select t.id from tasks t join subtasks s on t.parent_id=s.id where t.deleted is null
+        )
+        update tasks set deleted=now() where id in (select id from subtasks) and deleted is null",
+    )
+    .bind(task_id)
+    .execute(conn)
+    .await?;
     Ok(())
 }
 
+/// Takes the task out of the trash together with the subtasks deleted along with it,
+/// returns false if it isn't in there. A parent that is still in the trash is let go, purging it
+/// would take the task with it.
+pub async fn restore_task(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    task_id: Uuid,
+) -> Result<bool> {
+    let res = sqlx::query(
+        "with recursive subtasks as (
+            select id, deleted from tasks where id=$1 and project_id=$2 and deleted is not null
+            union select t.id, t.deleted from tasks t join subtasks s on t.parent_id=s.id and t.deleted=s.deleted
+        )
+        update tasks t set deleted=null,
+            parent_id=case when t.id=$1 and (select p.deleted from tasks p where p.id=t.parent_id) is not null
+                then null else t.parent_id end
+        where t.id in (select id from subtasks)",
+    )
+    .bind(task_id)
+    .bind(project_id)
+    .execute(conn)
+    .await?;
+    Ok(res.rows_affected() > 0)
+}
+
 pub async fn get_tasks_by_project(
     conn: &mut PgConnection,
     project_id: Uuid,
 ) -> Result<Vec<TaskModel>> {
-    let tasks = sqlx::query_as("select * from tasks where project_id=$1")
+    let tasks = sqlx::query_as("select * from tasks where project_id=$1 and deleted is null")
         .bind(project_id)
         .fetch_all(conn)
         .await?;
     Ok(tasks)
 }
 
+/// Tasks with a deadline assigned to the user, in projects the user can still read
+pub async fn get_assigned_tasks(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    is_admin: bool,
+) -> Result<Vec<TaskModel>> {
+    let tasks = sqlx::query_as(
+        "select t.* from tasks t join projects p on p.id=t.project_id
+            left join project_permissions pp on pp.project_id=p.id and pp.user_id=$1
+            where t.assignee_id=$1 and t.deadline is not null and t.deleted is null and p.archived is null
+            and ($2 or p.public or p.owner_id=$1 or pp.perm<=3)
+            order by t.deadline",
+    )
+    .bind(user_id)
+    .bind(is_admin)
+    .fetch_all(conn)
+    .await?;
+    Ok(tasks)
+}
+
+pub async fn count_tasks_by_project(conn: &mut PgConnection) -> Result<Vec<(Uuid, i64)>> {
+    let counts = sqlx::query_as(
+        "select project_id, count(*) from tasks where deleted is null group by project_id",
+    )
+    .fetch_all(conn)
+    .await?;
+    Ok(counts)
+}
+
 pub async fn add_task_label(conn: &mut PgConnection, task_id: Uuid, label_id: Uuid) -> Result<()> {
     sqlx::query("insert into labels_tasks (task_id, label_id) values($1, $2)")
         .bind(task_id)
@@ -110,7 +190,7 @@ pub async fn remove_task_label(
     task_id: Uuid,
     label_id: Uuid,
 ) -> Result<()> {
-    sqlx::query("delete from labels_tasls where task_id=$1 and label_id=$2)")
+    sqlx::query("delete from labels_tasks where task_id=$1 and label_id=$2")
         .bind(task_id)
         .bind(label_id)
         .execute(conn)
@@ -120,7 +200,7 @@ pub async fn remove_task_label(
 
 pub async fn get_task_labels(conn: &mut PgConnection, task_id: Uuid) -> Result<Vec<LabelModel>> {
     let labels = sqlx::query_as(
-        "select * from labels l join labels_tasks lt on lt.label_id=l.id where task_id=$1",
+        "select * from labels l join labels_tasks lt on lt.label_id=l.id where task_id=$1 and l.deleted is null",
     )
     .bind(task_id)
     .fetch_all(conn)
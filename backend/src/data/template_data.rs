@@ -0,0 +1,79 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::models::TemplateModel;
+use crate::{
+    error::Result,
+    util::{from_pg_rows, Pagination},
+};
+
+pub async fn create_template(conn: &mut PgConnection, template: &mut TemplateModel) -> Result<()> {
+    let id: Uuid = sqlx::query_scalar(
+        "INSERT INTO project_templates (name, owner_id, published, structure) values($1, $2, $3, $4::jsonb) returning id",
+    )
+    .bind(&template.name)
+    .bind(template.owner_id)
+    .bind(template.published)
+    .bind(&template.structure)
+    .fetch_one(conn)
+    .await?;
+    template.id = id;
+    Ok(())
+}
+
+/// The template, if the user may see it: published ones, their own and for admins all
+pub async fn get_template(
+    conn: &mut PgConnection,
+    id: Uuid,
+    user_id: Uuid,
+    is_admin: bool,
+) -> Result<Option<TemplateModel>> {
+    let template = sqlx::query_as(
+        "select name, id, owner_id, published, structure::text as structure from project_templates
+            where id=$1 and (published or owner_id=$2 or $3)",
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(is_admin)
+    .fetch_optional(conn)
+    .await?;
+    Ok(template)
+}
+
+/// The templates the user may see, see [`get_template`]
+pub async fn get_templates(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    is_admin: bool,
+    pag: Pagination,
+) -> Result<(i64, Vec<TemplateModel>)> {
+    let r = sqlx::query(
+        "select name, id, owner_id, published, structure::text as structure, count(*) over() as full_count from project_templates
+            where published or owner_id=$1 or $2 order by name, created ASC limit $3 offset $4",
+    )
+    .bind(user_id)
+    .bind(is_admin)
+    .bind(pag.count)
+    .bind(pag.count * (pag.page - 1))
+    .fetch_all(conn)
+    .await?;
+    from_pg_rows(&r, "full_count")
+}
+
+pub async fn update_template(conn: &mut PgConnection, template: &TemplateModel) -> Result<()> {
+    sqlx::query("update project_templates set name=$1, published=$2, modified=now() where id=$3")
+        .bind(&template.name)
+        .bind(template.published)
+        .bind(template.id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn delete_template(conn: &mut PgConnection, id: Uuid) -> Result<()> {
+    sqlx::query("delete from project_templates where id=$1")
+        .bind(id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
@@ -5,15 +5,22 @@ use crate::data::models::UserModel;
 use crate::error::Result;
 use crate::util::{from_pg_rows, Pagination};
 
-use super::models::UserSessionModel;
+use super::models::{
+    LoginAttemptKind, LoginChallengeModel, OidcLoginModel, PasswordResetModel, PersonalTokenModel,
+    UserSessionModel,
+};
 
 pub async fn create_user(conn: &mut PgConnection, user: &mut UserModel) -> Result<()> {
     let id: Uuid = sqlx::query_scalar(
-        "INSERT INTO users (username, password_hash, enabled, is_admin) Values($1, $2, true, $3) returning id",
+        "INSERT INTO users (username, password_hash, enabled, is_admin, email, email_verified, auth_provider) Values($1, $2, $3, $4, $5, $6, $7) returning id",
     )
     .bind(&user.username)
     .bind(&user.password_hash)
+    .bind(user.enabled)
     .bind(user.is_admin)
+    .bind(&user.email)
+    .bind(user.email_verified)
+    .bind(&user.auth_provider)
     .fetch_one(conn)
     .await?;
     user.id = id;
@@ -21,11 +28,18 @@ pub async fn create_user(conn: &mut PgConnection, user: &mut UserModel) -> Resul
 }
 
 pub async fn update_user(conn: &mut PgConnection, user: &UserModel) -> Result<()> {
-    sqlx::query("UPDATE users SET password_hash=$1, username=$2, is_admin=$3 WHERE id=$4")
-        .bind(&user.password_hash)
-        .bind(&user.username)
-        .bind(user.is_admin)
-        .bind(user.id)
+    sqlx::query(
+        "UPDATE users SET password_hash=$1, username=$2, is_admin=$3, enabled=$4, email=$5, email_verified=$6, totp_secret=$7, totp_enabled=$8 WHERE id=$9",
+    )
+    .bind(&user.password_hash)
+    .bind(&user.username)
+    .bind(user.is_admin)
+    .bind(user.enabled)
+    .bind(&user.email)
+    .bind(user.email_verified)
+    .bind(&user.totp_secret)
+    .bind(user.totp_enabled)
+    .bind(user.id)
         .execute(conn)
         .await?;
     Ok(())
@@ -44,7 +58,7 @@ pub async fn get_user_from_session(
     conn: &mut PgConnection,
     session_token: &str,
 ) -> Result<Option<UserModel>> {
-    let user = sqlx::query_as("select u.id, u.username, u.password_hash, u.is_admin from users u join user_sessions s on s.user_id=u.id where token=$1")
+    let user = sqlx::query_as("select u.id, u.username, u.password_hash, u.email, u.email_verified, u.is_admin, u.enabled, u.totp_secret, u.totp_enabled, u.auth_provider from users u join user_sessions s on s.user_id=u.id where token=$1")
         .bind(session_token)
         .fetch_optional(conn)
         .await?;
@@ -56,7 +70,7 @@ pub async fn get_user_by_name(
     username: &str,
 ) -> Result<Option<UserModel>> {
     let user = sqlx::query_as(
-        "select u.id, u.username, u.password_hash, u.is_admin from users u where username=$1",
+        "select u.id, u.username, u.password_hash, u.email, u.email_verified, u.is_admin, u.enabled, u.totp_secret, u.totp_enabled, u.auth_provider from users u where username=$1",
     )
     .bind(username)
     .fetch_optional(conn)
@@ -64,9 +78,59 @@ pub async fn get_user_by_name(
     Ok(user)
 }
 
+pub async fn get_user_by_email(conn: &mut PgConnection, email: &str) -> Result<Option<UserModel>> {
+    let user = sqlx::query_as(
+        "select u.id, u.username, u.password_hash, u.email, u.email_verified, u.is_admin, u.enabled, u.totp_secret, u.totp_enabled, u.auth_provider from users u where lower(email)=lower($1)",
+    )
+    .bind(email)
+    .fetch_optional(conn)
+    .await?;
+    Ok(user)
+}
+
+/// The user with the email if no oidc subject is linked to it yet and the email is verified
+pub async fn get_unlinked_user_by_email(
+    conn: &mut PgConnection,
+    email: &str,
+) -> Result<Option<UserModel>> {
+    let user = sqlx::query_as(
+        "select u.id, u.username, u.password_hash, u.email, u.email_verified, u.is_admin, u.enabled, u.totp_secret, u.totp_enabled, u.auth_provider from users u where lower(email)=lower($1) and email_verified and oidc_subject is null",
+    )
+    .bind(email)
+    .fetch_optional(conn)
+    .await?;
+    Ok(user)
+}
+
+pub async fn get_user_by_oidc_subject(
+    conn: &mut PgConnection,
+    subject: &str,
+) -> Result<Option<UserModel>> {
+    let user = sqlx::query_as(
+        "select u.id, u.username, u.password_hash, u.email, u.email_verified, u.is_admin, u.enabled, u.totp_secret, u.totp_enabled, u.auth_provider from users u where oidc_subject=$1",
+    )
+    .bind(subject)
+    .fetch_optional(conn)
+    .await?;
+    Ok(user)
+}
+
+pub async fn link_oidc_subject(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    subject: &str,
+) -> Result<()> {
+    sqlx::query("update users set oidc_subject=$1 where id=$2")
+        .bind(subject)
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
 pub async fn get_user_by_id(conn: &mut PgConnection, id: Uuid) -> Result<Option<UserModel>> {
     let user = sqlx::query_as(
-        "select u.id, u.username, u.password_hash, u.is_admin from users u where id=$1",
+        "select u.id, u.username, u.password_hash, u.email, u.email_verified, u.is_admin, u.enabled, u.totp_secret, u.totp_enabled, u.auth_provider from users u where id=$1",
     )
     .bind(id)
     .fetch_optional(conn)
@@ -74,6 +138,33 @@ pub async fn get_user_by_id(conn: &mut PgConnection, id: Uuid) -> Result<Option<
     Ok(user)
 }
 
+pub async fn get_user_by_feed_token(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> Result<Option<UserModel>> {
+    let user = sqlx::query_as(
+        "select u.id, u.username, u.password_hash, u.email, u.email_verified, u.is_admin, u.enabled, u.totp_secret, u.totp_enabled, u.auth_provider from users u where feed_token_hash=$1",
+    )
+    .bind(token_hash)
+    .fetch_optional(conn)
+    .await?;
+    Ok(user)
+}
+
+/// Replaces the calendar feed token of the user, `None` revokes it
+pub async fn set_feed_token(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    token_hash: Option<&str>,
+) -> Result<()> {
+    sqlx::query("update users set feed_token_hash=$1 where id=$2")
+        .bind(token_hash)
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
 pub async fn create_user_session(conn: &mut PgConnection, session: UserSessionModel) -> Result<()> {
     sqlx::query(
         "insert into user_sessions (token, user_agent, ip_addr, user_id) values($1, $2, $3, $4)",
@@ -117,9 +208,303 @@ pub async fn get_user_sessions_by_user(
     conn: &mut PgConnection,
     user_id: Uuid,
 ) -> Result<Vec<UserSessionModel>> {
-    let sessions = sqlx::query_as("select * from user_sessions")
+    let sessions = sqlx::query_as("select * from user_sessions where user_id=$1")
         .bind(user_id)
         .fetch_all(conn)
         .await?;
     Ok(sessions)
 }
+
+pub async fn delete_user_sessions_by_user(conn: &mut PgConnection, user_id: Uuid) -> Result<()> {
+    sqlx::query("delete from user_sessions where user_id=$1")
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn create_invite_code(conn: &mut PgConnection, code_hash: &str) -> Result<()> {
+    sqlx::query("insert into invite_codes (code) values($1)")
+        .bind(code_hash)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn create_password_reset(
+    conn: &mut PgConnection,
+    reset: &PasswordResetModel,
+) -> Result<()> {
+    sqlx::query(
+        "insert into password_reset_tokens (token_hash, user_id, expires) values($1, $2, $3)",
+    )
+    .bind(&reset.token_hash)
+    .bind(reset.user_id)
+    .bind(reset.expires)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Marks a reset token as used and returns the user it belongs to,
+/// if it exists, is unused and not expired
+pub async fn use_password_reset(conn: &mut PgConnection, token_hash: &str) -> Result<Option<Uuid>> {
+    let user_id = sqlx::query_scalar(
+        "update password_reset_tokens set used=true where token_hash=$1 and used=false and expires > now() returning user_id",
+    )
+    .bind(token_hash)
+    .fetch_optional(conn)
+    .await?;
+    Ok(user_id)
+}
+
+/// Replaces all recovery codes of a user with the given hashed codes
+pub async fn replace_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    code_hashes: &[String],
+) -> Result<()> {
+    delete_recovery_codes(&mut *conn, user_id).await?;
+    for hash in code_hashes {
+        sqlx::query("insert into user_recovery_codes (code_hash, user_id) values($1, $2)")
+            .bind(hash)
+            .bind(user_id)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+pub async fn delete_recovery_codes(conn: &mut PgConnection, user_id: Uuid) -> Result<()> {
+    sqlx::query("delete from user_recovery_codes where user_id=$1")
+        .bind(user_id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Marks a recovery code as used, returns false if it doesn't exist or was already used
+pub async fn use_recovery_code(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    code_hash: &str,
+) -> Result<bool> {
+    Ok(sqlx::query(
+        "update user_recovery_codes set used=true where user_id=$1 and code_hash=$2 and used=false",
+    )
+    .bind(user_id)
+    .bind(code_hash)
+    .execute(conn)
+    .await?
+    .rows_affected()
+        > 0)
+}
+
+/// Remembers the time step of an accepted totp code, returns false if that step or a later one
+/// was used already, so every code only works once
+pub async fn use_totp_step(conn: &mut PgConnection, user_id: Uuid, step: i64) -> Result<bool> {
+    Ok(sqlx::query(
+        "update users set totp_last_step=$2 where id=$1 and (totp_last_step is null or totp_last_step < $2)",
+    )
+    .bind(user_id)
+    .bind(step)
+    .execute(conn)
+    .await?
+    .rows_affected()
+        > 0)
+}
+
+pub async fn create_login_challenge(
+    conn: &mut PgConnection,
+    challenge: &LoginChallengeModel,
+) -> Result<()> {
+    sqlx::query("insert into login_challenges (token_hash, user_id, expires) values($1, $2, $3)")
+        .bind(&challenge.token_hash)
+        .bind(challenge.user_id)
+        .bind(challenge.expires)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Consumes a login challenge and returns the user it was issued for, if it is still valid
+pub async fn use_login_challenge(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> Result<Option<Uuid>> {
+    let user_id = sqlx::query_scalar(
+        "delete from login_challenges where token_hash=$1 and expires > now() returning user_id",
+    )
+    .bind(token_hash)
+    .fetch_optional(conn)
+    .await?;
+    Ok(user_id)
+}
+
+/// Returns the latest lockout still in effect for the username or ip
+pub async fn get_login_lock(
+    conn: &mut PgConnection,
+    username: &str,
+    ip_addr: &str,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>> {
+    let locked_until = sqlx::query_scalar(
+        "select max(locked_until) from login_attempts where ((kind=$1 and key=$2) or (kind=$3 and key=$4)) and locked_until > now()",
+    )
+    .bind(LoginAttemptKind::User.as_str())
+    .bind(username)
+    .bind(LoginAttemptKind::Ip.as_str())
+    .bind(ip_addr)
+    .fetch_one(conn)
+    .await?;
+    Ok(locked_until)
+}
+
+/// Counts a failed login and locks once `max_attempts` is reached.
+/// Every further failure doubles the lockout, capped at a day.
+/// Failures older than a day are forgotten.
+pub async fn record_login_failure(
+    conn: &mut PgConnection,
+    kind: LoginAttemptKind,
+    key: &str,
+    max_attempts: i32,
+    lockout_seconds: i64,
+) -> Result<()> {
+    sqlx::query(
+        "insert into login_attempts (kind, key, failures, locked_until) values($1, $2, 1, case when 1 >= $3 then now() + make_interval(secs => $4) else null end)
+        on conflict (kind, key) do update set
+            failures = case when login_attempts.modified < now() - interval '1 day' then 1 else login_attempts.failures + 1 end,
+            locked_until = case
+                when login_attempts.modified < now() - interval '1 day' then case when 1 >= $3 then now() + make_interval(secs => $4) else null end
+                when login_attempts.failures + 1 >= $3 then now() + make_interval(secs => least($4 * power(2, login_attempts.failures + 1 - $3), 86400))
+                else null end",
+    )
+    .bind(kind.as_str())
+    .bind(key)
+    .bind(max_attempts)
+    .bind(lockout_seconds as f64)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn clear_login_failures(
+    conn: &mut PgConnection,
+    kind: LoginAttemptKind,
+    key: &str,
+) -> Result<()> {
+    sqlx::query("delete from login_attempts where kind=$1 and key=$2")
+        .bind(kind.as_str())
+        .bind(key)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+pub async fn create_oidc_login(conn: &mut PgConnection, login: &OidcLoginModel) -> Result<()> {
+    sqlx::query(
+        "insert into oidc_logins (state_hash, pkce_verifier, nonce, expires) values($1, $2, $3, $4)",
+    )
+    .bind(&login.state_hash)
+    .bind(&login.pkce_verifier)
+    .bind(&login.nonce)
+    .bind(login.expires)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Consumes a started oidc login, if it is still valid
+pub async fn use_oidc_login(
+    conn: &mut PgConnection,
+    state_hash: &str,
+) -> Result<Option<OidcLoginModel>> {
+    let login = sqlx::query_as(
+        "delete from oidc_logins where state_hash=$1 and expires > now() returning state_hash, pkce_verifier, nonce, expires",
+    )
+    .bind(state_hash)
+    .fetch_optional(conn)
+    .await?;
+    Ok(login)
+}
+
+pub async fn create_personal_token(
+    conn: &mut PgConnection,
+    token: &mut PersonalTokenModel,
+) -> Result<()> {
+    (token.id, token.created) = sqlx::query_as(
+        "insert into personal_tokens (user_id, name, token_hash) values($1, $2, $3) returning id, created",
+    )
+    .bind(token.user_id)
+    .bind(&token.name)
+    .bind(&token.token_hash)
+    .fetch_one(conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_personal_tokens(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<PersonalTokenModel>> {
+    let tokens = sqlx::query_as("select * from personal_tokens where user_id=$1 order by created")
+        .bind(user_id)
+        .fetch_all(conn)
+        .await?;
+    Ok(tokens)
+}
+
+/// Returns false if the user has no such token
+pub async fn delete_personal_token(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    id: Uuid,
+) -> Result<bool> {
+    Ok(
+        sqlx::query("delete from personal_tokens where id=$1 and user_id=$2")
+            .bind(id)
+            .bind(user_id)
+            .execute(conn)
+            .await?
+            .rows_affected()
+            == 1,
+    )
+}
+
+/// The user of the token, noting that it was used
+pub async fn use_personal_token(
+    conn: &mut PgConnection,
+    token_hash: &str,
+) -> Result<Option<UserModel>> {
+    let user = sqlx::query_as(
+        "with used as (update personal_tokens set last_used=now() where token_hash=$1 returning user_id)
+        select u.id, u.username, u.password_hash, u.email, u.email_verified, u.is_admin, u.enabled, u.totp_secret, u.totp_enabled, u.auth_provider from users u join used on used.user_id=u.id",
+    )
+    .bind(token_hash)
+    .fetch_optional(conn)
+    .await?;
+    Ok(user)
+}
+
+pub async fn count_sessions(conn: &mut PgConnection) -> Result<i64> {
+    Ok(sqlx::query_scalar("select count(*) from user_sessions")
+        .fetch_one(conn)
+        .await?)
+}
+
+/// Removes expired reset tokens, login challenges, oidc logins and login
+/// failures old enough to be reset anyway, returns the number of rows
+pub async fn delete_expired_logins(conn: &mut PgConnection) -> Result<u64> {
+    let mut deleted = 0;
+    for query in [
+        "delete from password_reset_tokens where expires < now()",
+        "delete from login_challenges where expires < now()",
+        "delete from oidc_logins where expires < now()",
+        "delete from login_attempts where modified < now() - interval '1 day'",
+    ] {
+        deleted += sqlx::query(query)
+            .execute(&mut *conn)
+            .await?
+            .rows_affected();
+    }
+    Ok(deleted)
+}
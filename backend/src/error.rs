@@ -1,6 +1,9 @@
 use std::num::ParseIntError;
 
-use actix_web::{error::QueryPayloadError, http::header::ToStrError};
+use actix_web::{
+    error::QueryPayloadError,
+    http::{header::ToStrError, StatusCode},
+};
 use derive_more::derive::{Display, Error};
 
 pub type Result<T> = std::result::Result<T, AppError>;
@@ -16,9 +19,52 @@ pub enum AppError {
     Forbidden { msg: String },
     Error { msg: String },
     Unauthorized { msg: String },
+    MailError { msg: String },
 }
 
-impl actix_web::error::ResponseError for AppError {}
+impl actix_web::error::ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::RequestError { .. } => StatusCode::BAD_REQUEST,
+            AppError::AuthError { .. } => StatusCode::UNAUTHORIZED,
+            AppError::Unauthorized { .. } | AppError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            AppError::NotFound { .. } => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(item: std::io::Error) -> Self {
+        AppError::IOError {
+            msg: item.to_string(),
+        }
+    }
+}
+
+impl From<serde_json::Error> for AppError {
+    fn from(item: serde_json::Error) -> Self {
+        AppError::RequestError {
+            msg: item.to_string(),
+        }
+    }
+}
+
+impl From<crate::config::ConfigError> for AppError {
+    fn from(item: crate::config::ConfigError) -> Self {
+        AppError::Error {
+            msg: item.to_string(),
+        }
+    }
+}
+
+impl From<sqlx::migrate::MigrateError> for AppError {
+    fn from(item: sqlx::migrate::MigrateError) -> Self {
+        AppError::DBError {
+            msg: item.to_string(),
+        }
+    }
+}
 
 impl From<sqlx::error::Error> for AppError {
     fn from(item: sqlx::error::Error) -> Self {
@@ -36,6 +82,14 @@ impl From<QueryPayloadError> for AppError {
     }
 }
 
+impl From<csv::Error> for AppError {
+    fn from(item: csv::Error) -> Self {
+        AppError::RequestError {
+            msg: item.to_string(),
+        }
+    }
+}
+
 impl From<ParseIntError> for AppError {
     fn from(item: ParseIntError) -> Self {
         AppError::RequestError {
@@ -52,6 +106,30 @@ impl From<argon2::Error> for AppError {
     }
 }
 
+impl From<totp_rs::TotpUrlError> for AppError {
+    fn from(item: totp_rs::TotpUrlError) -> Self {
+        AppError::CryptError {
+            msg: item.to_string(),
+        }
+    }
+}
+
+impl From<std::time::SystemTimeError> for AppError {
+    fn from(item: std::time::SystemTimeError) -> Self {
+        AppError::Error {
+            msg: item.to_string(),
+        }
+    }
+}
+
+impl From<ldap3::LdapError> for AppError {
+    fn from(item: ldap3::LdapError) -> Self {
+        AppError::AuthError {
+            msg: format!("LDAP: {item}"),
+        }
+    }
+}
+
 impl From<ToStrError> for AppError {
     fn from(item: ToStrError) -> Self {
         AppError::CryptError {
@@ -68,12 +146,42 @@ impl From<uuid::Error> for AppError {
     }
 }
 
+impl From<lettre::error::Error> for AppError {
+    fn from(item: lettre::error::Error) -> Self {
+        AppError::MailError {
+            msg: item.to_string(),
+        }
+    }
+}
+
+impl From<lettre::address::AddressError> for AppError {
+    fn from(item: lettre::address::AddressError) -> Self {
+        AppError::MailError {
+            msg: item.to_string(),
+        }
+    }
+}
+
+impl From<lettre::transport::smtp::Error> for AppError {
+    fn from(item: lettre::transport::smtp::Error) -> Self {
+        AppError::MailError {
+            msg: item.to_string(),
+        }
+    }
+}
+
 pub fn db_error(msg: &str) -> AppError {
     AppError::DBError {
         msg: msg.to_owned(),
     }
 }
 
+pub fn crypt_error(msg: &str) -> AppError {
+    AppError::CryptError {
+        msg: msg.to_owned(),
+    }
+}
+
 pub fn bad_request(msg: &str) -> AppError {
     AppError::RequestError {
         msg: msg.to_owned(),
@@ -0,0 +1,228 @@
+use ldap3::{ldap_escape, Ldap, LdapConnAsync, Scope, SearchEntry};
+use sqlx::PgConnection;
+
+use crate::config::Config;
+use crate::data::models::{dummy_password_check, UserModel, LOCAL_AUTH_PROVIDER};
+use crate::data::user_data::{create_user, get_user_by_name, update_user};
+use crate::error::{bad_request, Result};
+use crate::util::generate_token;
+
+pub const LDAP_AUTH_PROVIDER: &str = "ldap";
+
+/// A way to check a username and password
+pub enum AuthProvider {
+    /// Argon2 hashes stored in the users table
+    Local,
+    Ldap(LdapProvider),
+}
+
+pub struct LdapProvider {
+    url: String,
+    base_dn: String,
+    user_filter: String,
+    bind_dn: Option<String>,
+    bind_password: Option<String>,
+    admin_group: Option<String>,
+    email_attribute: String,
+}
+
+/// The configured providers, tried in order until one accepts the credentials
+pub struct AuthProviders(Vec<AuthProvider>);
+
+impl AuthProviders {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let providers = config
+            .auth_providers
+            .iter()
+            .map(|name| match name.trim() {
+                LOCAL_AUTH_PROVIDER => Ok(AuthProvider::Local),
+                LDAP_AUTH_PROVIDER => Ok(AuthProvider::Ldap(LdapProvider::from_config(config)?)),
+                other => Err(bad_request(&format!("Unknown auth provider '{other}'"))),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if providers.is_empty() {
+            return Err(bad_request("At least one auth provider is required"));
+        }
+        Ok(Self(providers))
+    }
+
+    /// Returns the user if any provider accepts the credentials.
+    /// A provider that fails, e.g. an unreachable directory, is logged and skipped.
+    pub async fn authenticate(
+        &self,
+        conn: &mut PgConnection,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<UserModel>> {
+        for provider in &self.0 {
+            match provider.authenticate(conn, username, password).await {
+                Ok(Some(user)) => return Ok(Some(user)),
+                Ok(None) => {}
+                Err(e) => log::warn!("{} login of {username} failed: {e}", provider.name()),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl AuthProvider {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Local => LOCAL_AUTH_PROVIDER,
+            Self::Ldap(_) => LDAP_AUTH_PROVIDER,
+        }
+    }
+
+    pub async fn authenticate(
+        &self,
+        conn: &mut PgConnection,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<UserModel>> {
+        match self {
+            Self::Local => match get_user_by_name(conn, username).await? {
+                Some(user) if user.has_local_password().is_ok() => {
+                    match user.check_password(password)? {
+                        true => Ok(Some(user)),
+                        false => Ok(None),
+                    }
+                }
+                // ldap and oidc accounts only have a random password nobody knows
+                _ => {
+                    dummy_password_check(password);
+                    Ok(None)
+                }
+            },
+            Self::Ldap(ldap) => ldap.authenticate(conn, username, password).await,
+        }
+    }
+}
+
+impl LdapProvider {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match (&config.ldap_url, &config.ldap_base_dn) {
+            (Some(url), Some(base_dn)) => Ok(Self {
+                url: url.clone(),
+                base_dn: base_dn.clone(),
+                user_filter: config.ldap_user_filter.clone(),
+                bind_dn: config.ldap_bind_dn.clone(),
+                bind_password: config.ldap_bind_password.clone(),
+                admin_group: config.ldap_admin_group.clone(),
+                email_attribute: config.ldap_email_attribute.clone(),
+            }),
+            _ => Err(bad_request("LDAP needs url and base dn to be configured")),
+        }
+    }
+
+    /// Looks the user up, checks the password with a bind as that user and
+    /// creates or updates the matching local account
+    async fn authenticate(
+        &self,
+        conn: &mut PgConnection,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<UserModel>> {
+        // a simple bind without password is an anonymous bind and always succeeds
+        if password.is_empty() {
+            return Ok(None);
+        }
+        let (ldap_conn, mut ldap) = LdapConnAsync::new(&self.url).await?;
+        ldap3::drive!(ldap_conn);
+        if let (Some(dn), Some(pw)) = (&self.bind_dn, &self.bind_password) {
+            ldap.simple_bind(dn, pw).await?.success()?;
+        }
+        let filter = self
+            .user_filter
+            .replace("{username}", &ldap_escape(username));
+        let (mut entries, _) = ldap
+            .search(
+                &self.base_dn,
+                Scope::Subtree,
+                &filter,
+                vec![self.email_attribute.as_str(), "memberOf"],
+            )
+            .await?
+            .success()?;
+        if entries.len() != 1 {
+            ldap.unbind().await?;
+            return Ok(None);
+        }
+        let entry = SearchEntry::construct(entries.remove(0));
+        let is_admin = match &self.admin_group {
+            Some(group) => Some(self.is_member(&mut ldap, &entry, username, group).await?),
+            None => None,
+        };
+        let bound = ldap.simple_bind(&entry.dn, password).await?.rc == 0;
+        ldap.unbind().await?;
+        if !bound {
+            return Ok(None);
+        }
+        let email = entry
+            .attrs
+            .get(&self.email_attribute)
+            .and_then(|v| v.first().cloned());
+
+        match get_user_by_name(conn, username).await? {
+            Some(mut user) if user.auth_provider == LDAP_AUTH_PROVIDER => {
+                let mut changed = false;
+                if email.is_some() && (user.email != email || !user.email_verified) {
+                    user.email = email;
+                    user.email_verified = true;
+                    changed = true;
+                }
+                if let Some(admin) = is_admin.filter(|a| *a != user.is_admin) {
+                    user.is_admin = admin;
+                    changed = true;
+                }
+                if changed {
+                    update_user(conn, &user).await?;
+                }
+                Ok(Some(user))
+            }
+            // a local account with the same name belongs to someone else
+            Some(_) => Ok(None),
+            None => {
+                let mut user = UserModel {
+                    username: username.to_owned(),
+                    email_verified: email.is_some(),
+                    email,
+                    is_admin: is_admin.unwrap_or(false),
+                    enabled: true,
+                    auth_provider: LDAP_AUTH_PROVIDER.to_owned(),
+                    ..Default::default()
+                };
+                // the directory is the only way in, the local password is never handed out
+                user.change_password(&generate_token(32));
+                create_user(conn, &mut user).await?;
+                Ok(Some(user))
+            }
+        }
+    }
+
+    /// Checks memberOf on the user first, then the member attributes of the group itself
+    async fn is_member(
+        &self,
+        ldap: &mut Ldap,
+        entry: &SearchEntry,
+        username: &str,
+        group: &str,
+    ) -> Result<bool> {
+        let member_of = entry
+            .attrs
+            .get("memberOf")
+            .is_some_and(|groups| groups.iter().any(|g| g.eq_ignore_ascii_case(group)));
+        if member_of {
+            return Ok(true);
+        }
+        let dn = ldap_escape(&entry.dn);
+        let filter = format!(
+            "(|(member={dn})(uniqueMember={dn})(memberUid={}))",
+            ldap_escape(username)
+        );
+        let (entries, _) = ldap
+            .search(group, Scope::Base, &filter, vec!["1.1"])
+            .await?
+            .success()?;
+        Ok(!entries.is_empty())
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::export_handler::username_of;
+use super::ical_handler::{handle_task_details, is_done_state};
+use crate::data::models::{ProjectModel, TaskModel};
+use crate::data::project_data::get_columns;
+use crate::data::task_data::get_tasks_by_project;
+use crate::error::Result;
+
+/// Heading for the tasks that aren't in any column
+const NO_COLUMN: &str = "No column";
+
+/// What the text exports show of a task
+struct BoardTask {
+    task: TaskModel,
+    done: bool,
+    labels: Vec<String>,
+    assignee: Option<String>,
+}
+
+/// A column's name, `None` for the tasks without one, with its tasks and their depth
+type BoardColumn<'a> = (Option<&'a str>, Vec<(usize, &'a BoardTask)>);
+
+/// The tasks of a project by column, subtasks below their parent
+struct Board {
+    /// Column ids in board order, `None` last for the tasks without one
+    order: Vec<Option<Uuid>>,
+    column_names: HashMap<Uuid, String>,
+    tasks: HashMap<Uuid, BoardTask>,
+    /// Top level tasks of each column, by title
+    roots: HashMap<Option<Uuid>, Vec<Uuid>>,
+    children: HashMap<Uuid, Vec<Uuid>>,
+}
+
+impl Board {
+    async fn load(conn: &mut PgConnection, project_id: Uuid) -> Result<Self> {
+        let columns = get_columns(conn, project_id).await?;
+        let mut tasks = get_tasks_by_project(conn, project_id).await?;
+        tasks.sort_by_key(|t| (t.title.to_lowercase(), t.id));
+        let details = handle_task_details(conn, &tasks).await?;
+        let ids: HashSet<_> = tasks.iter().map(|t| t.id).collect();
+        let parents: HashMap<_, _> = tasks
+            .iter()
+            .filter_map(|t| Some((t.id, t.parent_id.filter(|p| ids.contains(p))?)))
+            .collect();
+
+        let mut board = Board {
+            order: columns.iter().map(|c| Some(c.id)).collect(),
+            column_names: columns.into_iter().map(|c| (c.id, c.name)).collect(),
+            tasks: HashMap::new(),
+            roots: HashMap::new(),
+            children: HashMap::new(),
+        };
+        let mut usernames = HashMap::new();
+        for (task, details) in tasks.into_iter().zip(details) {
+            let column = task
+                .column_id
+                .filter(|c| board.column_names.contains_key(c));
+            match parents.get(&task.id) {
+                Some(parent) => board.children.entry(*parent).or_default().push(task.id),
+                None => board.roots.entry(column).or_default().push(task.id),
+            }
+            let assignee = username_of(conn, &mut usernames, task.assignee_id).await?;
+            board.tasks.insert(
+                task.id,
+                BoardTask {
+                    task,
+                    done: details.state.as_deref().is_some_and(is_done_state),
+                    labels: details.labels,
+                    assignee,
+                },
+            );
+        }
+        if board.roots.contains_key(&None) {
+            board.order.push(None);
+        }
+        Ok(board)
+    }
+
+    fn column_name(&self, column_id: Option<Uuid>) -> Option<&str> {
+        column_id
+            .and_then(|id| self.column_names.get(&id))
+            .map(String::as_str)
+    }
+
+    /// Columns in order with their tasks, each followed by its subtasks
+    fn columns(&self) -> Vec<BoardColumn<'_>> {
+        let mut seen = HashSet::new();
+        self.order
+            .iter()
+            .map(|column| {
+                let mut tasks = Vec::new();
+                for id in self.roots.get(column).into_iter().flatten() {
+                    self.walk(*id, 0, &mut seen, &mut tasks);
+                }
+                (self.column_name(*column), tasks)
+            })
+            .collect()
+    }
+
+    fn walk<'a>(
+        &'a self,
+        id: Uuid,
+        depth: usize,
+        seen: &mut HashSet<Uuid>,
+        tasks: &mut Vec<(usize, &'a BoardTask)>,
+    ) {
+        if !seen.insert(id) {
+            return;
+        }
+        tasks.push((depth, &self.tasks[&id]));
+        for child in self.children.get(&id).into_iter().flatten() {
+            self.walk(*child, depth + 1, seen, tasks);
+        }
+    }
+}
+
+/// The board as markdown, a heading per column with the tasks as a checklist
+pub async fn handle_export_markdown(
+    conn: &mut PgConnection,
+    project: &ProjectModel,
+) -> Result<String> {
+    let board = Board::load(conn, project.id).await?;
+    let mut md = format!("# {}\n", escape_markdown(&project.name));
+    for (column, tasks) in board.columns() {
+        md.push_str(&format!(
+            "\n## {}\n",
+            escape_markdown(column.unwrap_or(NO_COLUMN))
+        ));
+        if !tasks.is_empty() {
+            md.push('\n');
+        }
+        for (depth, t) in tasks {
+            md.push_str(&"  ".repeat(depth));
+            md.push_str(if t.done { "- [x] " } else { "- [ ] " });
+            md.push_str(&escape_markdown(&t.task.title));
+            for label in &t.labels {
+                // code spans can't escape their delimiter
+                md.push_str(&format!(" `{}`", label.replace('`', "'")));
+            }
+            if let Some(assignee) = &t.assignee {
+                md.push_str(&format!(" @{}", escape_markdown(assignee)));
+            }
+            if let Some(deadline) = t.task.deadline {
+                md.push_str(&format!(" (due {})", deadline.format("%Y-%m-%d")));
+            }
+            md.push('\n');
+        }
+    }
+    Ok(md)
+}
+
+/// The board in todo.txt format, labels as +tags and the column as @context. The format has
+/// no nesting, subtasks follow their parent.
+pub async fn handle_export_todo_txt(conn: &mut PgConnection, project_id: Uuid) -> Result<String> {
+    let board = Board::load(conn, project_id).await?;
+    let mut txt = String::new();
+    for (_, tasks) in board.columns() {
+        for (_, t) in tasks {
+            if t.done {
+                txt.push_str("x ");
+            }
+            txt.push_str(
+                &t.task
+                    .title
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+            for label in &t.labels {
+                txt.push_str(&format!(" +{}", todo_tag(label)));
+            }
+            if let Some(column) = board.column_name(t.task.column_id) {
+                txt.push_str(&format!(" @{}", todo_tag(column)));
+            }
+            if let Some(assignee) = &t.assignee {
+                txt.push_str(&format!(" assignee:{}", todo_tag(assignee)));
+            }
+            if let Some(deadline) = t.task.deadline {
+                txt.push_str(&format!(" due:{}", deadline.format("%Y-%m-%d")));
+            }
+            txt.push('\n');
+        }
+    }
+    Ok(txt)
+}
+
+/// Backslash escapes the characters markdown could read as formatting, titles stay on one line
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\r' | '\n' => escaped.push(' '),
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Tags and contexts end at whitespace
+fn todo_tag(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
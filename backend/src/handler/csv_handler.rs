@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use csv::StringRecord;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::export_handler::{resolve_user, username_of};
+use super::task_handler::{handle_set_task_labels, handle_validate_task_references};
+use crate::data::models::{TaskModel, UserModel};
+use crate::data::project_data::{get_columns, get_labels, get_states};
+use crate::data::task_data::{
+    add_task_label, create_task, get_task, get_task_labels, get_tasks_by_project, update_task,
+};
+use crate::error::{bad_request, not_found, AppError, Result};
+use crate::messages::csv_messages::{CsvColumn, CsvImportMessage, CsvRowErrorMessage};
+
+/// Longest title the database takes
+const TITLE_LENGTH: usize = 128;
+/// Separates the labels within their cell
+const LABEL_SEPARATOR: char = ';';
+/// Spreadsheets run cells starting with these as formulas
+const FORMULA_PREFIXES: [char; 6] = ['=', '+', '-', '@', '\t', '\r'];
+
+/// Quotes a cell a spreadsheet would run as a formula
+fn escape_formula(value: String) -> String {
+    match value.starts_with(FORMULA_PREFIXES) {
+        true => format!("'{value}"),
+        false => value,
+    }
+}
+
+/// Undoes `escape_formula`
+fn unescape_formula(value: &str) -> &str {
+    match value.strip_prefix('\'') {
+        Some(rest) if rest.starts_with(FORMULA_PREFIXES) => rest,
+        _ => value,
+    }
+}
+
+/// The tasks of the project as csv with the given columns, references by name
+pub async fn handle_export_csv(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+    columns: &[CsvColumn],
+) -> Result<Vec<u8>> {
+    let column_names: HashMap<Uuid, String> = get_columns(conn, project_id)
+        .await?
+        .into_iter()
+        .map(|c| (c.id, c.name))
+        .collect();
+    let state_names: HashMap<Uuid, String> = get_states(conn, project_id)
+        .await?
+        .into_iter()
+        .map(|s| (s.id, s.name))
+        .collect();
+    let mut usernames = HashMap::new();
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(columns.iter().map(|c| c.name()))?;
+    for task in get_tasks_by_project(conn, project_id).await? {
+        let mut record = Vec::with_capacity(columns.len());
+        for column in columns {
+            let name_of = |names: &HashMap<Uuid, String>, id: Option<Uuid>| {
+                id.and_then(|id| names.get(&id).cloned())
+                    .unwrap_or_default()
+            };
+            record.push(match column {
+                CsvColumn::Id => task.id.to_string(),
+                CsvColumn::Title => task.title.clone(),
+                CsvColumn::Description => task.description.clone().unwrap_or_default(),
+                CsvColumn::Column => name_of(&column_names, task.column_id),
+                CsvColumn::State => name_of(&state_names, task.state_id),
+                CsvColumn::Labels => {
+                    let mut labels: Vec<_> = get_task_labels(conn, task.id)
+                        .await?
+                        .into_iter()
+                        .map(|l| l.name)
+                        .collect();
+                    labels.sort();
+                    labels.join(&format!("{LABEL_SEPARATOR} "))
+                }
+                CsvColumn::Assignee => username_of(conn, &mut usernames, task.assignee_id)
+                    .await?
+                    .unwrap_or_default(),
+                CsvColumn::Deadline => task
+                    .deadline
+                    .map(|d| d.to_rfc3339_opts(SecondsFormat::Secs, true))
+                    .unwrap_or_default(),
+                CsvColumn::Estimation => task.estimation.map(|e| e.to_string()).unwrap_or_default(),
+                CsvColumn::Parent => task.parent_id.map(|p| p.to_string()).unwrap_or_default(),
+            });
+        }
+        writer.write_record(record.into_iter().map(escape_formula))?;
+    }
+    Ok(writer.into_inner().map_err(|e| e.into_error())?)
+}
+
+/// Names of the project's columns, states and labels, matched ignoring case
+struct ProjectNames {
+    columns: HashMap<String, Uuid>,
+    states: HashMap<String, Uuid>,
+    labels: HashMap<String, Uuid>,
+    users: HashMap<String, Option<Uuid>>,
+}
+
+/// Creates a task for every row without an id and updates the task of the others, changing
+/// only the columns in the file. Rows with errors are reported and skipped, run it inside a
+/// transaction and only commit if the report is applied.
+pub async fn handle_import_csv(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    project_id: Uuid,
+    data: &[u8],
+    dry_run: bool,
+) -> Result<CsvImportMessage> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(data);
+    let header = reader
+        .headers()?
+        .iter()
+        .map(CsvColumn::parse)
+        .collect::<Result<Vec<_>>>()?;
+    let lower = |name: String| name.to_lowercase();
+    let mut names = ProjectNames {
+        columns: get_columns(conn, project_id)
+            .await?
+            .into_iter()
+            .map(|c| (lower(c.name), c.id))
+            .collect(),
+        states: get_states(conn, project_id)
+            .await?
+            .into_iter()
+            .map(|s| (lower(s.name), s.id))
+            .collect(),
+        labels: get_labels(conn, project_id)
+            .await?
+            .into_iter()
+            .map(|l| (lower(l.name), l.id))
+            .collect(),
+        users: HashMap::new(),
+    };
+
+    let mut report = CsvImportMessage {
+        dry_run,
+        ..Default::default()
+    };
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map(|p| p.line()).unwrap_or_default();
+        match import_row(conn, user, project_id, &header, &record, &mut names).await {
+            Ok(true) => report.created += 1,
+            Ok(false) => report.updated += 1,
+            Err(e @ (AppError::RequestError { .. } | AppError::NotFound { .. })) => {
+                report.errors.push(CsvRowErrorMessage {
+                    line,
+                    msg: e.to_string(),
+                })
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    report.applied = !dry_run && report.errors.is_empty();
+    Ok(report)
+}
+
+/// Returns true if the row created a task
+async fn import_row(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    project_id: Uuid,
+    header: &[CsvColumn],
+    record: &StringRecord,
+    names: &mut ProjectNames,
+) -> Result<bool> {
+    let id = header
+        .iter()
+        .position(|c| *c == CsvColumn::Id)
+        .and_then(|i| record.get(i))
+        .filter(|id| !id.is_empty());
+    let mut task = match id {
+        Some(id) => {
+            let id = parse_id(id)?;
+            get_task(conn, id)
+                .await?
+                .filter(|t| t.project_id == project_id)
+                .ok_or_else(|| not_found(&format!("Task {id} not found in the project")))?
+        }
+        None => TaskModel {
+            project_id,
+            creator_id: Some(user.id),
+            ..Default::default()
+        },
+    };
+    let created = task.id.is_nil();
+
+    let mut labels = None;
+    for (column, value) in header.iter().zip(record.iter()) {
+        let value = unescape_formula(value);
+        let empty = value.is_empty();
+        match column {
+            CsvColumn::Id => {}
+            CsvColumn::Title => {
+                if empty {
+                    return Err(bad_request("Title is empty"));
+                }
+                if value.chars().count() > TITLE_LENGTH {
+                    return Err(bad_request(&format!(
+                        "Title is longer than {TITLE_LENGTH} characters"
+                    )));
+                }
+                task.title = value.to_owned();
+            }
+            CsvColumn::Description => task.description = (!empty).then(|| value.to_owned()),
+            CsvColumn::Column => task.column_id = by_name(&names.columns, value, "column")?,
+            CsvColumn::State => task.state_id = by_name(&names.states, value, "state")?,
+            CsvColumn::Labels => {
+                let mut ids = value
+                    .split(LABEL_SEPARATOR)
+                    .filter_map(|l| by_name(&names.labels, l.trim(), "label").transpose())
+                    .collect::<Result<Vec<_>>>()?;
+                ids.sort();
+                ids.dedup();
+                labels = Some(ids);
+            }
+            CsvColumn::Assignee => {
+                task.assignee_id = match empty {
+                    true => None,
+                    false => Some(
+                        resolve_user(conn, &mut names.users, Some(value.to_owned()))
+                            .await?
+                            .ok_or_else(|| bad_request(&format!("Unknown user '{value}'")))?,
+                    ),
+                }
+            }
+            CsvColumn::Deadline => {
+                task.deadline = match empty {
+                    true => None,
+                    false => Some(parse_deadline(value)?),
+                }
+            }
+            CsvColumn::Estimation => {
+                task.estimation = match empty {
+                    true => None,
+                    false => Some(value.parse().map_err(|_| {
+                        bad_request(&format!("Estimation '{value}' is not a number"))
+                    })?),
+                }
+            }
+            CsvColumn::Parent => {
+                task.parent_id = match empty {
+                    true => None,
+                    false => Some(parse_id(value)?),
+                }
+            }
+        }
+    }
+    if created && task.title.is_empty() {
+        return Err(bad_request("New tasks need a title"));
+    }
+
+    handle_validate_task_references(conn, &task, labels.as_deref().unwrap_or_default()).await?;
+    if created {
+        create_task(conn, &mut task).await?;
+        for label in labels.unwrap_or_default() {
+            add_task_label(conn, task.id, label).await?;
+        }
+    } else {
+        update_task(conn, &mut task).await?;
+        if let Some(labels) = labels {
+            handle_set_task_labels(conn, task.id, &labels).await?;
+        }
+    }
+    Ok(created)
+}
+
+fn by_name(names: &HashMap<String, Uuid>, value: &str, kind: &str) -> Result<Option<Uuid>> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    names
+        .get(&value.to_lowercase())
+        .copied()
+        .map(Some)
+        .ok_or_else(|| bad_request(&format!("Unknown {kind} '{value}'")))
+}
+
+fn parse_id(value: &str) -> Result<Uuid> {
+    Uuid::parse_str(value).map_err(|_| bad_request(&format!("Invalid id '{value}'")))
+}
+
+/// RFC 3339 or a plain date, which means midnight UTC
+fn parse_deadline(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(deadline) = DateTime::parse_from_rfc3339(value) {
+        return Ok(deadline.to_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|d| d.and_time(chrono::NaiveTime::MIN).and_utc())
+        .map_err(|_| bad_request(&format!("Deadline '{value}' is not a date")))
+}
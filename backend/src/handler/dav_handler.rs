@@ -0,0 +1,616 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::ical_handler::{
+    handle_task_details, is_done_state, parse_vtodo, task_uid, write_task, IcalWriter,
+};
+use super::project_handler::handle_project_access;
+use super::task_handler::{handle_set_task_labels, handle_validate_task_references};
+use crate::data::dav_data::{create_dav_task, get_dav_tasks, get_task_stamps};
+use crate::data::models::{
+    DavTaskModel, LabelModel, Permissions, ProjectModel, StateModel, TaskModel, UserModel,
+};
+use crate::data::project_data::{
+    create_label, create_state, get_labels, get_member_projects, get_states,
+};
+use crate::data::task_data::{
+    add_task_label, create_task, get_task, get_tasks_by_project, update_task,
+};
+use crate::error::{bad_request, Result};
+use crate::messages::feed_messages::FeedEntry;
+use crate::util::hash_token;
+
+/// Where the caldav tree starts
+pub const DAV_ROOT: &str = "/dav";
+const DAV_NS: &str = "DAV:";
+const CALDAV_NS: &str = "urn:ietf:params:xml:ns:caldav";
+/// Everything but the unreserved characters of a path segment
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+const CS_NS: &str = "http://calendarserver.org/ns/";
+/// Longest title the database takes
+const TITLE_LENGTH: usize = 128;
+/// State completed tasks get in projects without a done state
+const DONE_STATE: &str = "Done";
+/// Properties of a PROPFIND without a list, the ones a resource doesn't have are left out
+const ALL_PROPS: [(&str, &str); 6] = [
+    (DAV_NS, "resourcetype"),
+    (DAV_NS, "displayname"),
+    (DAV_NS, "getetag"),
+    (DAV_NS, "getcontenttype"),
+    (CS_NS, "getctag"),
+    (CALDAV_NS, "supported-calendar-component-set"),
+];
+
+/// The resources of the caldav tree
+pub enum DavPath {
+    /// The principal of the user
+    Root,
+    /// Holds a calendar for every project
+    Home,
+    Calendar(Uuid),
+    /// A task, by its resource name
+    Object(Uuid, String),
+}
+
+/// A task as calendar object
+pub struct DavObject {
+    pub task: TaskModel,
+    pub name: String,
+    pub etag: String,
+    pub data: String,
+}
+
+/// Resource names and UIDs clients gave the tasks they created, the others go by their id
+pub struct DavNames {
+    tasks: HashMap<Uuid, DavTaskModel>,
+}
+
+impl DavNames {
+    pub async fn load(conn: &mut PgConnection, project_id: Uuid) -> Result<Self> {
+        let tasks = get_dav_tasks(conn, project_id)
+            .await?
+            .into_iter()
+            .map(|t| (t.task_id, t))
+            .collect();
+        Ok(Self { tasks })
+    }
+
+    pub fn name(&self, task_id: Uuid) -> String {
+        match self.tasks.get(&task_id) {
+            Some(t) => t.name.clone(),
+            None => format!("{task_id}.ics"),
+        }
+    }
+
+    pub fn uid(&self, task_id: Uuid) -> String {
+        match self.tasks.get(&task_id) {
+            Some(t) => t.uid.clone(),
+            None => task_uid(task_id),
+        }
+    }
+
+    pub fn task_by_name(&self, name: &str) -> Option<Uuid> {
+        if let Some(t) = self.tasks.values().find(|t| t.name == name) {
+            return Some(t.task_id);
+        }
+        name.strip_suffix(".ics")
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .filter(|id| !self.tasks.contains_key(id))
+    }
+
+    pub fn task_by_uid(&self, uid: &str) -> Option<Uuid> {
+        if let Some(t) = self.tasks.values().find(|t| t.uid == uid) {
+            return Some(t.task_id);
+        }
+        uid.strip_suffix("@foxboard")
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .filter(|id| !self.tasks.contains_key(id))
+    }
+}
+
+pub fn calendar_href(project_id: Uuid) -> String {
+    format!("{DAV_ROOT}/projects/{project_id}/")
+}
+
+fn object_href(project_id: Uuid, name: &str) -> String {
+    format!(
+        "{}{}",
+        calendar_href(project_id),
+        utf8_percent_encode(name, SEGMENT)
+    )
+}
+
+/// Renders the tasks as calendar objects with a single VTODO each
+pub async fn handle_dav_objects(
+    conn: &mut PgConnection,
+    names: &DavNames,
+    tasks: Vec<TaskModel>,
+) -> Result<Vec<DavObject>> {
+    let ids: Vec<Uuid> = tasks.iter().map(|t| t.id).collect();
+    let stamps = get_task_stamps(conn, &ids).await?;
+    let details = handle_task_details(conn, &tasks).await?;
+    let mut objects = Vec::with_capacity(tasks.len());
+    for (task, mut details) in tasks.into_iter().zip(details) {
+        details.uid = names.uid(task.id);
+        details.parent_uid = task.parent_id.map(|p| names.uid(p));
+        // the last change, so the object and with it the etag stay the same until the next one
+        let stamp = stamps.get(&task.id).copied().unwrap_or_else(Utc::now);
+        let mut ical = IcalWriter::default();
+        ical.begin_calendar();
+        write_task(&mut ical, FeedEntry::Todo, &task, &details, stamp);
+        ical.end("VCALENDAR");
+        let data = ical.finish();
+        objects.push(DavObject {
+            name: names.name(task.id),
+            etag: format!("\"{}\"", hash_token(&data)),
+            data,
+            task,
+        });
+    }
+    Ok(objects)
+}
+
+/// The calendar object with that name, if the project has it
+pub async fn handle_dav_object(
+    conn: &mut PgConnection,
+    names: &DavNames,
+    project_id: Uuid,
+    name: &str,
+) -> Result<Option<DavObject>> {
+    let Some(task_id) = names.task_by_name(name) else {
+        return Ok(None);
+    };
+    let Some(task) = get_task(conn, task_id)
+        .await?
+        .filter(|t| t.project_id == project_id)
+    else {
+        return Ok(None);
+    };
+    Ok(handle_dav_objects(conn, names, vec![task]).await?.pop())
+}
+
+/// Creates or updates the task behind the resource from the VTODO, run it inside a
+/// transaction. Columns, assignees and estimations aren't part of it and stay as they are.
+pub async fn handle_dav_put(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    names: &DavNames,
+    project_id: Uuid,
+    name: &str,
+    existing: Option<TaskModel>,
+    body: &str,
+) -> Result<()> {
+    let todo = parse_vtodo(body)?;
+    let created = existing.is_none();
+    let mut task = existing.unwrap_or_else(|| TaskModel {
+        project_id,
+        creator_id: Some(user.id),
+        ..Default::default()
+    });
+    task.title = todo.summary.trim().chars().take(TITLE_LENGTH).collect();
+    if task.title.is_empty() {
+        return Err(bad_request("The VTODO needs a SUMMARY"));
+    }
+    task.description = todo.description;
+    task.deadline = todo.due;
+    task.parent_id = todo
+        .parent_uid
+        .as_deref()
+        .and_then(|uid| names.task_by_uid(uid));
+
+    // apps only know done or not, other states are kept while the task isn't done
+    let states = get_states(conn, project_id).await?;
+    let done = |id: Uuid| states.iter().any(|s| s.id == id && is_done_state(&s.name));
+    match (todo.completed, task.state_id) {
+        (true, Some(id)) if done(id) => {}
+        (true, _) => {
+            task.state_id = match states.iter().find(|s| is_done_state(&s.name)) {
+                Some(state) => Some(state.id),
+                None => {
+                    let mut state = StateModel {
+                        name: DONE_STATE.to_owned(),
+                        project_id,
+                        ..Default::default()
+                    };
+                    create_state(conn, &mut state).await?;
+                    Some(state.id)
+                }
+            }
+        }
+        (false, Some(id)) if done(id) => task.state_id = None,
+        (false, _) => {}
+    }
+
+    // categories become labels, new ones are created
+    let mut labels: HashMap<String, Uuid> = get_labels(conn, project_id)
+        .await?
+        .into_iter()
+        .map(|l| (l.name.to_lowercase(), l.id))
+        .collect();
+    let mut label_ids = Vec::new();
+    for category in &todo.categories {
+        let id = match labels.get(&category.to_lowercase()) {
+            Some(id) => *id,
+            None => {
+                let mut label = LabelModel {
+                    name: category.chars().take(64).collect(),
+                    project_id,
+                    ..Default::default()
+                };
+                create_label(conn, &mut label).await?;
+                labels.insert(category.to_lowercase(), label.id);
+                label.id
+            }
+        };
+        if !label_ids.contains(&id) {
+            label_ids.push(id);
+        }
+    }
+
+    handle_validate_task_references(conn, &task, &label_ids).await?;
+    if created {
+        create_task(conn, &mut task).await?;
+        for label in &label_ids {
+            add_task_label(conn, task.id, *label).await?;
+        }
+        let uid = match todo.uid.is_empty() {
+            true => task_uid(task.id),
+            false => todo.uid,
+        };
+        create_dav_task(
+            conn,
+            &DavTaskModel {
+                task_id: task.id,
+                project_id,
+                name: name.to_owned(),
+                uid,
+            },
+        )
+        .await?;
+    } else {
+        update_task(conn, &mut task).await?;
+        handle_set_task_labels(conn, task.id, &label_ids).await?;
+    }
+    Ok(())
+}
+
+/// A qualified xml name
+type PropName = (String, String);
+
+/// The properties asked for, `None` for all of them
+fn requested_props(root: roxmltree::Node) -> Option<Vec<PropName>> {
+    let prop = root
+        .children()
+        .find(|n| n.tag_name().name() == "prop" && n.tag_name().namespace() == Some(DAV_NS))?;
+    Some(
+        prop.children()
+            .filter(|n| n.is_element())
+            .map(|n| {
+                (
+                    n.tag_name().namespace().unwrap_or_default().to_owned(),
+                    n.tag_name().name().to_owned(),
+                )
+            })
+            .collect(),
+    )
+}
+
+fn parse_xml(body: &str) -> Result<roxmltree::Document<'_>> {
+    roxmltree::Document::parse(body).map_err(|e| bad_request(&format!("Invalid xml: {e}")))
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Writes the element with our prefixes, or declaring the namespace of foreign ones
+fn element((ns, name): &PropName, inner: &str) -> String {
+    let (prefix, declare) = match ns.as_str() {
+        DAV_NS => ("d", String::new()),
+        CALDAV_NS => ("c", String::new()),
+        CS_NS => ("cs", String::new()),
+        _ => ("x", format!(" xmlns:x=\"{}\"", escape_xml(ns))),
+    };
+    match inner.is_empty() {
+        true => format!("<{prefix}:{name}{declare}/>"),
+        false => format!("<{prefix}:{name}{declare}>{inner}</{prefix}:{name}>"),
+    }
+}
+
+/// A resource in a multistatus
+enum DavResource<'a> {
+    Root,
+    Home,
+    Calendar {
+        project: &'a ProjectModel,
+        write: bool,
+        ctag: String,
+    },
+    Object {
+        object: &'a DavObject,
+        write: bool,
+    },
+}
+
+impl DavResource<'_> {
+    fn href(&self) -> String {
+        match self {
+            Self::Root => format!("{DAV_ROOT}/"),
+            Self::Home => format!("{DAV_ROOT}/projects/"),
+            Self::Calendar { project, .. } => calendar_href(project.id),
+            Self::Object { object, .. } => object_href(object.task.project_id, &object.name),
+        }
+    }
+
+    /// The inner xml of the property, if the resource has it
+    fn prop(&self, user: &UserModel, (ns, name): &PropName) -> Option<String> {
+        let href = |path: String| format!("<d:href>{}</d:href>", escape_xml(&path));
+        let privileges = |write: bool| {
+            let mut privileges = vec!["read"];
+            if write {
+                privileges.extend(["write", "write-content", "bind", "unbind"]);
+            }
+            privileges
+                .iter()
+                .map(|p| format!("<d:privilege><d:{p}/></d:privilege>"))
+                .collect::<String>()
+        };
+        match (ns.as_str(), name.as_str(), self) {
+            (DAV_NS, "resourcetype", Self::Root) => {
+                Some("<d:collection/><d:principal/>".to_owned())
+            }
+            (DAV_NS, "resourcetype", Self::Home) => Some("<d:collection/>".to_owned()),
+            (DAV_NS, "resourcetype", Self::Calendar { .. }) => {
+                Some("<d:collection/><c:calendar/>".to_owned())
+            }
+            (DAV_NS, "resourcetype", Self::Object { .. }) => Some(String::new()),
+            (DAV_NS, "displayname", Self::Root) => Some(escape_xml(&user.username)),
+            (DAV_NS, "displayname", Self::Home) => Some("Projects".to_owned()),
+            (DAV_NS, "displayname", Self::Calendar { project, .. }) => {
+                Some(escape_xml(&project.name))
+            }
+            (DAV_NS, "current-user-principal", _) | (DAV_NS, "principal-URL", Self::Root) => {
+                Some(href(format!("{DAV_ROOT}/")))
+            }
+            (CALDAV_NS, "calendar-home-set", _) => Some(href(format!("{DAV_ROOT}/projects/"))),
+            (DAV_NS, "current-user-privilege-set", Self::Root | Self::Home) => {
+                Some(privileges(false))
+            }
+            (
+                DAV_NS,
+                "current-user-privilege-set",
+                Self::Calendar { write, .. } | Self::Object { write, .. },
+            ) => Some(privileges(*write)),
+            (CALDAV_NS, "supported-calendar-component-set", Self::Calendar { .. }) => {
+                Some("<c:comp name=\"VTODO\"/>".to_owned())
+            }
+            (DAV_NS, "supported-report-set", Self::Calendar { .. }) => Some(
+                ["calendar-query", "calendar-multiget"]
+                    .iter()
+                    .map(|r| format!("<d:supported-report><d:report><c:{r}/></d:report></d:supported-report>"))
+                    .collect(),
+            ),
+            (CS_NS, "getctag", Self::Calendar { ctag, .. }) => Some(escape_xml(ctag)),
+            (DAV_NS, "getetag", Self::Object { object, .. }) => Some(escape_xml(&object.etag)),
+            (DAV_NS, "getcontenttype", Self::Object { .. }) => {
+                Some("text/calendar; charset=utf-8; component=VTODO".to_owned())
+            }
+            (CALDAV_NS, "calendar-data", Self::Object { object, .. }) => {
+                Some(escape_xml(&object.data))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Collects the responses of a 207 multistatus
+struct Multistatus {
+    out: String,
+}
+
+impl Multistatus {
+    fn new() -> Self {
+        Self {
+            out: format!(
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<d:multistatus xmlns:d=\"{DAV_NS}\" xmlns:c=\"{CALDAV_NS}\" xmlns:cs=\"{CS_NS}\">"
+            ),
+        }
+    }
+
+    fn response(&mut self, user: &UserModel, resource: &DavResource, props: Option<&[PropName]>) {
+        let all: Vec<PropName> = ALL_PROPS
+            .iter()
+            .map(|(ns, name)| (ns.to_string(), name.to_string()))
+            .collect();
+        let mut found = String::new();
+        let mut missing = String::new();
+        for prop in props.unwrap_or(&all) {
+            match resource.prop(user, prop) {
+                Some(inner) => found.push_str(&element(prop, &inner)),
+                None if props.is_some() => missing.push_str(&element(prop, "")),
+                None => {}
+            }
+        }
+        self.out.push_str(&format!(
+            "<d:response><d:href>{}</d:href>",
+            escape_xml(&resource.href())
+        ));
+        for (props, status) in [(found, "200 OK"), (missing, "404 Not Found")] {
+            if !props.is_empty() {
+                self.out.push_str(&format!(
+                    "<d:propstat><d:prop>{props}</d:prop><d:status>HTTP/1.1 {status}</d:status></d:propstat>"
+                ));
+            }
+        }
+        self.out.push_str("</d:response>");
+    }
+
+    fn not_found(&mut self, href: &str) {
+        self.out.push_str(&format!(
+            "<d:response><d:href>{}</d:href><d:status>HTTP/1.1 404 Not Found</d:status></d:response>",
+            escape_xml(href)
+        ));
+    }
+
+    fn finish(mut self) -> String {
+        self.out.push_str("</d:multistatus>");
+        self.out
+    }
+}
+
+/// Changes whenever an object of the calendar does
+fn ctag(objects: &[DavObject]) -> String {
+    let mut etags: Vec<_> = objects.iter().map(|o| o.etag.as_str()).collect();
+    etags.sort();
+    hash_token(&etags.concat())
+}
+
+/// The project's calendar objects and whether the user may change them
+async fn load_calendar(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    project_id: Uuid,
+) -> Result<(ProjectModel, bool, Vec<DavObject>)> {
+    let (project, perms) =
+        handle_project_access(conn, user, project_id, Permissions::Reader).await?;
+    let names = DavNames::load(conn, project.id).await?;
+    let tasks = get_tasks_by_project(conn, project.id).await?;
+    let objects = handle_dav_objects(conn, &names, tasks).await?;
+    let write = perms >= Permissions::Editor && project.archived.is_none();
+    Ok((project, write, objects))
+}
+
+/// Answers a PROPFIND, `children` for a depth of 1. Returns the multistatus, `None` if the
+/// object doesn't exist.
+pub async fn handle_propfind(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    path: &DavPath,
+    children: bool,
+    body: &str,
+) -> Result<Option<String>> {
+    let props = match body.trim().is_empty() {
+        true => None,
+        false => requested_props(parse_xml(body)?.root_element()),
+    };
+    let props = props.as_deref();
+    let mut status = Multistatus::new();
+    match path {
+        DavPath::Root => {
+            status.response(user, &DavResource::Root, props);
+            if children {
+                status.response(user, &DavResource::Home, props);
+            }
+        }
+        DavPath::Home => {
+            status.response(user, &DavResource::Home, props);
+            if children {
+                for project in get_member_projects(conn, user.id).await? {
+                    let (project, write, objects) = load_calendar(conn, user, project.id).await?;
+                    let ctag = ctag(&objects);
+                    let resource = DavResource::Calendar {
+                        project: &project,
+                        write,
+                        ctag,
+                    };
+                    status.response(user, &resource, props);
+                }
+            }
+        }
+        DavPath::Calendar(project_id) => {
+            let (project, write, objects) = load_calendar(conn, user, *project_id).await?;
+            let resource = DavResource::Calendar {
+                project: &project,
+                write,
+                ctag: ctag(&objects),
+            };
+            status.response(user, &resource, props);
+            if children {
+                for object in &objects {
+                    status.response(user, &DavResource::Object { object, write }, props);
+                }
+            }
+        }
+        DavPath::Object(project_id, name) => {
+            let (project, perms) =
+                handle_project_access(conn, user, *project_id, Permissions::Reader).await?;
+            let names = DavNames::load(conn, *project_id).await?;
+            let Some(object) = handle_dav_object(conn, &names, *project_id, name).await? else {
+                return Ok(None);
+            };
+            let write = perms >= Permissions::Editor && project.archived.is_none();
+            status.response(
+                user,
+                &DavResource::Object {
+                    object: &object,
+                    write,
+                },
+                props,
+            );
+        }
+    }
+    Ok(Some(status.finish()))
+}
+
+/// Answers a calendar-query, with every object since all of them are VTODOs, or a
+/// calendar-multiget
+pub async fn handle_report(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    project_id: Uuid,
+    body: &str,
+) -> Result<String> {
+    let doc = parse_xml(body)?;
+    let root = doc.root_element();
+    let props = requested_props(root);
+    let (project, write, objects) = load_calendar(conn, user, project_id).await?;
+    let mut status = Multistatus::new();
+    match (root.tag_name().namespace(), root.tag_name().name()) {
+        (Some(CALDAV_NS), "calendar-query") => {
+            for object in &objects {
+                status.response(
+                    user,
+                    &DavResource::Object { object, write },
+                    props.as_deref(),
+                );
+            }
+        }
+        (Some(CALDAV_NS), "calendar-multiget") => {
+            let base = calendar_href(project.id);
+            let hrefs = root
+                .children()
+                .filter(|n| {
+                    n.tag_name().name() == "href" && n.tag_name().namespace() == Some(DAV_NS)
+                })
+                .filter_map(|n| n.text());
+            for href in hrefs {
+                let name = href
+                    .trim()
+                    .strip_prefix(&base)
+                    .map(|n| percent_decode_str(n).decode_utf8_lossy().into_owned());
+                match name.and_then(|n| objects.iter().find(|o| o.name == n)) {
+                    Some(object) => status.response(
+                        user,
+                        &DavResource::Object { object, write },
+                        props.as_deref(),
+                    ),
+                    None => status.not_found(href.trim()),
+                }
+            }
+        }
+        (_, report) => return Err(bad_request(&format!("Unsupported report {report}"))),
+    }
+    Ok(status.finish())
+}
@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet};
+
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::data::models::{
+    LabelModel, Permissions, ProjectColumnModel, ProjectModel, StateModel, TaskModel, UserModel,
+};
+use crate::data::project_data::{
+    add_project_member, create_column, create_label, create_project, create_state, get_columns,
+    get_labels, get_project, get_project_members, get_states,
+};
+use crate::data::task_data::{add_task_label, create_task, get_task_labels, get_tasks_by_project};
+use crate::data::user_data::{get_user_by_id, get_user_by_name};
+use crate::error::{bad_request, not_found, Result};
+use crate::messages::export_messages::{
+    ExportColumnMessage, ExportLabelMessage, ExportMemberMessage, ExportStateMessage,
+    ExportTaskMessage, MemberRole, ProjectExportMessage, EXPORT_VERSION, MIN_EXPORT_VERSION,
+};
+
+pub async fn handle_export_project(
+    conn: &mut PgConnection,
+    project_id: Uuid,
+) -> Result<ProjectExportMessage> {
+    let project = get_project(conn, project_id)
+        .await?
+        .ok_or_else(|| not_found("Project not found"))?;
+    let columns = get_columns(conn, project_id).await?;
+    let labels = get_labels(conn, project_id).await?;
+    let states = get_states(conn, project_id).await?;
+
+    let project_tasks = get_tasks_by_project(conn, project_id).await?;
+    // references into the trash are left out, the import wouldn't find them
+    let exported: HashSet<Uuid> = columns
+        .iter()
+        .map(|c| c.id)
+        .chain(states.iter().map(|s| s.id))
+        .chain(project_tasks.iter().map(|t| t.id))
+        .collect();
+    let keep = |id: Option<Uuid>| id.filter(|i| exported.contains(i));
+
+    let mut usernames: HashMap<Uuid, Option<String>> = HashMap::new();
+    let mut tasks = Vec::new();
+    for task in project_tasks {
+        let assignee = username_of(conn, &mut usernames, task.assignee_id).await?;
+        let creator = username_of(conn, &mut usernames, task.creator_id).await?;
+        let task_labels = get_task_labels(conn, task.id).await?;
+        tasks.push(ExportTaskMessage {
+            id: task.id,
+            title: task.title,
+            description: task.description,
+            column_id: keep(task.column_id),
+            state_id: keep(task.state_id),
+            parent_id: keep(task.parent_id),
+            assignee,
+            creator,
+            deadline: task.deadline.map(|x| x.timestamp_millis()),
+            estimation: task.estimation,
+            task_type: task.task_type,
+            labels: task_labels.into_iter().map(|l| l.id).collect(),
+        });
+    }
+
+    Ok(ProjectExportMessage {
+        version: EXPORT_VERSION,
+        name: project.name,
+        public: project.public,
+        columns: columns
+            .into_iter()
+            .map(|c| ExportColumnMessage {
+                id: c.id,
+                name: c.name,
+                card_limit: c.card_limit,
+                index: c.index,
+            })
+            .collect(),
+        labels: labels
+            .into_iter()
+            .map(|l| ExportLabelMessage {
+                id: l.id,
+                name: l.name,
+            })
+            .collect(),
+        states: states
+            .into_iter()
+            .map(|s| ExportStateMessage {
+                id: s.id,
+                name: s.name,
+            })
+            .collect(),
+        tasks,
+        members: get_project_members(conn, project_id)
+            .await?
+            .into_iter()
+            .filter_map(|(username, perms)| {
+                MemberRole::from_permissions(perms)
+                    .map(|role| ExportMemberMessage { username, role })
+            })
+            .collect(),
+    })
+}
+
+/// Recreates an exported project with new ids, run it inside a transaction.
+/// Users that don't exist here are dropped from the tasks and members, members are editors at
+/// most. Callers drop the members unless the user may hand out access to them.
+pub async fn handle_import_project(
+    conn: &mut PgConnection,
+    owner: &UserModel,
+    msg: ProjectExportMessage,
+) -> Result<ProjectModel> {
+    Ok(handle_import_project_ids(conn, owner, msg).await?.0)
+}
+
+/// Which new id each id of an imported document got, per kind
+#[derive(Default)]
+pub struct ImportedIds {
+    pub columns: HashMap<Uuid, Uuid>,
+    pub labels: HashMap<Uuid, Uuid>,
+    pub states: HashMap<Uuid, Uuid>,
+    pub tasks: HashMap<Uuid, Uuid>,
+}
+
+/// Remembers the new id, ids have to be unique within their kind
+fn add_id(ids: &mut HashMap<Uuid, Uuid>, kind: &str, old: Uuid, new: Uuid) -> Result<()> {
+    if ids.insert(old, new).is_some() {
+        return Err(bad_request(&format!("Duplicate {kind} id {old}")));
+    }
+    Ok(())
+}
+
+/// The new id of a reference to something of `kind` in the document
+fn remap(ids: &HashMap<Uuid, Uuid>, kind: &str, id: Option<Uuid>) -> Result<Option<Uuid>> {
+    id.map(|i| {
+        ids.get(&i)
+            .copied()
+            .ok_or_else(|| bad_request(&format!("Unknown {kind} {i}")))
+    })
+    .transpose()
+}
+
+/// Like [`handle_import_project`], also returns which new id each id of the document got
+pub async fn handle_import_project_ids(
+    conn: &mut PgConnection,
+    owner: &UserModel,
+    msg: ProjectExportMessage,
+) -> Result<(ProjectModel, ImportedIds)> {
+    if !(MIN_EXPORT_VERSION..=EXPORT_VERSION).contains(&msg.version) {
+        return Err(bad_request(&format!(
+            "Unsupported export version {}, expected {MIN_EXPORT_VERSION} to {EXPORT_VERSION}",
+            msg.version
+        )));
+    }
+    let mut project = ProjectModel {
+        name: msg.name,
+        public: msg.public,
+        owner_id: owner.id,
+        ..Default::default()
+    };
+    create_project(conn, &mut project).await?;
+
+    let mut ids = ImportedIds::default();
+    for c in msg.columns {
+        let mut column = ProjectColumnModel {
+            name: c.name,
+            card_limit: c.card_limit,
+            project_id: project.id,
+            index: c.index,
+            ..Default::default()
+        };
+        create_column(conn, &mut column).await?;
+        add_id(&mut ids.columns, "column", c.id, column.id)?;
+    }
+    for l in msg.labels {
+        let mut label = LabelModel {
+            name: l.name,
+            project_id: project.id,
+            ..Default::default()
+        };
+        create_label(conn, &mut label).await?;
+        add_id(&mut ids.labels, "label", l.id, label.id)?;
+    }
+    for s in msg.states {
+        let mut state = StateModel {
+            name: s.name,
+            project_id: project.id,
+            ..Default::default()
+        };
+        create_state(conn, &mut state).await?;
+        add_id(&mut ids.states, "state", s.id, state.id)?;
+    }
+
+    let mut user_ids: HashMap<String, Option<Uuid>> = HashMap::new();
+    let mut pending = msg.tasks;
+    // parents have to exist before their subtasks, so insert in rounds
+    while !pending.is_empty() {
+        let (ready, rest): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|t| t.parent_id.is_none_or(|p| ids.tasks.contains_key(&p)));
+        if ready.is_empty() {
+            return Err(bad_request("Tasks reference unknown or circular parents"));
+        }
+        for t in ready {
+            let assignee_id = resolve_user(conn, &mut user_ids, t.assignee).await?;
+            let creator_id = resolve_user(conn, &mut user_ids, t.creator).await?;
+            let mut task = TaskModel {
+                title: t.title,
+                description: t.description,
+                project_id: project.id,
+                column_id: remap(&ids.columns, "column", t.column_id)?,
+                state_id: remap(&ids.states, "state", t.state_id)?,
+                parent_id: remap(&ids.tasks, "task", t.parent_id)?,
+                assignee_id,
+                creator_id: creator_id.or(Some(owner.id)),
+                deadline: t
+                    .deadline
+                    .and_then(chrono::DateTime::<chrono::Utc>::from_timestamp_millis),
+                estimation: t.estimation,
+                task_type: t.task_type,
+                ..Default::default()
+            };
+            let mut labels = Vec::new();
+            for label in t.labels {
+                labels.extend(remap(&ids.labels, "label", Some(label))?);
+            }
+            labels.sort();
+            labels.dedup();
+            create_task(conn, &mut task).await?;
+            for label_id in labels {
+                add_task_label(conn, task.id, label_id).await?;
+            }
+            add_id(&mut ids.tasks, "task", t.id, task.id)?;
+        }
+        pending = rest;
+    }
+
+    for m in msg.members {
+        if let Some(user_id) = resolve_user(conn, &mut user_ids, Some(m.username)).await? {
+            if user_id != owner.id {
+                // ownership isn't handed out by a document
+                let role = Permissions::from(m.role).min(Permissions::Editor);
+                add_project_member(conn, project.id, user_id, role).await?;
+            }
+        }
+    }
+    Ok((project, ids))
+}
+
+/// Username of the user id, looked up once per id
+pub async fn username_of(
+    conn: &mut PgConnection,
+    cache: &mut HashMap<Uuid, Option<String>>,
+    id: Option<Uuid>,
+) -> Result<Option<String>> {
+    let Some(id) = id else {
+        return Ok(None);
+    };
+    if let Some(name) = cache.get(&id) {
+        return Ok(name.clone());
+    }
+    let name = get_user_by_id(conn, id).await?.map(|u| u.username);
+    cache.insert(id, name.clone());
+    Ok(name)
+}
+
+/// Id of the user with that name, looked up once per name
+pub async fn resolve_user(
+    conn: &mut PgConnection,
+    cache: &mut HashMap<String, Option<Uuid>>,
+    username: Option<String>,
+) -> Result<Option<Uuid>> {
+    let Some(username) = username else {
+        return Ok(None);
+    };
+    if let Some(id) = cache.get(&username) {
+        return Ok(*id);
+    }
+    let id = get_user_by_name(conn, &username).await?.map(|u| u.id);
+    cache.insert(username, id);
+    Ok(id)
+}
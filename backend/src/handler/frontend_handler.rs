@@ -0,0 +1,75 @@
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+use crate::config::Config;
+
+/// Where the built frontend is served from
+pub enum Frontend {
+    Dir(PathBuf),
+    #[cfg(feature = "embed-frontend")]
+    Embedded,
+}
+
+#[cfg(feature = "embed-frontend")]
+#[derive(rust_embed::RustEmbed)]
+#[folder = "../frontend/dist"]
+#[allow_missing = true]
+struct EmbeddedAssets;
+
+pub struct Asset {
+    pub content: Cow<'static, [u8]>,
+    pub content_type: String,
+    /// Vite puts hashed files into assets/, their content never changes
+    pub immutable: bool,
+}
+
+impl Frontend {
+    /// The configured directory wins over the embedded files
+    pub fn from_config(config: &Config) -> Option<Self> {
+        if let Some(dir) = &config.frontend_dir {
+            return Some(Self::Dir(dir.clone()));
+        }
+        #[cfg(feature = "embed-frontend")]
+        if EmbeddedAssets::get("index.html").is_some() {
+            return Some(Self::Embedded);
+        }
+        None
+    }
+
+    fn read(&self, path: &str) -> Option<Cow<'static, [u8]>> {
+        match self {
+            Self::Dir(dir) => {
+                let mut file = dir.clone();
+                for segment in path.split('/').filter(|s| !s.is_empty()) {
+                    if segment == ".." || segment.contains('\\') {
+                        return None;
+                    }
+                    file.push(segment);
+                }
+                std::fs::read(file).ok().map(Cow::Owned)
+            }
+            #[cfg(feature = "embed-frontend")]
+            Self::Embedded => EmbeddedAssets::get(path).map(|f| f.data),
+        }
+    }
+
+    /// Finds the file for a request path, paths without a file extension are
+    /// routes of the app and get index.html
+    pub fn asset(&self, path: &str) -> Option<Asset> {
+        let path = path.trim_start_matches('/');
+        let (path, content) = match self.read(path).filter(|_| !path.is_empty()) {
+            Some(content) => (path, content),
+            None if !path.rsplit('/').next().unwrap_or_default().contains('.') => {
+                ("index.html", self.read("index.html")?)
+            }
+            None => return None,
+        };
+        Some(Asset {
+            content,
+            content_type: mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .to_string(),
+            immutable: path.starts_with("assets/"),
+        })
+    }
+}
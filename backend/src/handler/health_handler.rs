@@ -0,0 +1,42 @@
+use sqlx::{Pool, Postgres};
+
+use crate::data::system_data::{get_latest_migration, ping};
+use crate::data::task_data::count_tasks_by_project;
+use crate::data::user_data::count_sessions;
+use crate::data::MIGRATOR;
+use crate::error::{AppError, Result};
+use crate::metrics::Metrics;
+
+/// Ready when the database answers and every embedded migration is applied
+pub async fn handle_readiness(pool: &Pool<Postgres>) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    ping(&mut conn).await?;
+    let expected = MIGRATOR
+        .iter()
+        .filter(|m| !m.migration_type.is_down_migration())
+        .map(|m| m.version)
+        .max();
+    if get_latest_migration(&mut conn).await? < expected {
+        return Err(AppError::DBError {
+            msg: "Migrations pending".to_owned(),
+        });
+    }
+    Ok(())
+}
+
+/// Refreshes the gauges read from the database and renders all metrics
+pub async fn handle_metrics(pool: &Pool<Postgres>, metrics: &Metrics) -> Result<String> {
+    metrics.pool_connections.set(pool.size() as i64);
+    metrics.pool_idle.set(pool.num_idle() as i64);
+    let mut conn = pool.acquire().await?;
+    metrics.sessions.set(count_sessions(&mut conn).await?);
+    // deleted projects would otherwise keep their last count
+    metrics.project_tasks.reset();
+    for (project_id, count) in count_tasks_by_project(&mut conn).await? {
+        metrics
+            .project_tasks
+            .with_label_values(&[&project_id.to_string()])
+            .set(count);
+    }
+    Ok(metrics.render())
+}
@@ -0,0 +1,363 @@
+use std::collections::{hash_map::Entry, HashMap};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::data::models::TaskModel;
+use crate::data::project_data::get_states;
+use crate::data::task_data::get_task_labels;
+use crate::error::{bad_request, Result};
+use crate::messages::feed_messages::FeedEntry;
+
+const PRODID: &str = "-//foxboard//foxboard//EN";
+/// Longest content line in octets, longer ones are folded
+const LINE_LENGTH: usize = 75;
+/// States that mark a task as done, matched ignoring case
+const DONE_STATES: [&str; 6] = [
+    "done",
+    "complete",
+    "completed",
+    "closed",
+    "finished",
+    "resolved",
+];
+
+/// Writes iCalendar (RFC 5545) content lines
+#[derive(Default)]
+pub struct IcalWriter {
+    out: String,
+}
+
+impl IcalWriter {
+    /// Opens the VCALENDAR with the properties every calendar has
+    pub fn begin_calendar(&mut self) {
+        self.begin("VCALENDAR");
+        self.line("VERSION", "2.0");
+        self.line("PRODID", PRODID);
+        self.line("CALSCALE", "GREGORIAN");
+    }
+
+    pub fn begin(&mut self, component: &str) {
+        self.line("BEGIN", component);
+    }
+
+    pub fn end(&mut self, component: &str) {
+        self.line("END", component);
+    }
+
+    /// A property with its value written as is
+    pub fn line(&mut self, name: &str, value: &str) {
+        let mut len = 0;
+        for c in name.chars().chain([':']).chain(value.chars()) {
+            if len + c.len_utf8() > LINE_LENGTH {
+                self.out.push_str("\r\n ");
+                len = 1;
+            }
+            self.out.push(c);
+            len += c.len_utf8();
+        }
+        self.out.push_str("\r\n");
+    }
+
+    pub fn text(&mut self, name: &str, value: &str) {
+        self.line(name, &escape(value));
+    }
+
+    pub fn time(&mut self, name: &str, time: DateTime<Utc>) {
+        self.line(name, &time.format("%Y%m%dT%H%M%SZ").to_string());
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+/// Escapes a TEXT value
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// What an entry shows besides the task itself
+#[derive(Default)]
+pub struct TaskDetails {
+    pub uid: String,
+    pub parent_uid: Option<String>,
+    pub state: Option<String>,
+    pub labels: Vec<String>,
+}
+
+/// Stays the same for the task, so calendar apps update entries instead of duplicating them
+pub fn task_uid(task_id: Uuid) -> String {
+    format!("{task_id}@foxboard")
+}
+
+pub fn is_done_state(state: &str) -> bool {
+    DONE_STATES.iter().any(|s| s.eq_ignore_ascii_case(state))
+}
+
+/// Writes the task as a VEVENT at its deadline or a VTODO due then
+pub fn write_task(
+    ical: &mut IcalWriter,
+    entry: FeedEntry,
+    task: &TaskModel,
+    details: &TaskDetails,
+    stamp: DateTime<Utc>,
+) {
+    let component = match entry {
+        FeedEntry::Event => "VEVENT",
+        FeedEntry::Todo => "VTODO",
+    };
+    ical.begin(component);
+    ical.text("UID", &details.uid);
+    ical.time("DTSTAMP", stamp);
+    if let Some(deadline) = task.deadline {
+        match entry {
+            FeedEntry::Event => ical.time("DTSTART", deadline),
+            FeedEntry::Todo => ical.time("DUE", deadline),
+        }
+    }
+    ical.text("SUMMARY", &task.title);
+    if let Some(description) = task.description.as_deref().filter(|d| !d.is_empty()) {
+        ical.text("DESCRIPTION", description);
+    }
+    if entry == FeedEntry::Todo {
+        let done = details.state.as_deref().is_some_and(is_done_state);
+        ical.line("STATUS", if done { "COMPLETED" } else { "NEEDS-ACTION" });
+    }
+    if !details.labels.is_empty() {
+        let labels: Vec<_> = details.labels.iter().map(|l| escape(l)).collect();
+        ical.line("CATEGORIES", &labels.join(","));
+    }
+    if let Some(parent) = &details.parent_uid {
+        ical.text("RELATED-TO", parent);
+    }
+    ical.end(component);
+}
+
+/// UIDs, state names and sorted label names of the tasks, in the same order
+pub async fn handle_task_details(
+    conn: &mut PgConnection,
+    tasks: &[TaskModel],
+) -> Result<Vec<TaskDetails>> {
+    let mut states: HashMap<Uuid, HashMap<Uuid, String>> = HashMap::new();
+    let mut details = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        let names = match states.entry(task.project_id) {
+            Entry::Occupied(names) => names.into_mut(),
+            Entry::Vacant(entry) => entry.insert(
+                get_states(conn, task.project_id)
+                    .await?
+                    .into_iter()
+                    .map(|s| (s.id, s.name))
+                    .collect(),
+            ),
+        };
+        let mut labels: Vec<_> = get_task_labels(conn, task.id)
+            .await?
+            .into_iter()
+            .map(|l| l.name)
+            .collect();
+        labels.sort();
+        details.push(TaskDetails {
+            uid: task_uid(task.id),
+            parent_uid: task.parent_id.map(task_uid),
+            state: task.state_id.and_then(|id| names.get(&id).cloned()),
+            labels,
+        });
+    }
+    Ok(details)
+}
+
+/// A published calendar of the tasks
+pub async fn handle_task_calendar(
+    conn: &mut PgConnection,
+    name: &str,
+    tasks: &[TaskModel],
+    entry: FeedEntry,
+) -> Result<String> {
+    let details = handle_task_details(conn, tasks).await?;
+    let stamp = Utc::now();
+    let mut ical = IcalWriter::default();
+    ical.begin_calendar();
+    ical.line("METHOD", "PUBLISH");
+    ical.text("X-WR-CALNAME", name);
+    for (task, details) in tasks.iter().zip(&details) {
+        write_task(&mut ical, entry, task, details, stamp);
+    }
+    ical.end("VCALENDAR");
+    Ok(ical.finish())
+}
+
+/// The VTODO of a calendar object a client sent
+#[derive(Default, Debug)]
+pub struct VTodo {
+    pub uid: String,
+    pub summary: String,
+    pub description: Option<String>,
+    pub due: Option<DateTime<Utc>>,
+    pub completed: bool,
+    pub categories: Vec<String>,
+    pub parent_uid: Option<String>,
+}
+
+/// Reads the one VTODO of a calendar object, alarms and other components in it are ignored
+pub fn parse_vtodo(ical: &str) -> Result<VTodo> {
+    let unfolded = ical
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+    let mut components: Vec<String> = Vec::new();
+    let mut todo: Option<VTodo> = None;
+    let mut status = None;
+    let mut completed_stamp = false;
+    for line in unfolded.lines().filter(|l| !l.is_empty()) {
+        let (head, value) = split_property(line)
+            .ok_or_else(|| bad_request(&format!("Invalid content line '{line}'")))?;
+        let mut params = head.split(';');
+        let name = params.next().unwrap_or_default().to_ascii_uppercase();
+        match name.as_str() {
+            "BEGIN" => {
+                let component = value.to_ascii_uppercase();
+                if component == "VTODO" {
+                    if todo.is_some() {
+                        return Err(bad_request("Only one VTODO per calendar object"));
+                    }
+                    todo = Some(VTodo::default());
+                }
+                components.push(component);
+                continue;
+            }
+            "END" => {
+                components.pop();
+                continue;
+            }
+            _ => {}
+        }
+        let (Some("VTODO"), Some(todo)) = (components.last().map(String::as_str), todo.as_mut())
+        else {
+            continue;
+        };
+        match name.as_str() {
+            "UID" => todo.uid = unescape(value),
+            "SUMMARY" => todo.summary = unescape(value),
+            "DESCRIPTION" => todo.description = Some(unescape(value)).filter(|d| !d.is_empty()),
+            "DUE" => {
+                let tzid = params.find_map(|p| {
+                    let (key, zone) = p.split_once('=')?;
+                    key.eq_ignore_ascii_case("TZID")
+                        .then(|| zone.trim_matches('"'))
+                });
+                todo.due = Some(parse_time(value, tzid)?)
+            }
+            "STATUS" => status = Some(value.to_ascii_uppercase()),
+            "COMPLETED" => completed_stamp = true,
+            "CATEGORIES" => todo.categories.extend(
+                split_list(value)
+                    .iter()
+                    .map(|c| unescape(c))
+                    .filter(|c| !c.is_empty()),
+            ),
+            "RELATED-TO" => {
+                let parent = params.all(|p| {
+                    !p.to_ascii_uppercase().starts_with("RELTYPE=")
+                        || p.eq_ignore_ascii_case("RELTYPE=PARENT")
+                });
+                if parent {
+                    todo.parent_uid = Some(unescape(value));
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut todo = todo.ok_or_else(|| bad_request("Only VTODO calendar objects are supported"))?;
+    todo.completed = match status {
+        Some(status) => status == "COMPLETED",
+        None => completed_stamp,
+    };
+    Ok(todo)
+}
+
+/// Splits a content line at the colon after the name and parameters, which may quote colons
+fn split_property(line: &str) -> Option<(&str, &str)> {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ':' if !quoted => return Some((&line[..i], &line[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Splits a list value at the commas that aren't escaped
+fn split_list(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            ',' if !escaped => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => escaped = false,
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => {}
+            },
+            _ => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+/// UTC, in the TZID zone or floating, which is taken as UTC, or a date meaning midnight UTC
+fn parse_time(value: &str, tzid: Option<&str>) -> Result<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Ok(date.and_time(NaiveTime::MIN).and_utc());
+    }
+    let (local, utc) = match value.strip_suffix(['Z', 'z']) {
+        Some(local) => (local, true),
+        None => (value, false),
+    };
+    let time = NaiveDateTime::parse_from_str(local, "%Y%m%dT%H%M%S")
+        .map_err(|_| bad_request(&format!("Invalid date '{value}'")))?;
+    let Some(tzid) = tzid.filter(|_| !utc) else {
+        return Ok(time.and_utc());
+    };
+    let zone: Tz = tzid
+        .parse()
+        .map_err(|_| bad_request(&format!("Unknown time zone '{tzid}'")))?;
+    zone.from_local_datetime(&time)
+        .earliest()
+        .map(|t| t.with_timezone(&Utc))
+        .ok_or_else(|| bad_request(&format!("'{value}' doesn't exist in {tzid}")))
+}
@@ -0,0 +1,32 @@
+use lettre::{
+    message::header::ContentType, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::{config::Config, error::Result};
+
+/// Sends a plain text mail via the configured smtp server.
+/// If no smtp host is configured the mail is only logged.
+pub async fn send_mail(config: &Config, to: &str, subject: &str, body: String) -> Result<()> {
+    let host = match &config.smtp_host {
+        Some(h) => h,
+        None => {
+            log::warn!("No smtp host configured, not sending mail '{subject}' to {to}");
+            log::debug!("{body}");
+            return Ok(());
+        }
+    };
+    let mail = Message::builder()
+        .from(config.smtp_from.parse()?)
+        .to(to.parse()?)
+        .subject(subject)
+        .header(ContentType::TEXT_PLAIN)
+        .body(body)?;
+
+    let mut transport = AsyncSmtpTransport::<Tokio1Executor>::relay(host)?;
+    if let (Some(user), Some(password)) = (&config.smtp_user, &config.smtp_password) {
+        transport = transport.credentials(Credentials::new(user.clone(), password.clone()));
+    }
+    transport.build().send(mail).await?;
+    Ok(())
+}
@@ -1,2 +1,15 @@
+pub mod auth_provider;
+pub mod board_handler;
+pub mod csv_handler;
+pub mod dav_handler;
+pub mod export_handler;
+pub mod frontend_handler;
+pub mod health_handler;
+pub mod ical_handler;
+pub mod mail_handler;
+pub mod oidc_handler;
 pub mod project_handler;
+pub mod task_handler;
+pub mod template_handler;
+pub mod trello_handler;
 pub mod user_handler;
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use actix_web::HttpRequest;
+use openidconnect::core::{
+    CoreAuthenticationFlow, CoreClient, CoreGenderClaim, CoreProviderMetadata,
+};
+use openidconnect::{
+    reqwest, AdditionalClaims, AuthorizationCode, ClientId, ClientSecret, CsrfToken,
+    EndpointMaybeSet, EndpointNotSet, EndpointSet, IssuerUrl, Nonce, OAuth2TokenResponse,
+    PkceCodeChallenge, PkceCodeVerifier, RedirectUrl, Scope, UserInfoClaims,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+
+use crate::config::Config;
+use crate::data::models::{OidcLoginModel, UserModel};
+use crate::data::user_data::{
+    check_username_available, create_oidc_login, create_user, get_unlinked_user_by_email,
+    get_user_by_email, get_user_by_oidc_subject, link_oidc_subject, update_user, use_oidc_login,
+};
+use crate::error::{auth_error, not_found, AppError, Result};
+use crate::handler::user_handler::handle_login;
+use crate::messages::user_messages::LoginTokenMessage;
+use crate::util::{generate_token, hash_token};
+
+type OidcClient = CoreClient<
+    EndpointSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
+>;
+
+/// Whatever else the provider puts into the userinfo response, used for the admin mapping
+#[derive(Debug, Deserialize, Serialize)]
+struct ExtraClaims(HashMap<String, serde_json::Value>);
+impl AdditionalClaims for ExtraClaims {}
+
+pub struct OidcProvider {
+    client: OidcClient,
+    http: reqwest::Client,
+    admin_claim: Option<(String, String)>,
+    auto_provision: bool,
+    link_email: bool,
+}
+
+fn oidc_error<E: std::fmt::Display>(e: E) -> AppError {
+    auth_error(&format!("OIDC: {e}"))
+}
+
+impl OidcProvider {
+    /// Discovers the provider if oidc is configured
+    pub async fn from_config(config: &Config) -> Result<Option<Self>> {
+        let (issuer, client_id, redirect) = match (
+            &config.oidc_issuer,
+            &config.oidc_client_id,
+            &config.oidc_redirect_url,
+        ) {
+            (Some(i), Some(c), Some(r)) => (i, c, r),
+            (None, _, _) => return Ok(None),
+            _ => {
+                return Err(auth_error(
+                    "OIDC needs issuer, client id and redirect url to be configured",
+                ))
+            }
+        };
+        let http = reqwest::ClientBuilder::new()
+            // following redirects would allow SSRF through the provider metadata
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(oidc_error)?;
+        let metadata = CoreProviderMetadata::discover_async(
+            IssuerUrl::new(issuer.clone()).map_err(oidc_error)?,
+            &http,
+        )
+        .await
+        .map_err(oidc_error)?;
+        let client = CoreClient::from_provider_metadata(
+            metadata,
+            ClientId::new(client_id.clone()),
+            config.oidc_client_secret.clone().map(ClientSecret::new),
+        )
+        .set_redirect_uri(RedirectUrl::new(redirect.clone()).map_err(oidc_error)?);
+        let admin_claim = match (&config.oidc_admin_claim, &config.oidc_admin_value) {
+            (Some(c), Some(v)) => Some((c.clone(), v.clone())),
+            _ => None,
+        };
+        Ok(Some(Self {
+            client,
+            http,
+            admin_claim,
+            auto_provision: config.oidc_auto_provision,
+            link_email: config.oidc_link_email,
+        }))
+    }
+
+    /// Whether the configured admin claim is, or contains, the configured value
+    fn is_admin(&self, claims: &ExtraClaims) -> Option<bool> {
+        let (claim, value) = self.admin_claim.as_ref()?;
+        Some(match claims.0.get(claim) {
+            Some(serde_json::Value::String(s)) => s == value,
+            Some(serde_json::Value::Array(a)) => a.iter().any(|x| x.as_str() == Some(value)),
+            Some(serde_json::Value::Bool(b)) => *b && value == "true",
+            _ => false,
+        })
+    }
+}
+
+/// Starts an authorization code flow with PKCE and returns the url to send the user to
+pub async fn handle_oidc_start(pool: &Pool<Postgres>, provider: &OidcProvider) -> Result<String> {
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (url, state, nonce) = provider
+        .client
+        .authorize_url(
+            CoreAuthenticationFlow::AuthorizationCode,
+            CsrfToken::new_random,
+            Nonce::new_random,
+        )
+        .add_scope(Scope::new("email".to_owned()))
+        .add_scope(Scope::new("profile".to_owned()))
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+    create_oidc_login(
+        &mut *pool.acquire().await?,
+        &OidcLoginModel::new(
+            hash_token(state.secret()),
+            pkce_verifier.secret().clone(),
+            nonce.secret().clone(),
+        ),
+    )
+    .await?;
+    Ok(url.to_string())
+}
+
+/// Finishes the flow: exchanges the code, verifies the id token and logs in the user linked
+/// to the subject. Without one an account with the verified email is linked if configured,
+/// or a new one is created.
+pub async fn handle_oidc_callback(
+    pool: &Pool<Postgres>,
+    provider: &OidcProvider,
+    code: &str,
+    state: &str,
+    req: &HttpRequest,
+) -> Result<LoginTokenMessage> {
+    let login = use_oidc_login(&mut *pool.acquire().await?, &hash_token(state))
+        .await?
+        .ok_or_else(|| auth_error("OIDC login invalid or expired"))?;
+    let token_response = provider
+        .client
+        .exchange_code(AuthorizationCode::new(code.to_owned()))
+        .map_err(oidc_error)?
+        .set_pkce_verifier(PkceCodeVerifier::new(login.pkce_verifier))
+        .request_async(&provider.http)
+        .await
+        .map_err(oidc_error)?;
+    let id_token = token_response
+        .extra_fields()
+        .id_token()
+        .ok_or_else(|| auth_error("OIDC: provider returned no id token"))?;
+    let claims = id_token
+        .claims(
+            &provider.client.id_token_verifier(),
+            &Nonce::new(login.nonce),
+        )
+        .map_err(oidc_error)?;
+    let subject = claims.subject().to_string();
+    let email = claims
+        .email()
+        .filter(|_| claims.email_verified().unwrap_or(false))
+        .map(|e| e.to_string());
+    let preferred_username = claims.preferred_username().map(|u| u.to_string());
+
+    let is_admin = match &provider.admin_claim {
+        Some(_) => {
+            let user_info: UserInfoClaims<ExtraClaims, CoreGenderClaim> = provider
+                .client
+                .user_info(
+                    token_response.access_token().to_owned(),
+                    Some(claims.subject().clone()),
+                )
+                .map_err(oidc_error)?
+                .request_async(&provider.http)
+                .await
+                .map_err(oidc_error)?;
+            provider.is_admin(user_info.additional_claims())
+        }
+        None => None,
+    };
+
+    let mut t = pool.begin().await?;
+    let mut user = match get_user_by_oidc_subject(&mut t, &subject).await? {
+        Some(u) => u,
+        None => {
+            let linked = match &email {
+                Some(e) if provider.link_email => get_unlinked_user_by_email(&mut t, e).await?,
+                _ => None,
+            };
+            match linked {
+                Some(u) => u,
+                None if provider.auto_provision => {
+                    let base = preferred_username
+                        .or_else(|| email.clone())
+                        .unwrap_or_else(|| format!("oidc-{subject}"));
+                    let base: String = base.chars().take(38).collect();
+                    let mut username = base.clone();
+                    while !check_username_available(&mut t, &username).await? {
+                        username = format!("{base}-{}", generate_token(4));
+                    }
+                    // the address may belong to an account the subject isn't linked to
+                    let email = match &email {
+                        Some(e) if get_user_by_email(&mut t, e).await?.is_none() => email.clone(),
+                        _ => None,
+                    };
+                    let mut new_user = UserModel {
+                        username,
+                        email_verified: email.is_some(),
+                        email,
+                        enabled: true,
+                        auth_provider: "oidc".to_owned(),
+                        ..Default::default()
+                    };
+                    // oidc users don't get a usable local password
+                    new_user.change_password(&generate_token(32));
+                    create_user(&mut t, &mut new_user).await?;
+                    new_user
+                }
+                None => return Err(not_found("No account linked to this OIDC user")),
+            }
+        }
+    };
+    link_oidc_subject(&mut t, user.id, &subject).await?;
+    if let Some(admin) = is_admin {
+        if user.is_admin != admin {
+            user.is_admin = admin;
+            update_user(&mut t, &user).await?;
+        }
+    }
+    let msg = handle_login(&mut t, &user, req).await?;
+    t.commit().await?;
+    Ok(msg)
+}
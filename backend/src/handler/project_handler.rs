@@ -1 +1,157 @@
+use std::{future::Future, marker::PhantomData, pin::Pin};
 
+use actix_web::{web, FromRequest};
+use sqlx::{PgConnection, Pool, Postgres};
+use uuid::Uuid;
+
+use super::export_handler::{handle_export_project, handle_import_project};
+use crate::data::models::{AuthenticatedUser, Permissions, ProjectModel, Ressource, UserModel};
+use crate::data::project_data::{get_project, get_project_permission};
+use crate::error::{bad_request, db_error, forbidden, not_found, unauthorized, AppError, Result};
+use crate::messages::project_messages::CloneProjectMessage;
+
+/// Minimum permissions a [`ProjectAccess`] requires
+pub trait Requirement {
+    const PERMISSIONS: Permissions;
+    /// Archived projects are read-only, only a requirement with this set can change them
+    const ARCHIVED: bool = false;
+}
+
+pub struct ReadAccess;
+pub struct EditAccess;
+pub struct OwnerAccess;
+/// Owner access that is also given on archived projects, to unarchive them
+pub struct UnarchiveAccess;
+
+impl Requirement for ReadAccess {
+    const PERMISSIONS: Permissions = Permissions::Reader;
+}
+
+impl Requirement for EditAccess {
+    const PERMISSIONS: Permissions = Permissions::Editor;
+}
+
+impl Requirement for OwnerAccess {
+    const PERMISSIONS: Permissions = Permissions::Owner;
+}
+
+impl Requirement for UnarchiveAccess {
+    const PERMISSIONS: Permissions = Permissions::Owner;
+    const ARCHIVED: bool = true;
+}
+
+/// Effective permissions of the user on the project. Admins may do everything, members have
+/// their role and everyone can read public projects, the highest of these applies.
+pub async fn handle_project_permissions(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    project: &ProjectModel,
+) -> Result<Permissions> {
+    if user.is_admin {
+        return Ok(Permissions::Admin);
+    }
+    let member = get_project_permission(conn, user.id, project.id).await?;
+    Ok(member.max(project.get_permissions(user.id).unwrap_or_default()))
+}
+
+/// Loads the project and checks the user has at least `required` on it. Archived projects only
+/// allow reading.
+pub async fn handle_project_access(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    project_id: Uuid,
+    required: Permissions,
+) -> Result<(ProjectModel, Permissions)> {
+    project_access(conn, user, project_id, required, false).await
+}
+
+async fn project_access(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    project_id: Uuid,
+    required: Permissions,
+    archived: bool,
+) -> Result<(ProjectModel, Permissions)> {
+    let project = get_project(conn, project_id)
+        .await?
+        .ok_or_else(|| not_found("Project not found"))?;
+    let permissions = handle_project_permissions(conn, user, &project).await?;
+    if permissions < required {
+        return Err(unauthorized("Insufficient permissions on project"));
+    }
+    if project.archived.is_some() && required > Permissions::Reader && !archived {
+        return Err(forbidden("Project is archived, unarchive it to change it"));
+    }
+    Ok((project, permissions))
+}
+
+/// Copies the project into a new private one owned by the user, run it inside a transaction
+pub async fn handle_clone_project(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    source: &ProjectModel,
+    msg: CloneProjectMessage,
+) -> Result<ProjectModel> {
+    let mut copy = handle_export_project(conn, source.id).await?;
+    copy.name = msg
+        .name
+        .unwrap_or_else(|| format!("{} (copy)", source.name));
+    copy.public = false;
+    if !msg.tasks {
+        copy.tasks.clear();
+    }
+    if !msg.labels {
+        copy.labels.clear();
+        copy.tasks.iter_mut().for_each(|t| t.labels.clear());
+    }
+    if !msg.states {
+        copy.states.clear();
+        copy.tasks.iter_mut().for_each(|t| t.state_id = None);
+    }
+    if !msg.members {
+        copy.members.clear();
+    }
+    handle_import_project(conn, user, copy).await
+}
+
+/// The authenticated user, allowed at least `R` on the project in the `{project_id}` path segment
+pub struct ProjectAccess<R> {
+    pub user: AuthenticatedUser,
+    pub project: ProjectModel,
+    pub permissions: Permissions,
+    required: PhantomData<R>,
+}
+
+impl<R: Requirement + 'static> FromRequest for ProjectAccess<R> {
+    type Error = AppError;
+
+    type Future = Pin<Box<dyn Future<Output = std::result::Result<Self, Self::Error>>>>;
+
+    fn from_request(
+        req: &actix_web::HttpRequest,
+        payload: &mut actix_web::dev::Payload,
+    ) -> Self::Future {
+        let user = AuthenticatedUser::from_request(req, payload);
+        let pool = req.app_data::<web::Data<Pool<Postgres>>>().cloned();
+        let project_id = req.match_info().get("project_id").map(Uuid::parse_str);
+        Box::pin(async move {
+            let user = user.await?;
+            let pool = pool.ok_or_else(|| db_error("Pool Missing"))?;
+            let project_id = project_id.ok_or_else(|| bad_request("Project id missing"))??;
+            let (project, permissions) = project_access(
+                &mut *pool.acquire().await?,
+                &user,
+                project_id,
+                R::PERMISSIONS,
+                R::ARCHIVED,
+            )
+            .await?;
+            Ok(Self {
+                user,
+                project,
+                permissions,
+                required: PhantomData,
+            })
+        })
+    }
+}
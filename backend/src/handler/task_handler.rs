@@ -0,0 +1,69 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::data::models::TaskModel;
+use crate::data::project_data::{count_project_labels, get_column, get_state};
+use crate::data::task_data::{
+    add_task_label, get_task, get_task_labels, is_subtask_of, remove_task_label,
+};
+use crate::error::{bad_request, Result};
+
+/// Checks that the column, state, parent and labels of the task belong to the task's project.
+/// The labels have to be free of duplicates.
+pub async fn handle_validate_task_references(
+    conn: &mut PgConnection,
+    task: &TaskModel,
+    labels: &[Uuid],
+) -> Result<()> {
+    let project_id = task.project_id;
+    // a column or state the task already has may be in the trash, the task keeps it until then
+    let current = get_task(conn, task.id).await?;
+    let kept = |reference: fn(&TaskModel) -> Option<Uuid>| {
+        current.as_ref().map(reference) == Some(reference(task))
+    };
+    if let Some(column_id) = task.column_id.filter(|_| !kept(|t| t.column_id)) {
+        get_column(conn, project_id, column_id)
+            .await?
+            .ok_or_else(|| bad_request("Column is not part of the project"))?;
+    }
+    if let Some(state_id) = task.state_id.filter(|_| !kept(|t| t.state_id)) {
+        get_state(conn, project_id, state_id)
+            .await?
+            .ok_or_else(|| bad_request("State is not part of the project"))?;
+    }
+    if let Some(parent_id) = task.parent_id {
+        if parent_id == task.id {
+            return Err(bad_request("A task cannot be its own parent"));
+        }
+        get_task(conn, parent_id)
+            .await?
+            .filter(|parent| parent.project_id == project_id)
+            .ok_or_else(|| bad_request("Parent task is not part of the project"))?;
+        if is_subtask_of(conn, parent_id, task.id).await? {
+            return Err(bad_request("Parent task is a subtask of the task"));
+        }
+    }
+    if count_project_labels(conn, project_id, labels).await? != labels.len() as i64 {
+        return Err(bad_request("Label is not part of the project"));
+    }
+    Ok(())
+}
+
+/// Adds and removes labels until the task has exactly `labels`
+pub async fn handle_set_task_labels(
+    conn: &mut PgConnection,
+    task_id: Uuid,
+    labels: &[Uuid],
+) -> Result<()> {
+    let old_labels = get_task_labels(conn, task_id).await?;
+    for new_id in labels
+        .iter()
+        .filter(|x| !old_labels.iter().any(|y| y.id == **x))
+    {
+        add_task_label(conn, task_id, *new_id).await?;
+    }
+    for old_label in old_labels.iter().filter(|x| !labels.contains(&x.id)) {
+        remove_task_label(conn, task_id, old_label.id).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::export_handler::{handle_export_project, handle_import_project};
+use super::project_handler::handle_project_access;
+use crate::data::models::{Permissions, ProjectModel, TemplateModel, UserModel};
+use crate::data::template_data::{create_template, get_template};
+use crate::error::{not_found, Result};
+use crate::messages::export_messages::ProjectExportMessage;
+use crate::messages::template_messages::CreateTemplateMessage;
+
+/// Saves the structure of a project the user can read as a new, unpublished template
+pub async fn handle_create_template(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    msg: CreateTemplateMessage,
+) -> Result<TemplateModel> {
+    handle_project_access(conn, user, msg.project_id, Permissions::Reader).await?;
+    let mut structure = handle_export_project(conn, msg.project_id).await?;
+    structure.name = msg.name.clone();
+    structure.members.clear();
+    if msg.tasks {
+        // whoever starts from the template becomes the creator
+        for task in &mut structure.tasks {
+            task.assignee = None;
+            task.creator = None;
+        }
+    } else {
+        structure.tasks.clear();
+    }
+    let mut template = TemplateModel {
+        name: msg.name,
+        owner_id: Some(user.id),
+        structure: serde_json::to_string(&structure)?,
+        ..Default::default()
+    };
+    create_template(conn, &mut template).await?;
+    Ok(template)
+}
+
+/// Creates a project owned by the user from a template they can see, run it inside a transaction
+pub async fn handle_create_from_template(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    template_id: Uuid,
+    name: String,
+    public: bool,
+) -> Result<ProjectModel> {
+    let template = get_template(conn, template_id, user.id, user.is_admin)
+        .await?
+        .ok_or_else(|| not_found("Template not found"))?;
+    let mut structure: ProjectExportMessage = serde_json::from_str(&template.structure)?;
+    structure.name = name;
+    structure.public = public;
+    handle_import_project(conn, user, structure).await
+}
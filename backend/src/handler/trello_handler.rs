@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+
+use rand::{rngs::OsRng, RngCore};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::export_handler::handle_import_project_ids;
+use crate::data::models::{ProjectModel, UserModel};
+use crate::data::project_data::delete_column;
+use crate::data::task_data::delete_task;
+use crate::data::user_data::get_user_by_name;
+use crate::error::Result;
+use crate::messages::export_messages::{
+    ExportColumnMessage, ExportLabelMessage, ExportMemberMessage, ExportStateMessage,
+    ExportTaskMessage, MemberRole, ProjectExportMessage, EXPORT_VERSION,
+};
+use crate::messages::trello_messages::TrelloBoard;
+
+/// Longest names the database takes
+const NAME_LENGTH: usize = 64;
+const TITLE_LENGTH: usize = 128;
+/// State of cards whose due date is done and of ticked checklist items
+const COMPLETE_STATE: &str = "Complete";
+
+/// Creates a private project owned by `owner` from a Trello board export, run it inside a
+/// transaction. Lists become columns and cards tasks, with checklist items as subtasks.
+/// Archived lists and cards go to the trash. Board members become editors only with
+/// `add_members`, otherwise they are neither added nor assigned. Returns what couldn't be mapped.
+pub async fn handle_import_trello(
+    conn: &mut PgConnection,
+    owner: &UserModel,
+    board: TrelloBoard,
+    add_members: bool,
+) -> Result<(ProjectModel, Vec<String>)> {
+    let mut unmapped = Vec::new();
+    // trello ids are only unique within the board, the document gets its own
+    let mut ids: HashMap<String, Uuid> = HashMap::new();
+
+    let mut lists = board.lists;
+    lists.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+    let mut closed = Vec::new();
+    let mut columns = Vec::new();
+    for (index, list) in lists.into_iter().enumerate() {
+        let id = doc_id(&mut ids, &list.id);
+        if list.closed {
+            closed.push(id);
+        }
+        columns.push(ExportColumnMessage {
+            id,
+            name: fit(&list.name, NAME_LENGTH, "list", &mut unmapped),
+            card_limit: 0,
+            index: index as i32,
+        });
+    }
+
+    let labels = board
+        .labels
+        .into_iter()
+        .map(|l| {
+            // labels without a name are only a color in trello
+            let name = match l.name.is_empty() {
+                true => l.color.unwrap_or_else(|| "label".to_owned()),
+                false => l.name,
+            };
+            ExportLabelMessage {
+                id: doc_id(&mut ids, &l.id),
+                name: fit(&name, NAME_LENGTH, "label", &mut unmapped),
+            }
+        })
+        .collect();
+
+    let mut members = Vec::new();
+    let mut usernames: HashMap<String, String> = HashMap::new();
+    for m in board.members {
+        match get_user_by_name(conn, &m.username).await? {
+            Some(user) if user.id == owner.id => {
+                usernames.insert(m.id, user.username);
+            }
+            Some(user) if add_members => {
+                usernames.insert(m.id, user.username.clone());
+                members.push(ExportMemberMessage {
+                    username: user.username,
+                    role: MemberRole::Editor,
+                });
+            }
+            Some(_) => unmapped.push(format!(
+                "member {}: only admins can add members",
+                m.username
+            )),
+            None => unmapped.push(format!("member {}: no user with that name", m.username)),
+        }
+    }
+
+    let complete = random_id();
+    let mut uses_complete = false;
+    let mut tasks = Vec::new();
+    for card in board.cards {
+        let title = fit(&card.name, TITLE_LENGTH, "card", &mut unmapped);
+        let mut assignees = card.id_members.iter().filter_map(|m| usernames.get(m));
+        let assignee = assignees.next().cloned();
+        if assignees.next().is_some() {
+            unmapped.push(format!(
+                "card '{title}': only the first of its members is assigned"
+            ));
+        }
+        let deadline = match card
+            .due
+            .as_deref()
+            .map(chrono::DateTime::parse_from_rfc3339)
+        {
+            Some(Ok(due)) => Some(due.timestamp_millis()),
+            Some(Err(_)) => {
+                unmapped.push(format!("card '{title}': due date isn't readable"));
+                None
+            }
+            None => None,
+        };
+        if !card.attachments.is_empty() {
+            unmapped.push(format!(
+                "card '{title}': {} attachments aren't imported",
+                card.attachments.len()
+            ));
+        }
+        uses_complete |= card.due_complete;
+        let id = doc_id(&mut ids, &card.id);
+        if card.closed {
+            closed.push(id);
+        }
+        tasks.push(ExportTaskMessage {
+            id,
+            title,
+            description: Some(card.desc).filter(|d| !d.is_empty()),
+            column_id: ids.get(&card.id_list).copied(),
+            state_id: card.due_complete.then_some(complete),
+            parent_id: None,
+            assignee,
+            creator: None,
+            deadline,
+            estimation: None,
+            task_type: 0,
+            labels: card
+                .id_labels
+                .iter()
+                .filter_map(|l| ids.get(l).copied())
+                .collect(),
+        });
+    }
+
+    for checklist in board.checklists {
+        let Some(card) = ids.get(&checklist.id_card).copied() else {
+            unmapped.push(format!(
+                "checklist '{}': its card isn't in the export",
+                checklist.name
+            ));
+            continue;
+        };
+        let mut items = checklist.check_items;
+        items.sort_by(|a, b| a.pos.total_cmp(&b.pos));
+        for item in items {
+            let done = item.state == "complete";
+            uses_complete |= done;
+            tasks.push(ExportTaskMessage {
+                id: random_id(),
+                title: fit(&item.name, TITLE_LENGTH, "checklist item", &mut unmapped),
+                description: None,
+                column_id: None,
+                state_id: done.then_some(complete),
+                parent_id: Some(card),
+                assignee: None,
+                creator: None,
+                deadline: None,
+                estimation: None,
+                task_type: 0,
+                labels: Vec::new(),
+            });
+        }
+    }
+
+    let comments = board
+        .actions
+        .iter()
+        .filter(|a| a.kind == "commentCard")
+        .count();
+    if comments > 0 {
+        unmapped.push(format!("{comments} comments aren't imported"));
+    }
+
+    let name = match board.name.is_empty() {
+        true => "Trello import".to_owned(),
+        false => fit(&board.name, NAME_LENGTH, "board", &mut unmapped),
+    };
+    let doc = ProjectExportMessage {
+        version: EXPORT_VERSION,
+        name,
+        public: false,
+        columns,
+        labels,
+        states: match uses_complete {
+            true => vec![ExportStateMessage {
+                id: complete,
+                name: COMPLETE_STATE.to_owned(),
+            }],
+            false => Vec::new(),
+        },
+        tasks,
+        members,
+    };
+    let (project, new_ids) = handle_import_project_ids(conn, owner, doc).await?;
+    for id in &closed {
+        if let Some(column_id) = new_ids.columns.get(id) {
+            delete_column(conn, project.id, *column_id).await?;
+        } else if let Some(task_id) = new_ids.tasks.get(id) {
+            delete_task(conn, *task_id).await?;
+        }
+    }
+    Ok((project, unmapped))
+}
+
+/// Ids only used within the document, the import gives everything new ones
+fn random_id() -> Uuid {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    uuid::Builder::from_random_bytes(bytes).into_uuid()
+}
+
+fn doc_id(ids: &mut HashMap<String, Uuid>, trello_id: &str) -> Uuid {
+    *ids.entry(trello_id.to_owned()).or_insert_with(random_id)
+}
+
+/// Cuts the text to `max` characters and notes it
+fn fit(text: &str, max: usize, kind: &str, unmapped: &mut Vec<String>) -> String {
+    if text.chars().count() <= max {
+        return text.to_owned();
+    }
+    let short: String = text.chars().take(max).collect();
+    unmapped.push(format!(
+        "{kind} '{short}': name shortened to {max} characters"
+    ));
+    short
+}
@@ -1,9 +1,27 @@
 use actix_web::HttpRequest;
+use rand::{rngs::OsRng, RngCore};
 use sqlx::{Pool, Postgres};
 
-use crate::data::models::UserSessionModel;
-use crate::data::user_data::update_user_session;
-use crate::error::{bad_request, Result};
+use sqlx::PgConnection;
+use totp_rs::Secret;
+use uuid::Uuid;
+
+use crate::data::models::{
+    LoginAttemptKind, LoginChallengeModel, PasswordResetModel, PersonalTokenModel, UserSessionModel,
+};
+use crate::data::user_data::{
+    clear_login_failures, create_invite_code, create_login_challenge, create_password_reset,
+    create_personal_token, create_user_session, delete_recovery_codes,
+    delete_user_sessions_by_user, get_login_lock, get_user_by_email, get_user_by_feed_token,
+    get_user_by_id, record_login_failure, replace_recovery_codes, set_feed_token, update_user,
+    use_login_challenge, use_password_reset, use_personal_token, use_recovery_code, use_totp_step,
+};
+use crate::error::{auth_error, bad_request, not_found, Result};
+use crate::handler::auth_provider::AuthProviders;
+use crate::handler::mail_handler::send_mail;
+use crate::messages::feed_messages::FeedTokenMessage;
+use crate::messages::user_messages::{LoginTokenMessage, PersonalTokenMessage, TotpEnrollMessage};
+use crate::util::{generate_token, hash_token};
 use crate::{
     config::Config,
     data::{
@@ -14,30 +32,103 @@ use crate::{
 };
 
 pub async fn handle_create_initial_admin(config: &Config, pool: &Pool<Postgres>) -> Result<()> {
+    let (Some(admin_user), Some(password)) = (&config.admin_user, &config.admin_initial_password)
+    else {
+        return Err(bad_request(
+            "Admin user and initial password need to be configured",
+        ));
+    };
     let mut conn = pool.acquire().await?;
-    let user = get_user_by_name(&mut conn, &config.admin_user).await?;
+    let user = get_user_by_name(&mut conn, admin_user).await?;
 
     if user.is_none() {
         create_user(
             &mut conn,
-            &mut CreateUserMessage::new(
-                config.admin_user.clone(),
-                config.admin_initial_password.clone(),
-                true,
-            )
-            .into(),
+            &mut CreateUserMessage::new(admin_user.clone(), password.clone(), true).into(),
         )
         .await?;
     }
     Ok(())
 }
 
-pub async fn handle_token_update(
+/// Creates a reset token for the user with the given email and mails it.
+/// Unknown emails are silently ignored so the endpoint can't be used to probe for accounts,
+/// ldap and oidc accounts get a mail that their password is managed elsewhere.
+/// Runs after the request was answered, see `forgot_password_route`.
+pub async fn handle_password_forgot(
+    config: &Config,
+    pool: &Pool<Postgres>,
+    email: &str,
+) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    let user = match get_user_by_email(&mut conn, email).await? {
+        Some(u) => u,
+        None => return Ok(()),
+    };
+    if let Err(e) = user.has_local_password() {
+        return send_mail(
+            config,
+            email,
+            "foxboard password reset",
+            format!(
+                "Hello {},\n\nsomeone requested a password reset for your account.\n{}.\n",
+                user.username, e
+            ),
+        )
+        .await;
+    }
+    let token = generate_token(32);
+    create_password_reset(
+        &mut conn,
+        &PasswordResetModel::new(hash_token(&token), user.id, config.password_reset_ttl),
+    )
+    .await?;
+    let link = format!(
+        "{}/password/reset?token={}",
+        config.public_url.trim_end_matches('/'),
+        token
+    );
+    send_mail(
+        config,
+        email,
+        "foxboard password reset",
+        format!(
+            "Hello {},\n\nsomeone requested a password reset for your account.\nUse the following link within {} minutes to set a new password:\n\n{}\n\nIf this wasn't you, you can ignore this mail.\n",
+            user.username, config.password_reset_ttl, link
+        ),
+    )
+    .await
+}
+
+/// Sets a new password if the token is valid and revokes all sessions of the user
+pub async fn handle_password_reset(
     pool: &Pool<Postgres>,
-    user: &UserModel,
     token: &str,
-    req: &HttpRequest,
+    new_password: &str,
 ) -> Result<()> {
+    let mut t = pool.begin().await?;
+    let user_id = use_password_reset(&mut t, &hash_token(token))
+        .await?
+        .ok_or_else(|| bad_request("Reset token invalid or expired"))?;
+    let mut user = get_user_by_id(&mut t, user_id)
+        .await?
+        .ok_or_else(|| bad_request("Reset token invalid or expired"))?;
+    user.has_local_password()?;
+    user.change_password(new_password);
+    update_user(&mut t, &user).await?;
+    delete_user_sessions_by_user(&mut t, user.id).await?;
+    t.commit().await?;
+    Ok(())
+}
+
+/// Number of recovery codes handed out when totp is enabled
+const RECOVERY_CODE_COUNT: usize = 10;
+
+pub async fn handle_create_session(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    req: &HttpRequest,
+) -> Result<String> {
     let user_agent = req
         .headers()
         .get("User-Agent")
@@ -47,11 +138,314 @@ pub async fn handle_token_update(
         .peer_addr()
         .ok_or(bad_request("IP Address Missing"))?
         .to_string();
-    update_user_session(
-        &mut *pool.acquire().await?,
-        UserSessionModel::new(token, user_agent, ip_addr, user.id),
+    let token = generate_token(64);
+    create_user_session(
+        conn,
+        UserSessionModel::new(&token, user_agent, ip_addr, user.id),
     )
     .await?;
+    Ok(token)
+}
 
+/// Ip of the client, without the port. Behind a trusted proxy it is the last address in
+/// X-Forwarded-For that isn't a trusted proxy itself, earlier ones can be made up by the client.
+fn client_ip(config: &Config, req: &HttpRequest) -> Result<String> {
+    let mut ip = req
+        .peer_addr()
+        .ok_or(bad_request("IP Address Missing"))?
+        .ip();
+    let forwarded = req
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .collect::<Vec<_>>();
+    for hop in forwarded.into_iter().rev() {
+        if !config.trusted_proxies.contains(&ip) {
+            break;
+        }
+        ip = hop
+            .trim()
+            .parse()
+            .map_err(|_| bad_request("X-Forwarded-For invalid"))?;
+    }
+    Ok(ip.to_string())
+}
+
+async fn handle_login_failure(
+    config: &Config,
+    conn: &mut PgConnection,
+    username: &str,
+    ip_addr: &str,
+) -> Result<()> {
+    for (kind, key) in [
+        (LoginAttemptKind::User, username),
+        (LoginAttemptKind::Ip, ip_addr),
+    ] {
+        record_login_failure(
+            &mut *conn,
+            kind,
+            key,
+            config.login_max_attempts,
+            config.login_lockout_seconds,
+        )
+        .await?;
+    }
     Ok(())
 }
+
+/// Checks username and password against the auth providers, with lockout after too many failed attempts
+/// per username and per ip. A successful login only clears the username, the failures of an ip
+/// are forgotten after a day so one valid account doesn't reset the count for guessing others.
+pub async fn handle_password_login(
+    config: &Config,
+    providers: &AuthProviders,
+    conn: &mut PgConnection,
+    username: &str,
+    password: &str,
+    req: &HttpRequest,
+) -> Result<LoginTokenMessage> {
+    let ip_addr = client_ip(config, req)?;
+    if get_login_lock(conn, username, &ip_addr).await?.is_some() {
+        return Err(auth_error("Too many failed logins, try again later"));
+    }
+    match providers.authenticate(conn, username, password).await? {
+        Some(user) => {
+            clear_login_failures(conn, LoginAttemptKind::User, username).await?;
+            handle_login(conn, &user, req).await
+        }
+        None => {
+            handle_login_failure(config, conn, username, &ip_addr).await?;
+            Err(bad_request("username or password wrong"))
+        }
+    }
+}
+
+/// Finishes a login for a user whose password was verified.
+/// Users with totp enabled get a challenge instead of a session token.
+pub async fn handle_login(
+    conn: &mut PgConnection,
+    user: &UserModel,
+    req: &HttpRequest,
+) -> Result<LoginTokenMessage> {
+    if !user.enabled {
+        return Err(auth_error("Account disabled"));
+    }
+    if user.totp_enabled {
+        let challenge = generate_token(32);
+        create_login_challenge(
+            conn,
+            &LoginChallengeModel::new(hash_token(&challenge), user.id),
+        )
+        .await?;
+        Ok(LoginTokenMessage::challenge(&challenge))
+    } else {
+        let token = handle_create_session(conn, user, req).await?;
+        Ok(LoginTokenMessage::new(&token))
+    }
+}
+
+/// Completes a login challenge with either a totp code or a recovery code.
+/// The challenge is consumed on the first attempt, so a wrong code requires a new login.
+pub async fn handle_login_second_factor(
+    config: &Config,
+    pool: &Pool<Postgres>,
+    challenge: &str,
+    code: &str,
+    req: &HttpRequest,
+) -> Result<LoginTokenMessage> {
+    let mut t = pool.begin().await?;
+    let user_id = use_login_challenge(&mut t, &hash_token(challenge))
+        .await?
+        .ok_or_else(|| auth_error("Login challenge invalid or expired"))?;
+    let user = get_user_by_id(&mut t, user_id)
+        .await?
+        .ok_or_else(|| auth_error("Login challenge invalid or expired"))?;
+    let valid = match user.check_totp(code)? {
+        Some(step) => use_totp_step(&mut t, user.id, step).await?,
+        None => use_recovery_code(&mut t, user.id, &hash_token(code.trim())).await?,
+    };
+    if !valid {
+        handle_login_failure(config, &mut t, &user.username, &client_ip(config, req)?).await?;
+        t.commit().await?;
+        return Err(auth_error("Code invalid"));
+    }
+    if !user.enabled {
+        t.commit().await?;
+        return Err(auth_error("Account disabled"));
+    }
+    let token = handle_create_session(&mut t, &user, req).await?;
+    t.commit().await?;
+    Ok(LoginTokenMessage::new(&token))
+}
+
+/// Generates a new totp secret for the user. It only takes effect after it was confirmed.
+pub async fn handle_totp_enroll(
+    pool: &Pool<Postgres>,
+    mut user: UserModel,
+) -> Result<TotpEnrollMessage> {
+    if user.totp_enabled {
+        return Err(bad_request("Totp already enabled"));
+    }
+    let mut secret = [0u8; 20];
+    OsRng.fill_bytes(&mut secret);
+    user.totp_secret = Some(Secret::Raw(secret.to_vec()).to_encoded().to_string());
+    let totp = user
+        .totp()?
+        .ok_or_else(|| bad_request("Totp secret missing"))?;
+    update_user(&mut *pool.acquire().await?, &user).await?;
+    Ok(TotpEnrollMessage {
+        secret: totp.get_secret_base32(),
+        otpauth_uri: totp.get_url(),
+    })
+}
+
+/// Enables totp after checking a first code and returns fresh recovery codes
+pub async fn handle_totp_confirm(
+    pool: &Pool<Postgres>,
+    mut user: UserModel,
+    code: &str,
+) -> Result<Vec<String>> {
+    if user.totp_enabled {
+        return Err(bad_request("Totp already enabled"));
+    }
+    let mut t = pool.begin().await?;
+    let step = user
+        .check_totp(code)?
+        .ok_or_else(|| bad_request("Code invalid"))?;
+    if !use_totp_step(&mut t, user.id, step).await? {
+        return Err(bad_request("Code invalid"));
+    }
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_token(9))
+        .collect();
+    let hashes: Vec<String> = codes.iter().map(|c| hash_token(c)).collect();
+    user.totp_enabled = true;
+    update_user(&mut t, &user).await?;
+    replace_recovery_codes(&mut t, user.id, &hashes).await?;
+    t.commit().await?;
+    Ok(codes)
+}
+
+pub async fn handle_totp_disable(
+    pool: &Pool<Postgres>,
+    mut user: UserModel,
+    code: &str,
+) -> Result<()> {
+    if !user.totp_enabled {
+        return Err(bad_request("Totp not enabled"));
+    }
+    if user.check_totp(code)?.is_none() {
+        return Err(bad_request("Code invalid"));
+    }
+    let mut t = pool.begin().await?;
+    user.totp_enabled = false;
+    user.totp_secret = None;
+    update_user(&mut t, &user).await?;
+    delete_recovery_codes(&mut t, user.id).await?;
+    t.commit().await?;
+    Ok(())
+}
+
+/// Gives the user a new calendar feed token, the previous one stops working
+pub async fn handle_create_feed_token(
+    config: &Config,
+    pool: &Pool<Postgres>,
+    user: &UserModel,
+) -> Result<FeedTokenMessage> {
+    let token = generate_token(32);
+    set_feed_token(
+        &mut *pool.acquire().await?,
+        user.id,
+        Some(&hash_token(&token)),
+    )
+    .await?;
+    Ok(FeedTokenMessage {
+        url: format!(
+            "{}/api/feeds/{}",
+            config.public_url.trim_end_matches('/'),
+            token
+        ),
+        token,
+    })
+}
+
+/// The enabled user the feed token belongs to
+pub async fn handle_feed_user(conn: &mut PgConnection, token: &str) -> Result<UserModel> {
+    get_user_by_feed_token(conn, &hash_token(token))
+        .await?
+        .filter(|u| u.enabled)
+        .ok_or_else(|| auth_error("Feed token invalid"))
+}
+
+/// Creates a named token for clients that authenticate with the username and it
+pub async fn handle_create_personal_token(
+    pool: &Pool<Postgres>,
+    user: &UserModel,
+    name: &str,
+) -> Result<PersonalTokenMessage> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 64 {
+        return Err(bad_request("Token name has to be 1 to 64 characters"));
+    }
+    let token = generate_token(32);
+    let mut model = PersonalTokenModel {
+        user_id: user.id,
+        name: name.to_owned(),
+        token_hash: hash_token(&token),
+        ..Default::default()
+    };
+    create_personal_token(&mut *pool.acquire().await?, &mut model).await?;
+    let mut msg: PersonalTokenMessage = model.into();
+    msg.token = Some(token);
+    Ok(msg)
+}
+
+/// The enabled user with that name, if the personal token is one of theirs
+pub async fn handle_personal_token_user(
+    conn: &mut PgConnection,
+    username: &str,
+    token: &str,
+) -> Result<UserModel> {
+    use_personal_token(conn, &hash_token(token))
+        .await?
+        .filter(|u| u.enabled && u.username == username)
+        .ok_or_else(|| auth_error("Username or token invalid"))
+}
+
+/// Enables or disables an account. Disabling revokes all sessions of the user.
+pub async fn handle_set_user_enabled(
+    pool: &Pool<Postgres>,
+    admin: &UserModel,
+    user_id: Uuid,
+    enabled: bool,
+) -> Result<UserModel> {
+    admin.is_admin()?;
+    if !enabled && admin.id == user_id {
+        return Err(bad_request("You cannot disable yourself"));
+    }
+    let mut t = pool.begin().await?;
+    let mut user = get_user_by_id(&mut t, user_id)
+        .await?
+        .ok_or_else(|| not_found("User not found"))?;
+    user.enabled = enabled;
+    update_user(&mut t, &user).await?;
+    if !enabled {
+        delete_user_sessions_by_user(&mut t, user.id).await?;
+    }
+    t.commit().await?;
+    Ok(user)
+}
+
+/// Creates invite codes, only their hashes are stored
+pub async fn handle_create_invites(pool: &Pool<Postgres>, count: usize) -> Result<Vec<String>> {
+    let mut t = pool.begin().await?;
+    let mut codes = Vec::with_capacity(count);
+    for _ in 0..count {
+        let code = generate_token(24);
+        create_invite_code(&mut t, &hash_token(&code)).await?;
+        codes.push(code);
+    }
+    t.commit().await?;
+    Ok(codes)
+}
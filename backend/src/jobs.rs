@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use actix_web::web;
+use sqlx::{Pool, Postgres};
+
+use crate::data::project_data::purge_trash;
+use crate::data::user_data::delete_expired_logins;
+use crate::error::Result;
+use crate::metrics::Metrics;
+
+/// Seconds between runs of the cleanup job
+const CLEANUP_INTERVAL: u64 = 3600;
+/// Seconds between runs of the trash purge
+const PURGE_INTERVAL: u64 = 24 * 3600;
+
+/// Starts the periodic jobs on the current runtime, their status shows up in the metrics.
+/// The trash is only purged with a retention of at least one day.
+pub fn spawn_jobs(pool: Pool<Postgres>, metrics: web::Data<Metrics>, trash_retention_days: i64) {
+    if trash_retention_days > 0 {
+        let pool = pool.clone();
+        let metrics = metrics.clone();
+        actix_web::rt::spawn(async move {
+            let mut interval = actix_web::rt::time::interval(Duration::from_secs(PURGE_INTERVAL));
+            loop {
+                interval.tick().await;
+                let result = purge(&pool, trash_retention_days).await;
+                if let Err(e) = &result {
+                    log::warn!("purge job failed: {e}");
+                }
+                metrics.record_job("purge", result.is_ok());
+            }
+        });
+    }
+    actix_web::rt::spawn(async move {
+        let mut interval = actix_web::rt::time::interval(Duration::from_secs(CLEANUP_INTERVAL));
+        loop {
+            interval.tick().await;
+            let result = cleanup(&pool).await;
+            if let Err(e) = &result {
+                log::warn!("cleanup job failed: {e}");
+            }
+            metrics.record_job("cleanup", result.is_ok());
+        }
+    });
+}
+
+async fn cleanup(pool: &Pool<Postgres>) -> Result<()> {
+    let deleted = delete_expired_logins(&mut *pool.acquire().await?).await?;
+    log::debug!("cleanup removed {deleted} expired login rows");
+    Ok(())
+}
+
+async fn purge(pool: &Pool<Postgres>, days: i64) -> Result<()> {
+    let purged = purge_trash(&mut *pool.acquire().await?, days).await?;
+    log::debug!("purge removed {purged} rows deleted more than {days} days ago");
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use std::{io::IsTerminal, time::Instant};
+
+use crate::config::Config;
+use crate::util::generate_token;
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderName, HeaderValue},
+    middleware::Next,
+    HttpMessage, HttpResponse,
+};
+use clap::ValueEnum;
+use serde::Serialize;
+use tracing::{field::Empty, Instrument};
+use tracing_subscriber::EnvFilter;
+use utoipa::ToSchema;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Id of the current request, taken from X-Request-Id or generated
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+/// Body of every error response, the request id lets support find the matching logs
+#[derive(Serialize, Debug, ToSchema)]
+pub struct ErrorMessage {
+    pub msg: String,
+    pub request_id: String,
+}
+
+/// Logs to stderr, `log` records from dependencies end up in the same output
+pub fn init_logging(config: &Config) {
+    let filter = if config.debug {
+        EnvFilter::new("debug")
+    } else {
+        EnvFilter::new(config.log_level.as_deref().unwrap_or("info"))
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal());
+    match config.log_format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .init(),
+    }
+}
+
+/// Accepts ids from proxies only if they are short and harmless to log
+fn valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= 64
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Wraps every request in a span with its id, route, user and latency,
+/// echoes the id as header and puts it into error bodies
+pub async fn trace_request(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let start = Instant::now();
+    let request_id = req
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|h| h.to_str().ok())
+        .filter(|id| valid_request_id(id))
+        .map(str::to_owned)
+        .unwrap_or_else(|| generate_token(16));
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_owned());
+    let ip = req
+        .peer_addr()
+        .map(|a| a.ip().to_string())
+        .unwrap_or_default();
+    let user_agent = req
+        .headers()
+        .get("User-Agent")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        route = %route,
+        user_id = Empty,
+    );
+
+    let res = next.call(req).instrument(span.clone()).await?;
+    let status = res.status();
+    span.in_scope(|| {
+        tracing::info!(
+            status = status.as_u16(),
+            latency_ms = start.elapsed().as_millis() as u64,
+            ip = %ip,
+            user_agent = %user_agent,
+            "request finished"
+        )
+    });
+
+    let mut res = match res.response().error().map(|e| e.to_string()) {
+        Some(msg) => {
+            let body = ErrorMessage {
+                msg,
+                request_id: request_id.clone(),
+            };
+            let mut json = HttpResponse::build(status);
+            // only the body is replaced, headers of the middlewares within stay
+            for (name, value) in res.headers() {
+                if name != header::CONTENT_TYPE && name != header::CONTENT_LENGTH {
+                    json.append_header((name.clone(), value.clone()));
+                }
+            }
+            res.into_response(json.json(body)).map_into_right_body()
+        }
+        None => res.map_into_left_body(),
+    };
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        res.headers_mut()
+            .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+    }
+    Ok(res)
+}
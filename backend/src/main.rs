@@ -1,63 +1,232 @@
-use actix_web::{middleware::Logger, web, App, HttpServer};
-use clap::Parser;
-use config::Config;
-use env_logger::Env;
-use handler::user_handler::handle_create_initial_admin;
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware, web, App, HttpServer,
+};
+use config::{Command, Config};
+use handler::{
+    auth_provider::AuthProviders, frontend_handler::Frontend, oidc_handler::OidcProvider,
+    user_handler::handle_create_initial_admin,
+};
+use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+use std::{sync::Arc, time::Duration};
+mod cli;
 mod config;
 pub mod data;
 pub mod error;
 pub mod handler;
+mod jobs;
+pub mod logging;
 pub mod messages;
+pub mod metrics;
 pub mod routes;
+mod security;
 pub mod util;
 
-use routes::{project_routes::register_project_routes, task_routes::register_task_routes, user_routes::register_user_routes};
+#[cfg(test)]
+mod tests;
+
+use logging::trace_request;
+use metrics::{track_request, Metrics};
+use routes::{
+    dav_routes::register_dav_routes,
+    feed_routes::register_feed_routes,
+    frontend_routes::frontend_route,
+    health_routes::register_health_routes,
+    openapi_routes::{register_openapi_routes, swagger_ui},
+    project_routes::register_project_routes,
+    task_routes::register_task_routes,
+    template_routes::register_template_routes,
+    user_routes::register_user_routes,
+    Routes,
+};
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let config = Config::parse();
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(2);
+    });
 
-    env_logger::init_from_env(
-        Env::default()
-            .filter("FOXB_LOG_LEVEL")
-            .default_filter_or(if config.debug { "debug" } else { "info" }), // TODO separate config value for loglevel. debug if debug otherwise grap value, info by default
-    );
-    log::info!("This is an example message.");
+    logging::init_logging(&config);
 
-    let pool = PgPoolOptions::new()
-        .max_connections(20) //TODO as config value
-        .connect(&format!(
-            "postgres://{}:{}@{}/{}",
-            &config.db_user, &config.db_password, &config.db_host, &config.db_db
-        ))
-        .await
-        .expect("Pool Failed");
-    sqlx::migrate!("./migrations")
-        .run(&pool)
-        .await
-        .expect("migraitions failed");
+    match config.command.clone() {
+        None | Some(Command::Serve) => serve(config).await,
+        Some(command) => {
+            if let Err(e) = cli::run_command(&config, command).await {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn connect_pool(config: &Config) -> error::Result<Pool<Postgres>> {
+    Ok(PgPoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .min_connections(config.db_min_connections)
+        .acquire_timeout(Duration::from_secs(config.db_acquire_timeout))
+        .idle_timeout(Duration::from_secs(config.db_idle_timeout))
+        .connect_with(config.db_connect_options()?)
+        .await?)
+}
+
+async fn serve(config: Config) -> std::io::Result<()> {
+    log::info!("starting foxboard {}", env!("CARGO_PKG_VERSION"));
+    let pool = connect_pool(&config).await.expect("Pool Failed");
+    data::MIGRATOR.run(&pool).await.expect("migraitions failed");
 
     handle_create_initial_admin(&config, &pool)
         .await
         .expect("Could not create admin user");
-    start_server(pool).await
+    let oidc = OidcProvider::from_config(&config)
+        .await
+        .expect("OIDC provider discovery failed");
+    let providers = AuthProviders::from_config(&config).expect("Invalid auth provider config");
+    start_server(config, pool, oidc, providers).await
 }
 
 fn init_app(cfg: &mut web::ServiceConfig) {
-    register_user_routes(cfg);
-    register_project_routes(cfg);
-    register_task_routes(cfg);
-}
-
-async fn start_server(pool: Pool<Postgres>) -> std::io::Result<()> {
-    HttpServer::new(move || {
-        // TODO Configure this properly
-        App::new()
-            .configure(init_app)
-            .wrap(Logger::new("%a %{User-Agent}i"))
-            .app_data(web::Data::new(pool.clone()))
-    })
-    .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+    register_routes(cfg, &mut Vec::new());
+}
+
+/// Adds every route, all but the caldav ones are recorded in `registered`
+fn register_routes(cfg: &mut web::ServiceConfig, registered: &mut Vec<(Method, String)>) {
+    register_dav_routes(cfg);
+    let mut routes = Routes::new(cfg, registered);
+    register_health_routes(&mut routes);
+    register_openapi_routes(&mut routes);
+    let mut api = routes.scope("/api");
+    register_user_routes(&mut api);
+    register_project_routes(&mut api);
+    register_task_routes(&mut api);
+    register_template_routes(&mut api);
+    register_feed_routes(&mut api);
+}
+
+/// What every worker's app shares, built once at startup
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: Pool<Postgres>,
+    pub config: web::Data<Config>,
+    pub oidc: web::Data<Option<OidcProvider>>,
+    pub providers: web::Data<AuthProviders>,
+    pub metrics: web::Data<Metrics>,
+    pub frontend: web::Data<Option<Frontend>>,
+}
+
+impl AppState {
+    pub fn new(
+        config: Config,
+        pool: Pool<Postgres>,
+        oidc: Option<OidcProvider>,
+        providers: AuthProviders,
+    ) -> Self {
+        let frontend = Frontend::from_config(&config);
+        Self {
+            pool,
+            config: web::Data::new(config),
+            oidc: web::Data::new(oidc),
+            providers: web::Data::new(providers),
+            metrics: web::Data::new(Metrics::default()),
+            frontend: web::Data::new(frontend),
+        }
+    }
+}
+
+/// The app as it is served: the routes, the frontend for everything else and the middleware
+pub fn build_app(
+    state: &AppState,
+) -> App<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse<impl MessageBody>,
+        Error = actix_web::Error,
+        InitError = (),
+    >,
+> {
+    let config = &state.config;
+    App::new()
+        .configure(init_app)
+        .configure(|cfg| {
+            if config.api_docs {
+                cfg.service(swagger_ui());
+            }
+        })
+        .default_service(web::to(frontend_route))
+        .wrap(middleware::Condition::new(
+            !config.cors_origins.is_empty(),
+            security::cors(config),
+        ))
+        .wrap(security::security_headers(config))
+        .wrap(middleware::from_fn(track_request))
+        .wrap(middleware::from_fn(trace_request))
+        .app_data(web::Data::new(state.pool.clone()))
+        .app_data(state.config.clone())
+        .app_data(state.oidc.clone())
+        .app_data(state.providers.clone())
+        .app_data(state.metrics.clone())
+        .app_data(state.frontend.clone())
+}
+
+async fn start_server(
+    config: Config,
+    pool: Pool<Postgres>,
+    oidc: Option<OidcProvider>,
+    providers: AuthProviders,
+) -> std::io::Result<()> {
+    let tls = load_tls_config(&config)?;
+    let addrs: Vec<(String, u16)> = config
+        .listen
+        .iter()
+        .map(|a| (a.clone(), config.port))
+        .collect();
+    let workers = config.workers;
+    let state = AppState::new(config, pool, oidc, providers);
+    jobs::spawn_jobs(
+        state.pool.clone(),
+        state.metrics.clone(),
+        state.config.trash_retention_days,
+    );
+    let mut server = HttpServer::new(move || build_app(&state));
+    if let Some(workers) = workers {
+        server = server.workers(workers);
+    }
+    for addr in addrs {
+        server = match &tls {
+            Some(tls) => server.bind_rustls_0_23(addr, tls.clone())?,
+            None => server.bind(addr)?,
+        };
+    }
+    server.run().await
+}
+
+/// Builds the rustls config if a certificate and key are configured
+fn load_tls_config(config: &Config) -> std::io::Result<Option<rustls::ServerConfig>> {
+    let (cert_path, key_path) = match (&config.tls_cert, &config.tls_key) {
+        (Some(c), Some(k)) => (c, k),
+        (None, None) => return Ok(None),
+        _ => {
+            return Err(std::io::Error::other(
+                "tls needs both a certificate and a key",
+            ))
+        }
+    };
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|c| c.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| std::io::Error::other(format!("{}: {e}", cert_path.display())))?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|e| std::io::Error::other(format!("{}: {e}", key_path.display())))?;
+    let tls = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(std::io::Error::other)?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)
+    .map_err(std::io::Error::other)?;
+    Ok(Some(tls))
 }
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::error::{bad_request, Result};
+
+/// A column of the task csv, named like its header
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvColumn {
+    Id,
+    Title,
+    Description,
+    Column,
+    State,
+    Labels,
+    Assignee,
+    Deadline,
+    Estimation,
+    Parent,
+}
+
+impl CsvColumn {
+    pub const ALL: [CsvColumn; 10] = [
+        Self::Id,
+        Self::Title,
+        Self::Description,
+        Self::Column,
+        Self::State,
+        Self::Labels,
+        Self::Assignee,
+        Self::Deadline,
+        Self::Estimation,
+        Self::Parent,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Title => "title",
+            Self::Description => "description",
+            Self::Column => "column",
+            Self::State => "state",
+            Self::Labels => "labels",
+            Self::Assignee => "assignee",
+            Self::Deadline => "deadline",
+            Self::Estimation => "estimation",
+            Self::Parent => "parent",
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self> {
+        let name = name.trim();
+        Self::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(name))
+            .ok_or_else(|| bad_request(&format!("Unknown csv column '{name}'")))
+    }
+
+    /// Parses a comma separated list, the id always comes first
+    pub fn parse_list(list: &str) -> Result<Vec<Self>> {
+        let mut columns = vec![Self::Id];
+        for name in list.split(',').filter(|n| !n.trim().is_empty()) {
+            let column = Self::parse(name)?;
+            if !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+        Ok(columns)
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+pub struct CsvImportMessage {
+    pub dry_run: bool,
+    /// False after a dry run or if any row has an error, nothing is changed then
+    pub applied: bool,
+    pub created: usize,
+    pub updated: usize,
+    pub errors: Vec<CsvRowErrorMessage>,
+}
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct CsvRowErrorMessage {
+    /// Line in the file, the header is line 1
+    pub line: u64,
+    pub msg: String,
+}
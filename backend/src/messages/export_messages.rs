@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::data::models::Permissions;
+use crate::messages::project_messages::ProjectMessage;
+
+/// Bumped whenever the export format changes in a way older imports can't read.
+/// Version 2 added the members.
+pub const EXPORT_VERSION: i32 = 2;
+/// The oldest version the import still reads
+pub const MIN_EXPORT_VERSION: i32 = 1;
+
+/// A whole project, ids are only used to link the entries within the document
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct ProjectExportMessage {
+    pub version: i32,
+    pub name: String,
+    pub public: bool,
+    pub columns: Vec<ExportColumnMessage>,
+    pub labels: Vec<ExportLabelMessage>,
+    pub states: Vec<ExportStateMessage>,
+    pub tasks: Vec<ExportTaskMessage>,
+    #[serde(default)]
+    pub members: Vec<ExportMemberMessage>,
+}
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct ExportColumnMessage {
+    pub id: Uuid,
+    pub name: String,
+    pub card_limit: i32,
+    pub index: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct ExportLabelMessage {
+    pub id: Uuid,
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct ExportStateMessage {
+    pub id: Uuid,
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MemberRole {
+    Owner,
+    Editor,
+    Reader,
+}
+
+impl MemberRole {
+    pub fn from_permissions(perms: Permissions) -> Option<Self> {
+        match perms {
+            Permissions::Admin | Permissions::Owner => Some(Self::Owner),
+            Permissions::Editor => Some(Self::Editor),
+            Permissions::Reader => Some(Self::Reader),
+            Permissions::None => None,
+        }
+    }
+}
+
+impl From<MemberRole> for Permissions {
+    fn from(value: MemberRole) -> Self {
+        match value {
+            MemberRole::Owner => Permissions::Owner,
+            MemberRole::Editor => Permissions::Editor,
+            MemberRole::Reader => Permissions::Reader,
+        }
+    }
+}
+
+/// A member by username, members that don't exist on import are dropped
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct ExportMemberMessage {
+    pub username: String,
+    pub role: MemberRole,
+}
+
+/// Users are referenced by username, so the export can move between instances
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct ExportTaskMessage {
+    pub id: Uuid,
+    pub title: String,
+    /// Missing in exports before the description was added
+    #[serde(default)]
+    pub description: Option<String>,
+    pub column_id: Option<Uuid>,
+    pub state_id: Option<Uuid>,
+    pub parent_id: Option<Uuid>,
+    pub assignee: Option<String>,
+    pub creator: Option<String>,
+    pub deadline: Option<i64>,
+    pub estimation: Option<i32>,
+    pub task_type: i32,
+    pub labels: Vec<Uuid>,
+}
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct ProjectImportMessage {
+    pub project: ProjectMessage,
+    /// Usernames of the document's members that weren't added, only admins import members
+    pub skipped_members: Vec<String>,
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What the tasks of a calendar feed become
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Default, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedEntry {
+    /// VEVENT at the deadline, shown by every calendar app
+    #[default]
+    Event,
+    /// VTODO due at the deadline, for apps that show tasks
+    Todo,
+}
+
+/// Only returned once, the server keeps a hash
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct FeedTokenMessage {
+    pub token: String,
+    /// Base of the feeds, `{url}/tasks.ics` has the tasks assigned to you and
+    /// `{url}/projects/{id}/tasks.ics` those of a project
+    pub url: String,
+}
@@ -1,4 +1,9 @@
+pub mod csv_messages;
+pub mod export_messages;
+pub mod feed_messages;
 pub mod general_messages;
 pub mod project_messages;
 pub mod task_massages;
+pub mod template_messages;
+pub mod trello_messages;
 pub mod user_messages;
@@ -1,14 +1,19 @@
 use serde::{Deserialize, Serialize};
 use sqlx::types::Uuid;
+use utoipa::ToSchema;
 
-use crate::data::models::{LabelModel, Permissions, ProjectColumnModel, ProjectModel, StateModel};
+use crate::data::models::{
+    LabelModel, Permissions, ProjectColumnModel, ProjectModel, StateModel, TrashModel,
+};
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct ProjectMessage {
     pub id: Uuid,
     pub name: String,
     pub owner_id: Uuid,
     pub public: bool,
+    /// When the project was archived, in milliseconds
+    pub archived: Option<i64>,
     pub labels: Option<Vec<LabelMessage>>,
     pub columns: Option<Vec<ProjectColumnMessage>>,
 }
@@ -20,16 +25,19 @@ impl From<ProjectModel> for ProjectMessage {
             name: value.name,
             owner_id: value.owner_id,
             public: value.public,
+            archived: value.archived.map(|x| x.timestamp_millis()),
             labels: None,
             columns: None,
         }
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct CreateProjectMessage {
     pub name: String,
     pub public: bool,
+    /// Start with the columns, labels, states and tasks of this template
+    pub template_id: Option<Uuid>,
 }
 impl CreateProjectMessage {
     pub fn to_model(self, owner_id: Uuid) -> ProjectModel {
@@ -38,11 +46,12 @@ impl CreateProjectMessage {
             name: self.name,
             owner_id,
             public: self.public,
+            archived: None,
         }
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct UpdateProjectMessage {
     pub name: Option<String>,
     pub public: Option<bool>,
@@ -70,7 +79,20 @@ impl UpdateProjectMessage {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/// Columns are always copied, everything else on request
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+#[serde(default)]
+pub struct CloneProjectMessage {
+    /// Defaults to the name of the project with " (copy)"
+    pub name: Option<String>,
+    pub labels: bool,
+    pub states: bool,
+    pub tasks: bool,
+    /// Needs owner permissions on the project
+    pub members: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct ProjectColumnMessage {
     pub id: Uuid,
     pub name: String,
@@ -90,7 +112,7 @@ impl From<ProjectColumnModel> for ProjectColumnMessage {
         }
     }
 }
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct CreateProjectColumnMessage {
     pub name: String,
     pub card_limit: Option<i32>,
@@ -107,7 +129,7 @@ impl CreateProjectColumnMessage {
         }
     }
 }
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct UpdateProjectColumnMessage {
     pub name: Option<String>,
     pub index: Option<i32>,
@@ -127,7 +149,7 @@ impl UpdateProjectColumnMessage {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct LabelMessage {
     pub id: Uuid,
     pub name: String,
@@ -144,7 +166,7 @@ impl From<LabelModel> for LabelMessage {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct CreateLabelMessage {
     pub name: String,
 }
@@ -158,7 +180,7 @@ impl CreateLabelMessage {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct UpdateLabelMessage {
     pub name: String,
 }
@@ -169,7 +191,7 @@ impl UpdateLabelMessage {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct StateMessage {
     pub id: Uuid,
     pub name: String,
@@ -186,7 +208,7 @@ impl From<StateModel> for StateMessage {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct CreateStateMessage {
     pub name: String,
 }
@@ -201,7 +223,7 @@ impl CreateStateMessage {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct UpdateStateMessage {
     pub name: String,
 }
@@ -211,3 +233,43 @@ impl UpdateStateMessage {
         model.name = self.name;
     }
 }
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TrashKind {
+    Task,
+    Column,
+    Label,
+    State,
+}
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct TrashMessage {
+    pub kind: TrashKind,
+    pub id: Uuid,
+    pub name: String,
+    /// When it was deleted, in milliseconds
+    pub deleted: i64,
+}
+
+impl From<TrashModel> for TrashMessage {
+    fn from(value: TrashModel) -> Self {
+        Self {
+            kind: match value.kind.as_str() {
+                "column" => TrashKind::Column,
+                "label" => TrashKind::Label,
+                "state" => TrashKind::State,
+                _ => TrashKind::Task,
+            },
+            id: value.id,
+            name: value.name,
+            deleted: value.deleted.timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct RestoreMessage {
+    pub kind: TrashKind,
+    pub id: Uuid,
+}
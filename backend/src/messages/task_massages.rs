@@ -1,12 +1,14 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::data::models::TaskModel;
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct TaskMessage {
     pub id: Uuid,
     pub title: String,
+    pub description: Option<String>,
     pub project_id: Uuid,
     pub column_id: Option<Uuid>,
     pub assignee_id: Option<Uuid>,
@@ -24,6 +26,7 @@ impl From<TaskModel> for TaskMessage {
         Self {
             id: value.id,
             title: value.title,
+            description: value.description,
             project_id: value.project_id,
             column_id: value.column_id,
             assignee_id: value.assignee_id,
@@ -38,9 +41,10 @@ impl From<TaskModel> for TaskMessage {
     }
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct CreateOrUpdateTaskMessage {
     pub title: String,
+    pub description: Option<String>,
     pub project_id: Uuid,
     pub column_id: Option<Uuid>,
     pub assignee_id: Option<Uuid>,
@@ -58,6 +62,7 @@ impl CreateOrUpdateTaskMessage {
         TaskModel {
             id: Uuid::nil(),
             title: self.title,
+            description: self.description,
             project_id: self.project_id,
             column_id: self.column_id,
             assignee_id: self.assignee_id,
@@ -74,6 +79,7 @@ impl CreateOrUpdateTaskMessage {
 
     pub fn update_model(self, model: &mut TaskModel) {
         model.title = self.title;
+        model.description = self.description;
         model.column_id = self.column_id;
         model.assignee_id = self.assignee_id;
         model.creator_id = self.creator_id;
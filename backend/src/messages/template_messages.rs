@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::export_messages::ProjectExportMessage;
+use crate::data::models::TemplateModel;
+use crate::error::Result;
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct TemplateMessage {
+    pub id: Uuid,
+    pub name: String,
+    pub owner_id: Option<Uuid>,
+    pub published: bool,
+    pub structure: ProjectExportMessage,
+}
+
+impl TryFrom<TemplateModel> for TemplateMessage {
+    type Error = crate::error::AppError;
+
+    fn try_from(value: TemplateModel) -> Result<Self> {
+        Ok(Self {
+            id: value.id,
+            name: value.name,
+            owner_id: value.owner_id,
+            published: value.published,
+            structure: serde_json::from_str(&value.structure)?,
+        })
+    }
+}
+
+/// Saves the structure of a project as a template
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct CreateTemplateMessage {
+    pub name: String,
+    pub project_id: Uuid,
+    /// Keep the tasks too, without their assignees
+    #[serde(default)]
+    pub tasks: bool,
+}
+
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+pub struct UpdateTemplateMessage {
+    pub name: Option<String>,
+    /// Only admins may publish a template
+    pub published: Option<bool>,
+}
+
+impl UpdateTemplateMessage {
+    pub fn update_model(self, model: &mut TemplateModel) {
+        if let Some(name) = self.name {
+            model.name = name
+        };
+        if let Some(published) = self.published {
+            model.published = published
+        };
+    }
+}
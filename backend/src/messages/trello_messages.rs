@@ -0,0 +1,94 @@
+//! The parts of a Trello board export the import reads, everything else in it is ignored
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::project_messages::ProjectMessage;
+
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TrelloBoard {
+    pub name: String,
+    pub lists: Vec<TrelloList>,
+    pub cards: Vec<TrelloCard>,
+    pub labels: Vec<TrelloLabel>,
+    pub checklists: Vec<TrelloChecklist>,
+    pub members: Vec<TrelloMember>,
+    pub actions: Vec<TrelloAction>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TrelloList {
+    pub id: String,
+    pub name: String,
+    pub closed: bool,
+    pub pos: f64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TrelloCard {
+    pub id: String,
+    pub name: String,
+    pub desc: String,
+    pub closed: bool,
+    pub id_list: String,
+    pub id_labels: Vec<String>,
+    pub id_members: Vec<String>,
+    /// RFC 3339
+    pub due: Option<String>,
+    pub due_complete: bool,
+    pub attachments: Vec<TrelloAttachment>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+#[serde(default)]
+pub struct TrelloAttachment {
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+#[serde(default)]
+pub struct TrelloLabel {
+    pub id: String,
+    pub name: String,
+    pub color: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TrelloChecklist {
+    pub id_card: String,
+    pub name: String,
+    pub check_items: Vec<TrelloCheckItem>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+#[serde(default)]
+pub struct TrelloCheckItem {
+    pub name: String,
+    /// "complete" or "incomplete"
+    pub state: String,
+    pub pos: f64,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct TrelloMember {
+    pub id: String,
+    pub username: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default, ToSchema)]
+#[serde(default)]
+pub struct TrelloAction {
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct TrelloImportMessage {
+    pub project: ProjectMessage,
+    /// What couldn't be taken over as it was, one line per item
+    pub unmapped: Vec<String>,
+}
@@ -1,13 +1,14 @@
-use crate::data::models::{AuthenticatedUser, Permissions, UserModel};
+use crate::data::models::{AuthenticatedUser, Permissions, PersonalTokenModel, UserModel};
 use crate::error::{bad_request, Result};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
 pub struct CreateUserMessage {
     pub(crate) username: String,
     pub(crate) password: String,
-    // pub(crate) email: String,
+    pub(crate) email: Option<String>,
     pub(crate) is_admin: Option<bool>,
 }
 
@@ -16,16 +17,20 @@ impl CreateUserMessage {
         Self {
             username,
             password,
+            email: None,
             is_admin: Some(is_admin),
         }
     }
 }
-#[derive(Serialize, Deserialize, Debug)]
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct UserMessage {
     pub(crate) username: String,
-    // pub(crate) email: String,
+    pub(crate) email: Option<String>,
     pub id: Uuid,
     pub(crate) is_admin: bool,
+    pub(crate) enabled: bool,
+    pub(crate) totp_enabled: bool,
 }
 
 impl UserMessage {}
@@ -34,35 +39,72 @@ impl From<UserModel> for UserMessage {
     fn from(value: UserModel) -> Self {
         Self {
             username: value.username,
+            email: value.email,
             is_admin: value.is_admin,
+            enabled: value.enabled,
+            totp_enabled: value.totp_enabled,
             id: value.id,
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct LoginMessage {
     pub username: String,
     pub password: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Either a session token, or a challenge if the user still has to pass a second factor
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct LoginTokenMessage {
-    pub token: String,
+    pub token: Option<String>,
+    pub challenge: Option<String>,
 }
 
 impl LoginTokenMessage {
     pub fn new(token: &str) -> Self {
         Self {
-            token: token.to_owned(),
+            token: Some(token.to_owned()),
+            challenge: None,
         }
     }
+
+    pub fn challenge(challenge: &str) -> Self {
+        Self {
+            token: None,
+            challenge: Some(challenge.to_owned()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct LoginSecondFactorMessage {
+    pub challenge: String,
+    /// Either a current totp code or an unused recovery code
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct TotpEnrollMessage {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct TotpCodeMessage {
+    pub code: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct RecoveryCodesMessage {
+    pub codes: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct UpdateUserMessage {
     pub is_admin: Option<bool>,
     pub username: Option<String>,
+    pub email: Option<String>,
     pub id: Option<Uuid>,
     pub new_password: Option<String>,
     pub old_password: Option<String>,
@@ -85,13 +127,19 @@ impl UpdateUserMessage {
         }
     }
 
-    pub fn update_user(self, mod_user: &mut UserModel) -> Result<()> {
+    /// Applies the changes, an email only stays verified if an admin sets it or it doesn't change
+    pub fn update_user(self, mod_user: &mut UserModel, by_admin: bool) -> Result<()> {
         self.username
             .into_iter()
             .for_each(|u| mod_user.username = u);
         self.is_admin
             .into_iter()
             .for_each(|u| mod_user.is_admin = u);
+        if let Some(e) = self.email {
+            mod_user.email_verified =
+                by_admin || (mod_user.email_verified && mod_user.email.as_ref() == Some(&e));
+            mod_user.email = Some(e);
+        }
         if let Some(u) = &self.new_password {
             if mod_user.check_password(&self.old_password.unwrap_or("".to_owned()))? {
                 mod_user.change_password(u);
@@ -100,3 +148,55 @@ impl UpdateUserMessage {
         Ok(())
     }
 }
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ForgotPasswordMessage {
+    pub email: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct ResetPasswordMessage {
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct OidcStartMessage {
+    /// Url of the provider the user has to be sent to
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct OidcCallbackMessage {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct CreatePersonalTokenMessage {
+    /// What the token is for, e.g. "phone"
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct PersonalTokenMessage {
+    pub id: Uuid,
+    pub name: String,
+    pub created: i64,
+    pub last_used: Option<i64>,
+    /// Only returned when the token is created, the server keeps a hash
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl From<PersonalTokenModel> for PersonalTokenMessage {
+    fn from(value: PersonalTokenModel) -> Self {
+        Self {
+            id: value.id,
+            name: value.name,
+            created: value.created.timestamp_millis(),
+            last_used: value.last_used.map(|x| x.timestamp_millis()),
+            token: None,
+        }
+    }
+}
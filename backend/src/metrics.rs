@@ -0,0 +1,149 @@
+use std::time::Instant;
+
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    middleware::Next,
+    web,
+};
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+
+/// Prometheus metrics of the server, rendered by /metrics
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    pub pool_connections: IntGauge,
+    pub pool_idle: IntGauge,
+    pub sessions: IntGauge,
+    pub project_tasks: IntGaugeVec,
+    job_runs: IntCounterVec,
+    job_last_run: IntGaugeVec,
+    job_last_success: IntGaugeVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let registry =
+            Registry::new_custom(Some("foxboard".to_owned()), None).expect("valid metrics prefix");
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Handled requests"),
+            &["method", "route", "status"],
+        )
+        .expect("valid metric");
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Request latency"),
+            &["method", "route"],
+        )
+        .expect("valid metric");
+        let pool_connections = IntGauge::new("db_pool_connections", "Open database connections")
+            .expect("valid metric");
+        let pool_idle = IntGauge::new("db_pool_idle_connections", "Idle database connections")
+            .expect("valid metric");
+        let sessions = IntGauge::new("sessions", "Active user sessions").expect("valid metric");
+        let project_tasks = IntGaugeVec::new(
+            Opts::new("project_tasks", "Tasks per project"),
+            &["project"],
+        )
+        .expect("valid metric");
+        let job_runs = IntCounterVec::new(
+            Opts::new("job_runs_total", "Background job runs"),
+            &["job", "result"],
+        )
+        .expect("valid metric");
+        let job_last_run = IntGaugeVec::new(
+            Opts::new(
+                "job_last_run_timestamp_seconds",
+                "Last run of a background job",
+            ),
+            &["job"],
+        )
+        .expect("valid metric");
+        let job_last_success = IntGaugeVec::new(
+            Opts::new(
+                "job_last_success_timestamp_seconds",
+                "Last successful run of a background job",
+            ),
+            &["job"],
+        )
+        .expect("valid metric");
+        for c in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_duration.clone()),
+            Box::new(pool_connections.clone()),
+            Box::new(pool_idle.clone()),
+            Box::new(sessions.clone()),
+            Box::new(project_tasks.clone()),
+            Box::new(job_runs.clone()),
+            Box::new(job_last_run.clone()),
+            Box::new(job_last_success.clone()),
+        ] {
+            registry.register(c).expect("metric registered once");
+        }
+        Self {
+            registry,
+            http_requests,
+            http_duration,
+            pool_connections,
+            pool_idle,
+            sessions,
+            project_tasks,
+            job_runs,
+            job_last_run,
+            job_last_success,
+        }
+    }
+}
+
+impl Metrics {
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, seconds: f64) {
+        self.http_requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.http_duration
+            .with_label_values(&[method, route])
+            .observe(seconds);
+    }
+
+    pub fn record_job(&self, job: &str, success: bool) {
+        let now = chrono::Utc::now().timestamp();
+        let result = if success { "success" } else { "failure" };
+        self.job_runs.with_label_values(&[job, result]).inc();
+        self.job_last_run.with_label_values(&[job]).set(now);
+        if success {
+            self.job_last_success.with_label_values(&[job]).set(now);
+        }
+    }
+
+    /// All metrics in the prometheus text format
+    pub fn render(&self) -> String {
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .unwrap_or_default()
+    }
+}
+
+/// Counts requests and their latency by route pattern, so ids in paths don't create new series
+pub async fn track_request(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| "unmatched".to_owned());
+    let metrics = req.app_data::<web::Data<Metrics>>().cloned();
+    let res = next.call(req).await?;
+    if let Some(metrics) = metrics {
+        metrics.observe_request(
+            &method,
+            &route,
+            res.status().as_u16(),
+            start.elapsed().as_secs_f64(),
+        );
+    }
+    Ok(res)
+}
@@ -0,0 +1,219 @@
+use actix_web::http::header::{self, HeaderMap};
+use actix_web::http::{Method, StatusCode};
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use sqlx::{PgConnection, Pool, Postgres};
+use uuid::Uuid;
+
+use crate::data::models::{Permissions, UserModel};
+use crate::data::task_data::delete_task;
+use crate::error::{auth_error, bad_request, AppError, Result};
+use crate::handler::dav_handler::{
+    handle_dav_object, handle_dav_put, handle_propfind, handle_report, DavNames, DavPath, DAV_ROOT,
+};
+use crate::handler::project_handler::handle_project_access;
+use crate::handler::user_handler::handle_personal_token_user;
+
+const ALLOW: &str = "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, REPORT";
+
+/// Caldav for task apps, projects are calendars of VTODOs. Every resource takes all methods
+/// through its default service, which answers the unsupported ones with 405 and the Allow
+/// header clients probe the server with. Caldav isn't part of the openapi document.
+pub fn register_dav_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(web::resource("/.well-known/caldav").default_service(web::to(well_known_route)));
+    cfg.service(
+        web::scope(DAV_ROOT)
+            .service(web::resource(["", "/"]).default_service(web::to(root_route)))
+            .service(
+                web::resource(["/projects", "/projects/"]).default_service(web::to(home_route)),
+            )
+            .service(
+                web::resource(["/projects/{project_id}", "/projects/{project_id}/"])
+                    .default_service(web::to(calendar_route)),
+            )
+            .service(
+                web::resource("/projects/{project_id}/{name}")
+                    .default_service(web::to(object_route)),
+            ),
+    );
+}
+
+async fn well_known_route() -> HttpResponse {
+    HttpResponse::MovedPermanently()
+        .insert_header((header::LOCATION, format!("{DAV_ROOT}/")))
+        .finish()
+}
+
+async fn root_route(
+    req: HttpRequest,
+    pool: web::Data<Pool<Postgres>>,
+    body: web::Bytes,
+) -> HttpResponse {
+    dav(&req, &pool, DavPath::Root, &body).await
+}
+
+async fn home_route(
+    req: HttpRequest,
+    pool: web::Data<Pool<Postgres>>,
+    body: web::Bytes,
+) -> HttpResponse {
+    dav(&req, &pool, DavPath::Home, &body).await
+}
+
+async fn calendar_route(
+    req: HttpRequest,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<Uuid>,
+    body: web::Bytes,
+) -> HttpResponse {
+    dav(&req, &pool, DavPath::Calendar(path.into_inner()), &body).await
+}
+
+async fn object_route(
+    req: HttpRequest,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<(Uuid, String)>,
+    body: web::Bytes,
+) -> HttpResponse {
+    let (project_id, name) = path.into_inner();
+    dav(&req, &pool, DavPath::Object(project_id, name), &body).await
+}
+
+async fn dav(req: &HttpRequest, pool: &Pool<Postgres>, path: DavPath, body: &[u8]) -> HttpResponse {
+    if req.method() == Method::OPTIONS {
+        return HttpResponse::Ok()
+            .insert_header(("DAV", "1, 3, calendar-access"))
+            .insert_header((header::ALLOW, ALLOW))
+            .finish();
+    }
+    match dispatch(req, pool, path, body).await {
+        Ok(res) => res,
+        // clients only send credentials after being asked for them
+        Err(e @ AppError::AuthError { .. }) => HttpResponse::Unauthorized()
+            .insert_header((header::WWW_AUTHENTICATE, "Basic realm=\"foxboard\""))
+            .body(e.to_string()),
+        Err(e) => e.error_response(),
+    }
+}
+
+async fn dispatch(
+    req: &HttpRequest,
+    pool: &Pool<Postgres>,
+    path: DavPath,
+    body: &[u8],
+) -> Result<HttpResponse> {
+    let mut t = pool.begin().await?;
+    let user = dav_user(&mut t, req.headers()).await?;
+    let body = std::str::from_utf8(body).map_err(|_| bad_request("Body is not utf-8"))?;
+    let res = match (req.method().as_str(), &path) {
+        ("PROPFIND", _) => {
+            // only the resource for 0, its children as well for 1 and infinity
+            let depth = req.headers().get("Depth").and_then(|d| d.to_str().ok());
+            match handle_propfind(&mut t, &user, &path, depth != Some("0"), body).await? {
+                Some(xml) => multistatus(xml),
+                None => HttpResponse::NotFound().finish(),
+            }
+        }
+        ("REPORT", DavPath::Calendar(project_id)) => {
+            multistatus(handle_report(&mut t, &user, *project_id, body).await?)
+        }
+        ("GET" | "HEAD", DavPath::Object(project_id, name)) => {
+            handle_project_access(&mut t, &user, *project_id, Permissions::Reader).await?;
+            let names = DavNames::load(&mut t, *project_id).await?;
+            match handle_dav_object(&mut t, &names, *project_id, name).await? {
+                Some(object) if req.method() == Method::HEAD => HttpResponse::Ok()
+                    .content_type("text/calendar; charset=utf-8")
+                    .insert_header((header::ETAG, object.etag))
+                    .finish(),
+                Some(object) => HttpResponse::Ok()
+                    .content_type("text/calendar; charset=utf-8")
+                    .insert_header((header::ETAG, object.etag))
+                    .body(object.data),
+                None => HttpResponse::NotFound().finish(),
+            }
+        }
+        ("PUT", DavPath::Object(project_id, name)) => {
+            handle_project_access(&mut t, &user, *project_id, Permissions::Editor).await?;
+            let names = DavNames::load(&mut t, *project_id).await?;
+            let current = handle_dav_object(&mut t, &names, *project_id, name).await?;
+            if !preconditions_met(req.headers(), current.as_ref().map(|o| o.etag.as_str())) {
+                return Ok(HttpResponse::PreconditionFailed().finish());
+            }
+            let status = match current {
+                Some(_) => StatusCode::NO_CONTENT,
+                None => StatusCode::CREATED,
+            };
+            handle_dav_put(
+                &mut t,
+                &user,
+                &names,
+                *project_id,
+                name,
+                current.map(|o| o.task),
+                body,
+            )
+            .await?;
+            // a created task only has its name now
+            let names = DavNames::load(&mut t, *project_id).await?;
+            let object = handle_dav_object(&mut t, &names, *project_id, name).await?;
+            let mut res = HttpResponse::build(status);
+            if let Some(object) = object {
+                res.insert_header((header::ETAG, object.etag));
+            }
+            res.finish()
+        }
+        ("DELETE", DavPath::Object(project_id, name)) => {
+            handle_project_access(&mut t, &user, *project_id, Permissions::Editor).await?;
+            let names = DavNames::load(&mut t, *project_id).await?;
+            match handle_dav_object(&mut t, &names, *project_id, name).await? {
+                Some(object) if preconditions_met(req.headers(), Some(&object.etag)) => {
+                    delete_task(&mut t, object.task.id).await?;
+                    HttpResponse::NoContent().finish()
+                }
+                Some(_) => HttpResponse::PreconditionFailed().finish(),
+                None => HttpResponse::NotFound().finish(),
+            }
+        }
+        _ => HttpResponse::MethodNotAllowed()
+            .insert_header((header::ALLOW, ALLOW))
+            .finish(),
+    };
+    t.commit().await?;
+    Ok(res)
+}
+
+fn multistatus(xml: String) -> HttpResponse {
+    HttpResponse::build(StatusCode::MULTI_STATUS)
+        .content_type("application/xml; charset=utf-8")
+        .body(xml)
+}
+
+/// The user from basic auth with the username and one of their personal tokens
+async fn dav_user(conn: &mut PgConnection, headers: &HeaderMap) -> Result<UserModel> {
+    let credentials = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Basic "))
+        .and_then(|c| BASE64_STANDARD.decode(c.trim()).ok())
+        .and_then(|c| String::from_utf8(c).ok())
+        .ok_or_else(|| auth_error("Basic auth with your username and a personal token required"))?;
+    let (username, token) = credentials
+        .split_once(':')
+        .ok_or_else(|| auth_error("Basic auth credentials invalid"))?;
+    handle_personal_token_user(conn, username, token).await
+}
+
+/// Checks If-Match and If-None-Match against the etag of the resource, `None` if it doesn't exist
+fn preconditions_met(headers: &HeaderMap, etag: Option<&str>) -> bool {
+    let matches = |name| {
+        headers
+            .get(name)
+            .and_then(|h| h.to_str().ok())
+            .map(|h| match (h.trim(), etag) {
+                ("*", etag) => etag.is_some(),
+                (tags, Some(etag)) => tags.split(',').any(|t| t.trim() == etag),
+                (_, None) => false,
+            })
+    };
+    matches(header::IF_MATCH) != Some(false) && matches(header::IF_NONE_MATCH) != Some(true)
+}
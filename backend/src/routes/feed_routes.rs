@@ -0,0 +1,105 @@
+use actix_web::{http::Method, web, HttpResponse};
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+use utoipa::{IntoParams, OpenApi};
+use uuid::Uuid;
+
+use crate::data::models::Permissions;
+use crate::data::task_data::{get_assigned_tasks, get_tasks_by_project};
+use crate::error::{not_found, Result};
+use crate::handler::ical_handler::handle_task_calendar;
+use crate::handler::project_handler::handle_project_access;
+use crate::handler::user_handler::handle_feed_user;
+use crate::logging::ErrorMessage;
+use crate::messages::feed_messages::FeedEntry;
+use crate::routes::Routes;
+
+/// Calendar apps can't send a session, the feeds are authenticated by the token in their url
+pub fn register_feed_routes(routes: &mut Routes) {
+    routes
+        .scope("/feeds/{token}")
+        .route(Method::GET, "/tasks.ics", assigned_feed_route)
+        .route(
+            Method::GET,
+            "/projects/{project_id}/tasks.ics",
+            project_feed_route,
+        );
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(assigned_feed_route, project_feed_route))]
+pub struct FeedApi;
+
+fn calendar(ical: String) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/calendar; charset=utf-8")
+        .body(ical)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/feeds/{token}/tasks.ics",
+    tag = "feeds",
+    summary = "Deadlines of the tasks assigned to you",
+    params(("token" = String, Path, description = "Feed token"), FeedQuery),
+    responses((status = 200, body = String, content_type = "text/calendar"), (status = "default", description = "Error", body = ErrorMessage))
+)]
+async fn assigned_feed_route(
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<String>,
+    query: web::Query<FeedQuery>,
+) -> Result<HttpResponse> {
+    let mut conn = pool.acquire().await?;
+    let user = handle_feed_user(&mut conn, &path).await?;
+    let tasks = get_assigned_tasks(&mut conn, user.id, user.is_admin).await?;
+    let name = format!("foxboard: {}", user.username);
+    Ok(calendar(
+        handle_task_calendar(&mut conn, &name, &tasks, query.entry).await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/feeds/{token}/projects/{project_id}/tasks.ics",
+    tag = "feeds",
+    summary = "Deadlines of the tasks of a project",
+    params(
+        ("token" = String, Path, description = "Feed token"),
+        ("project_id" = Uuid, Path, description = "Project id"),
+        FeedQuery
+    ),
+    responses((status = 200, body = String, content_type = "text/calendar"), (status = "default", description = "Error", body = ErrorMessage))
+)]
+async fn project_feed_route(
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<(String, Uuid)>,
+    query: web::Query<FeedQuery>,
+) -> Result<HttpResponse> {
+    let (token, project_id) = path.into_inner();
+    let mut conn = pool.acquire().await?;
+    let user = handle_feed_user(&mut conn, &token).await?;
+    let (project, _) =
+        handle_project_access(&mut conn, &user, project_id, Permissions::Reader).await?;
+    // archived projects are gone from the calendar like from the assigned tasks
+    if project.archived.is_some() {
+        return Err(not_found("Project not found"));
+    }
+    let mut tasks: Vec<_> = get_tasks_by_project(&mut conn, project.id)
+        .await?
+        .into_iter()
+        .filter(|t| t.deadline.is_some())
+        .collect();
+    tasks.sort_by_key(|t| t.deadline);
+    Ok(calendar(
+        handle_task_calendar(&mut conn, &project.name, &tasks, query.entry).await?,
+    ))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct FeedQuery {
+    /// `event` (default) or `todo`
+    #[serde(default)]
+    #[param(inline)]
+    pub entry: FeedEntry,
+}
@@ -0,0 +1,33 @@
+use actix_web::{
+    error::ErrorNotFound,
+    http::{header, Method},
+    web, HttpRequest, HttpResponse,
+};
+
+use crate::handler::frontend_handler::Frontend;
+
+/// Default service, serves the frontend for everything that isn't an api route
+pub async fn frontend_route(
+    req: HttpRequest,
+    frontend: web::Data<Option<Frontend>>,
+) -> actix_web::Result<HttpResponse> {
+    let path = req.path().to_owned();
+    let is_api = path == "/api" || path.starts_with("/api/");
+    let is_read = req.method() == Method::GET || req.method() == Method::HEAD;
+    if frontend.is_none() || is_api || !is_read {
+        return Err(ErrorNotFound("Not found"));
+    }
+    // reading from disk blocks
+    let asset = web::block(move || frontend.as_ref().as_ref().and_then(|f| f.asset(&path)))
+        .await?
+        .ok_or_else(|| ErrorNotFound("Not found"))?;
+    let cache = if asset.immutable {
+        "public, max-age=31536000, immutable"
+    } else {
+        "no-cache"
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(asset.content_type)
+        .insert_header((header::CACHE_CONTROL, cache))
+        .body(asset.content))
+}
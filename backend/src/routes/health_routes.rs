@@ -0,0 +1,81 @@
+use actix_web::{http::Method, web, HttpRequest, HttpResponse};
+use sqlx::{Pool, Postgres};
+use utoipa::OpenApi;
+
+use crate::config::Config;
+use crate::error::{auth_error, Result};
+use crate::handler::health_handler::{handle_metrics, handle_readiness};
+use crate::logging::ErrorMessage;
+use crate::messages::general_messages::SuccessMessage;
+use crate::metrics::Metrics;
+use crate::routes::Routes;
+use crate::util::hash_token;
+
+pub fn register_health_routes(routes: &mut Routes) {
+    routes
+        .route(Method::GET, "/healthz", healthz_route)
+        .route(Method::GET, "/readyz", readyz_route)
+        .route(Method::GET, "/metrics", metrics_route);
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(healthz_route, readyz_route, metrics_route))]
+pub struct HealthApi;
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "system",
+    summary = "Liveness probe",
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage))
+)]
+async fn healthz_route() -> web::Json<SuccessMessage> {
+    web::Json(SuccessMessage::new(true))
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "system",
+    summary = "Readiness probe, 503 while the database is unreachable or migrations are pending",
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage))
+)]
+async fn readyz_route(pool: web::Data<Pool<Postgres>>) -> HttpResponse {
+    match handle_readiness(&pool).await {
+        Ok(()) => HttpResponse::Ok().json(SuccessMessage::new(true)),
+        Err(e) => {
+            log::warn!("not ready: {e}");
+            HttpResponse::ServiceUnavailable().json(SuccessMessage::new(false))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "system",
+    summary = "Prometheus metrics, needs the metrics token if one is configured",
+    responses((status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"), (status = "default", description = "Error", body = ErrorMessage))
+)]
+async fn metrics_route(
+    config: web::Data<Config>,
+    pool: web::Data<Pool<Postgres>>,
+    metrics: web::Data<Metrics>,
+    req: HttpRequest,
+) -> Result<HttpResponse> {
+    if let Some(token) = &config.metrics_token {
+        let given = req
+            .headers()
+            .get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        // compare hashes so the time taken doesn't depend on the token
+        if hash_token(given) != hash_token(token) {
+            return Err(auth_error("Metrics token wrong"));
+        }
+    }
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(handle_metrics(&pool, &metrics).await?))
+}
@@ -1,3 +1,77 @@
+use actix_web::{guard, http::Method, web, FromRequest, Handler, Responder};
+
+pub mod dav_routes;
+pub mod feed_routes;
+pub mod frontend_routes;
+pub mod health_routes;
+pub mod openapi_routes;
 pub mod project_routes;
 pub mod task_routes;
+pub mod template_routes;
 pub mod user_routes;
+
+/// Adds routes to the app and records their method and path, actix can't list them later.
+/// The openapi test checks the recorded routes against the documented ones.
+pub struct Routes<'a> {
+    cfg: &'a mut web::ServiceConfig,
+    prefix: String,
+    registered: &'a mut Vec<(Method, String)>,
+}
+
+impl<'a> Routes<'a> {
+    pub fn new(cfg: &'a mut web::ServiceConfig, registered: &'a mut Vec<(Method, String)>) -> Self {
+        Self {
+            cfg,
+            prefix: String::new(),
+            registered,
+        }
+    }
+
+    /// Routes below `prefix`
+    pub fn scope(&mut self, prefix: &str) -> Routes<'_> {
+        Routes {
+            cfg: self.cfg,
+            prefix: format!("{}{prefix}", self.prefix),
+            registered: self.registered,
+        }
+    }
+
+    pub fn route<F, Args>(&mut self, method: Method, path: &str, handler: F) -> &mut Self
+    where
+        F: Handler<Args>,
+        Args: FromRequest + 'static,
+        F::Output: Responder + 'static,
+    {
+        let resource = self.resource(method, path);
+        self.cfg.service(resource.route(web::route().to(handler)));
+        self
+    }
+
+    /// A route with its own app data, e.g. a larger body limit
+    pub fn route_with<F, Args, D>(
+        &mut self,
+        method: Method,
+        path: &str,
+        data: D,
+        handler: F,
+    ) -> &mut Self
+    where
+        F: Handler<Args>,
+        Args: FromRequest + 'static,
+        F::Output: Responder + 'static,
+        D: 'static,
+    {
+        let resource = self.resource(method, path);
+        self.cfg
+            .service(resource.app_data(data).route(web::route().to(handler)));
+        self
+    }
+
+    /// The method is a guard of the resource, so other methods fall through to the
+    /// next resource of the same path
+    fn resource(&mut self, method: Method, path: &str) -> actix_web::Resource {
+        let path = format!("{}{path}", self.prefix);
+        self.registered.push((method.clone(), path.clone()));
+        web::resource(path).guard(guard::Method(method))
+    }
+}
@@ -0,0 +1,156 @@
+use actix_web::{http::Method, web};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::routes::feed_routes::FeedApi;
+use crate::routes::health_routes::HealthApi;
+use crate::routes::project_routes::ProjectApi;
+use crate::routes::task_routes::TaskApi;
+use crate::routes::template_routes::TemplateApi;
+use crate::routes::user_routes::UserApi;
+use crate::routes::Routes;
+
+pub fn register_openapi_routes(routes: &mut Routes) {
+    routes.route(Method::GET, "/openapi.json", openapi_route);
+}
+
+/// Swagger UI under /docs/, reads the spec from /openapi.json
+pub fn swagger_ui() -> SwaggerUi {
+    SwaggerUi::new("/docs/{_:.*}").config(utoipa_swagger_ui::Config::from("/openapi.json"))
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "foxboard",
+        description = "Kanban board api. Failed requests answer with an ErrorMessage."
+    ),
+    paths(openapi_route),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "auth", description = "Logins, second factors and password resets"),
+        (name = "users", description = "User accounts"),
+        (name = "projects", description = "Projects with their columns, labels and states"),
+        (name = "tasks", description = "Tasks"),
+        (name = "templates", description = "Project templates"),
+        (name = "feeds", description = "Calendar feeds of task deadlines"),
+        (name = "system", description = "Health checks, metrics and this document"),
+    )
+)]
+struct ApiDoc;
+
+/// Session tokens from the login routes are sent as `Authorization: Bearer <token>`
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+/// The full spec, collected from the annotated handlers of every route module
+pub fn api_doc() -> utoipa::openapi::OpenApi {
+    let mut doc = ApiDoc::openapi();
+    // filled from Cargo.toml, which doesn't name one
+    doc.info.license = None;
+    doc.merge(HealthApi::openapi());
+    doc.merge(UserApi::openapi());
+    doc.merge(ProjectApi::openapi());
+    doc.merge(TaskApi::openapi());
+    doc.merge(TemplateApi::openapi());
+    doc.merge(FeedApi::openapi());
+    doc
+}
+
+#[utoipa::path(
+    get,
+    path = "/openapi.json",
+    tag = "system",
+    summary = "This document",
+    responses((status = 200, description = "OpenAPI 3 document", content_type = "application/json"))
+)]
+async fn openapi_route() -> web::Json<utoipa::openapi::OpenApi> {
+    web::Json(api_doc())
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{dev::ResourceDef, http::Method, test, App};
+
+    use super::*;
+
+    const METHODS: [Method; 5] = [
+        Method::GET,
+        Method::POST,
+        Method::PUT,
+        Method::DELETE,
+        Method::PATCH,
+    ];
+
+    fn uri(path: &str) -> String {
+        path.split('/')
+            .map(|s| match s.starts_with('{') {
+                true => "00000000-0000-0000-0000-000000000000",
+                false => s,
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    #[actix_web::test]
+    async fn spec_matches_registered_routes() {
+        let mut registered = Vec::new();
+        let app = test::init_service(
+            App::new().configure(|cfg| crate::register_routes(cfg, &mut registered)),
+        )
+        .await;
+        let mut registered: Vec<_> = registered
+            .into_iter()
+            .map(|(method, path)| (method.to_string(), path))
+            .collect();
+        let mut documented = Vec::new();
+        for (path, item) in api_doc().paths.paths {
+            let operations = [
+                ("GET", item.get.is_some()),
+                ("POST", item.post.is_some()),
+                ("PUT", item.put.is_some()),
+                ("DELETE", item.delete.is_some()),
+                ("PATCH", item.patch.is_some()),
+            ];
+            for (method, _) in operations.into_iter().filter(|(_, doc)| *doc) {
+                documented.push((method.to_owned(), path.clone()));
+            }
+        }
+        documented.sort();
+        registered.sort();
+        assert_eq!(
+            documented, registered,
+            "registered routes and documented operations differ"
+        );
+
+        // registered routes answer, the other methods only where another route matches
+        for (_, path) in &registered {
+            let uri = uri(path);
+            for method in METHODS {
+                let req = test::TestRequest::default()
+                    .method(method.clone())
+                    .uri(&uri)
+                    .to_request();
+                let status = test::call_service(&app, req).await.status();
+                let routed = status != 404 && status != 405;
+                let expected = registered.iter().any(|(m, p)| {
+                    *m == method.as_str() && ResourceDef::new(p.as_str()).is_match(&uri)
+                });
+                assert_eq!(
+                    routed, expected,
+                    "{method} {uri} is routed: {routed}, registered: {expected}"
+                );
+            }
+        }
+    }
+}
@@ -1,124 +1,429 @@
-use crate::data::models::{Permissions, ProjectModel, Ressource};
 use crate::data::project_data::{
-    create_column, create_label, create_project, create_state, delete_column, delete_label,
-    delete_project, delete_state, get_column, get_columns, get_label, get_labels, get_project,
-    get_project_permission, get_projects, get_state, list_projects, update_column, update_label,
-    update_project, update_state,
+    archive_project, create_column, create_label, create_project, create_state, delete_column,
+    delete_label, delete_project, delete_state, get_column, get_columns, get_label, get_labels,
+    get_projects, get_state, get_trash, list_projects, restore_column, restore_label,
+    restore_state, update_column, update_label, update_project, update_state,
 };
+use crate::data::task_data::restore_task;
 use crate::error::{not_found, unauthorized, Result};
+use crate::handler::board_handler::{handle_export_markdown, handle_export_todo_txt};
+use crate::handler::csv_handler::{handle_export_csv, handle_import_csv};
+use crate::handler::export_handler::{handle_export_project, handle_import_project};
+use crate::handler::project_handler::{
+    handle_clone_project, EditAccess, OwnerAccess, ProjectAccess, ReadAccess, UnarchiveAccess,
+};
+use crate::handler::template_handler::handle_create_from_template;
+use crate::handler::trello_handler::handle_import_trello;
+use crate::logging::ErrorMessage;
+use crate::messages::csv_messages::{CsvColumn, CsvImportMessage};
+use crate::messages::export_messages::{ProjectExportMessage, ProjectImportMessage};
 use crate::messages::general_messages::SuccessMessage;
 use crate::messages::project_messages::{
-    CreateLabelMessage, CreateProjectColumnMessage, CreateStateMessage, LabelMessage,
-    ProjectColumnMessage, StateMessage, UpdateLabelMessage, UpdateProjectColumnMessage,
-    UpdateProjectMessage, UpdateStateMessage,
+    CloneProjectMessage, CreateLabelMessage, CreateProjectColumnMessage, CreateStateMessage,
+    LabelMessage, ProjectColumnMessage, RestoreMessage, StateMessage, TrashKind, TrashMessage,
+    UpdateLabelMessage, UpdateProjectColumnMessage, UpdateProjectMessage, UpdateStateMessage,
 };
+use crate::messages::trello_messages::{TrelloBoard, TrelloImportMessage};
+use crate::routes::Routes;
 use crate::util::{Page, Pagination};
-use actix_web::{web, HttpRequest};
-use sqlx::{PgConnection, Pool, Postgres};
-use uuid::Uuid;
-// use crate::handler::project_handler::handle_create_project;
-use crate::handler::user_handler::handle_token_update;
 use crate::{
-    data::models::AuthenticatedUser,
+    data::models::{AuthenticatedUser, Permissions},
     messages::project_messages::{CreateProjectMessage, ProjectMessage},
 };
+use actix_web::http::header::ContentDisposition;
+use actix_web::{http::Method, web, HttpResponse};
+use serde::Deserialize;
+use sqlx::{Pool, Postgres};
+use utoipa::{IntoParams, OpenApi};
+use uuid::Uuid;
+
+/// Bytes an imported project may have, far more than the default json limit
+const IMPORT_LIMIT: usize = 16 * 1024 * 1024;
 
-pub fn register_project_routes(cfg: &mut web::ServiceConfig) {
-    // cfg.service(web::scope("/user").route("/new", web::post().to(create_user)));
-    cfg.service(
-        web::scope("/projects")
-            .route("", web::post().to(create_project_route))
-            .route("/list", web::get().to(get_projects_route))
-            .route("/{project_id}", web::get().to(get_project_route))
-            .route("/{project_id}", web::delete().to(delete_project_route))
-            .route("/{project_id}", web::put().to(update_project_route))
-            .route("/{project_id}/columns", web::post().to(create_column_route))
-            .route("/{project_id}/labels", web::post().to(create_label_route))
-            .route(
-                "/{project_id}/columns/{column_id}",
-                web::put().to(update_column_route),
-            )
-            .route(
-                "/{project_id}/labels/{label_id}",
-                web::put().to(update_label_route),
-            )
-            .route(
-                "/{project_id}/columns/{column_id}",
-                web::delete().to(delete_column_route),
-            )
-            .route(
-                "/{project_id}/labels/{label_id}",
-                web::delete().to(delete_label_route),
-            )
-            .route("/{project_id}/states", web::post().to(create_state_route))
-            .route(
-                "/{project_id}/states/{state_id}",
-                web::put().to(update_state_route),
-            )
-            .route(
-                "/{project_id}/states/{state_id}",
-                web::delete().to(delete_state_route),
-            ),
-    );
+pub fn register_project_routes(routes: &mut Routes) {
+    let import_limit = || web::JsonConfig::default().limit(IMPORT_LIMIT);
+    routes
+        .scope("/projects")
+        .route(Method::POST, "", create_project_route)
+        .route(Method::GET, "/list", get_projects_route)
+        .route(Method::GET, "/archived", get_archived_projects_route)
+        .route_with(
+            Method::POST,
+            "/import",
+            import_limit(),
+            import_project_route,
+        )
+        .route_with(
+            Method::POST,
+            "/import/trello",
+            import_limit(),
+            import_trello_route,
+        )
+        .route(Method::GET, "/{project_id}", get_project_route)
+        .route(Method::DELETE, "/{project_id}", delete_project_route)
+        .route(Method::PUT, "/{project_id}", update_project_route)
+        .route(Method::POST, "/{project_id}/archive", archive_project_route)
+        .route(
+            Method::POST,
+            "/{project_id}/unarchive",
+            unarchive_project_route,
+        )
+        .route(Method::GET, "/{project_id}/trash", get_trash_route)
+        .route(Method::POST, "/{project_id}/clone", clone_project_route)
+        .route(Method::GET, "/{project_id}/export", export_project_route)
+        .route(Method::GET, "/{project_id}/tasks.csv", export_csv_route)
+        .route_with(
+            Method::POST,
+            "/{project_id}/tasks.csv",
+            web::PayloadConfig::new(IMPORT_LIMIT),
+            import_csv_route,
+        )
+        .route(
+            Method::GET,
+            "/{project_id}/export.md",
+            export_markdown_route,
+        )
+        .route(
+            Method::GET,
+            "/{project_id}/export.txt",
+            export_todo_txt_route,
+        )
+        .route(Method::POST, "/{project_id}/trash/restore", restore_route)
+        .route(Method::POST, "/{project_id}/columns", create_column_route)
+        .route(Method::POST, "/{project_id}/labels", create_label_route)
+        .route(
+            Method::PUT,
+            "/{project_id}/columns/{column_id}",
+            update_column_route,
+        )
+        .route(
+            Method::PUT,
+            "/{project_id}/labels/{label_id}",
+            update_label_route,
+        )
+        .route(
+            Method::DELETE,
+            "/{project_id}/columns/{column_id}",
+            delete_column_route,
+        )
+        .route(
+            Method::DELETE,
+            "/{project_id}/labels/{label_id}",
+            delete_label_route,
+        )
+        .route(Method::POST, "/{project_id}/states", create_state_route)
+        .route(
+            Method::PUT,
+            "/{project_id}/states/{state_id}",
+            update_state_route,
+        )
+        .route(
+            Method::DELETE,
+            "/{project_id}/states/{state_id}",
+            delete_state_route,
+        );
 }
 
+#[derive(OpenApi)]
+#[openapi(paths(
+    create_project_route,
+    get_project_route,
+    get_projects_route,
+    get_archived_projects_route,
+    delete_project_route,
+    update_project_route,
+    archive_project_route,
+    unarchive_project_route,
+    get_trash_route,
+    restore_route,
+    clone_project_route,
+    export_project_route,
+    import_project_route,
+    import_trello_route,
+    export_csv_route,
+    import_csv_route,
+    export_markdown_route,
+    export_todo_txt_route,
+    create_column_route,
+    create_label_route,
+    update_column_route,
+    update_label_route,
+    delete_column_route,
+    delete_label_route,
+    create_state_route,
+    update_state_route,
+    delete_state_route
+))]
+pub struct ProjectApi;
+
+#[utoipa::path(
+    post,
+    path = "/api/projects",
+    tag = "projects",
+    summary = "Create a project, empty or from a template",
+    request_body = CreateProjectMessage,
+    responses((status = 200, body = ProjectMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn create_project_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<CreateProjectMessage>,
-    req: HttpRequest,
 ) -> Result<web::Json<ProjectMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
+    if let Some(template_id) = msg.template_id {
+        let msg = msg.0;
+        let mut t = pool.begin().await?;
+        let project =
+            handle_create_from_template(&mut t, &user, template_id, msg.name, msg.public).await?;
+        t.commit().await?;
+        return Ok(web::Json(project.into()));
+    }
     let mut new_project = msg.0.to_model(user.id);
     create_project(&mut *pool.acquire().await?, &mut new_project).await?;
     Ok(web::Json(new_project.into()))
 }
 
-async fn get_project_route(
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/export",
+    tag = "projects",
+    summary = "Export a project with its columns, labels, states, tasks and members",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    responses((status = 200, body = ProjectExportMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn export_project_route(
+    access: ProjectAccess<ReadAccess>,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<web::Json<ProjectExportMessage>> {
+    let export = handle_export_project(&mut *pool.acquire().await?, access.project.id).await?;
+    Ok(web::Json(export))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/projects/import",
+    tag = "projects",
+    summary = "Import an exported project, owned by you",
+    description = "Only admins import the members, as editors at most. For everyone else they are skipped.",
+    request_body = ProjectExportMessage,
+    responses((status = 200, body = ProjectImportMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn import_project_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<ProjectExportMessage>,
+) -> Result<web::Json<ProjectImportMessage>> {
+    let mut msg = msg.0;
+    // anyone may import, but not add other users to what they import
+    let skipped_members = match user.is_admin {
+        true => Vec::new(),
+        false => msg.members.drain(..).map(|m| m.username).collect(),
+    };
+    let mut t = pool.begin().await?;
+    let project = handle_import_project(&mut t, &user, msg).await?;
+    t.commit().await?;
+    Ok(web::Json(ProjectImportMessage {
+        project: project.into(),
+        skipped_members,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/projects/import/trello",
+    tag = "projects",
+    summary = "Import a Trello board export, owned by you",
+    request_body = TrelloBoard,
+    responses((status = 200, body = TrelloImportMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn import_trello_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
+    msg: web::Json<TrelloBoard>,
+) -> Result<web::Json<TrelloImportMessage>> {
+    let mut t = pool.begin().await?;
+    let (project, unmapped) = handle_import_trello(&mut t, &user, msg.0, user.is_admin).await?;
+    t.commit().await?;
+    Ok(web::Json(TrelloImportMessage {
+        project: project.into(),
+        unmapped,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/tasks.csv",
+    tag = "projects",
+    summary = "Export the tasks of a project as csv",
+    params(("project_id" = Uuid, Path, description = "Project id"), CsvExportQuery),
+    responses((status = 200, body = String, content_type = "text/csv"), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn export_csv_route(
+    access: ProjectAccess<ReadAccess>,
+    pool: web::Data<Pool<Postgres>>,
+    query: web::Query<CsvExportQuery>,
+) -> Result<HttpResponse> {
+    let columns = match &query.columns {
+        Some(list) => CsvColumn::parse_list(list)?,
+        None => CsvColumn::ALL.to_vec(),
+    };
+    let csv = handle_export_csv(&mut *pool.acquire().await?, access.project.id, &columns).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header(ContentDisposition::attachment("tasks.csv"))
+        .body(csv))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/tasks.csv",
+    tag = "projects",
+    summary = "Create and update tasks from csv",
+    description = "Rows with an id update that task, only changing the columns in the file. Rows without one create a task. Nothing is changed if any row has an error.",
+    params(("project_id" = Uuid, Path, description = "Project id"), CsvImportQuery),
+    request_body(content = String, content_type = "text/csv"),
+    responses((status = 200, body = CsvImportMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn import_csv_route(
+    access: ProjectAccess<EditAccess>,
+    pool: web::Data<Pool<Postgres>>,
+    query: web::Query<CsvImportQuery>,
+    body: web::Bytes,
+) -> Result<web::Json<CsvImportMessage>> {
+    let mut t = pool.begin().await?;
+    let report = handle_import_csv(
+        &mut t,
+        &access.user,
+        access.project.id,
+        &body,
+        query.dry_run,
+    )
+    .await?;
+    if report.applied {
+        t.commit().await?;
+    }
+    Ok(web::Json(report))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/export.md",
+    tag = "projects",
+    summary = "Export the board as markdown",
+    description = "A heading per column with its tasks as a checklist, showing labels, assignee and deadline. Subtasks are nested below their parent.",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    responses((status = 200, body = String, content_type = "text/markdown"), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn export_markdown_route(
+    access: ProjectAccess<ReadAccess>,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<HttpResponse> {
+    let md = handle_export_markdown(&mut *pool.acquire().await?, &access.project).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/markdown; charset=utf-8")
+        .body(md))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/export.txt",
+    tag = "projects",
+    summary = "Export the board in todo.txt format",
+    description = "A line per task, labels as +tags, the column as @context and the deadline as due:. Subtasks follow their parent.",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    responses((status = 200, body = String, content_type = "text/plain"), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn export_todo_txt_route(
+    access: ProjectAccess<ReadAccess>,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<HttpResponse> {
+    let txt = handle_export_todo_txt(&mut *pool.acquire().await?, access.project.id).await?;
+    Ok(HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .body(txt))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/clone",
+    tag = "projects",
+    summary = "Copy a project into a new private one",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    request_body = CloneProjectMessage,
+    responses((status = 200, body = ProjectMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn clone_project_route(
+    access: ProjectAccess<ReadAccess>,
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<CloneProjectMessage>,
+) -> Result<web::Json<ProjectMessage>> {
+    // the copy is owned by the caller, who would hand out access to everyone in the project
+    if msg.members && access.permissions < Permissions::Owner {
+        return Err(unauthorized("Only owners can copy the members"));
+    }
+    let mut t = pool.begin().await?;
+    let project = handle_clone_project(&mut t, &access.user, &access.project, msg.0).await?;
+    t.commit().await?;
+    Ok(web::Json(project.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}",
+    tag = "projects",
+    summary = "Get a project with its columns and labels",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    responses((status = 200, body = ProjectMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn get_project_route(
+    access: ProjectAccess<ReadAccess>,
+    pool: web::Data<Pool<Postgres>>,
 ) -> Result<web::Json<ProjectMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let project_id = path.into_inner();
+    let project_id = access.project.id;
     let mut conn = pool.acquire().await?;
-    let proj: ProjectModel = get_project(&mut conn, project_id)
+    let labels = get_labels(&mut conn, project_id)
         .await?
-        .ok_or_else(|| not_found("Project Not Found"))?;
-    let req = Permissions::Reader;
-    let perms = help_get_project_permissions(&user, &proj, &mut conn).await?;
-    if perms <= req {
-        let labels = get_labels(&mut conn, project_id)
-            .await?
-            .into_iter()
-            .map(Into::into)
-            .collect();
-        let columns = get_columns(&mut conn, project_id)
-            .await?
-            .into_iter()
-            .map(Into::into)
-            .collect();
-        let mut msg: ProjectMessage = proj.into();
-        msg.labels = Some(labels);
-        msg.columns = Some(columns);
-        Ok(web::Json(msg))
-    } else {
-        Err(unauthorized("Not allowed"))
-    }
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let columns = get_columns(&mut conn, project_id)
+        .await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    let mut msg: ProjectMessage = access.project.into();
+    msg.labels = Some(labels);
+    msg.columns = Some(columns);
+    Ok(web::Json(msg))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/projects/list",
+    tag = "projects",
+    summary = "List visible projects that aren't archived",
+    params(("page" = Option<i32>, Query, description = "Page, starts at 1"), ("count" = Option<i32>, Query, description = "Items per page, at most 200")),
+    responses((status = 200, body = Page<ProjectMessage>), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn get_projects_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     pag: Pagination,
 ) -> Result<web::Json<Page<ProjectMessage>>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
     let (est, projects) = if user.is_admin {
-        list_projects(&mut *pool.acquire().await?, pag).await?
+        list_projects(&mut *pool.acquire().await?, false, pag).await?
     } else {
-        get_projects(&mut *pool.acquire().await?, user.id, pag).await?
+        get_projects(&mut *pool.acquire().await?, user.id, false, pag).await?
     };
     Ok(web::Json(Page::new(
         projects.into_iter().map(Into::into).collect(),
@@ -127,250 +432,366 @@ async fn get_projects_route(
     )))
 }
 
-async fn delete_project_route(
+#[utoipa::path(
+    get,
+    path = "/api/projects/archived",
+    tag = "projects",
+    summary = "List visible archived projects",
+    params(("page" = Option<i32>, Query, description = "Page, starts at 1"), ("count" = Option<i32>, Query, description = "Items per page, at most 200")),
+    responses((status = 200, body = Page<ProjectMessage>), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn get_archived_projects_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
+    pag: Pagination,
+) -> Result<web::Json<Page<ProjectMessage>>> {
+    let (est, projects) = if user.is_admin {
+        list_projects(&mut *pool.acquire().await?, true, pag).await?
+    } else {
+        get_projects(&mut *pool.acquire().await?, user.id, true, pag).await?
+    };
+    Ok(web::Json(Page::new(
+        projects.into_iter().map(Into::into).collect(),
+        pag,
+        est,
+    )))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/archive",
+    tag = "projects",
+    summary = "Archive a project, it disappears from the project list",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    responses((status = 200, body = ProjectMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn archive_project_route(
+    access: ProjectAccess<OwnerAccess>,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<web::Json<ProjectMessage>> {
+    let mut project = access.project;
+    archive_project(&mut *pool.acquire().await?, &mut project, true).await?;
+    Ok(web::Json(project.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/unarchive",
+    tag = "projects",
+    summary = "Bring an archived project back",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    responses((status = 200, body = ProjectMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn unarchive_project_route(
+    access: ProjectAccess<UnarchiveAccess>,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<web::Json<ProjectMessage>> {
+    let mut project = access.project;
+    archive_project(&mut *pool.acquire().await?, &mut project, false).await?;
+    Ok(web::Json(project.into()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/projects/{project_id}/trash",
+    tag = "projects",
+    summary = "List the deleted tasks, columns, labels and states of a project",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    responses((status = 200, body = Vec<TrashMessage>), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn get_trash_route(
+    access: ProjectAccess<ReadAccess>,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<web::Json<Vec<TrashMessage>>> {
+    let trash = get_trash(&mut *pool.acquire().await?, access.project.id).await?;
+    Ok(web::Json(trash.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/trash/restore",
+    tag = "projects",
+    summary = "Restore a deleted task, column, label or state",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    request_body = RestoreMessage,
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn restore_route(
+    access: ProjectAccess<EditAccess>,
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<RestoreMessage>,
 ) -> Result<web::Json<SuccessMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let project_id = path.into_inner();
     let mut conn = pool.acquire().await?;
-    if user.is_admin
-        || get_project_permission(&mut conn, user.id, project_id).await? >= Permissions::Owner
-    {
-        delete_project(&mut conn, project_id).await?;
-        Ok(web::Json(SuccessMessage::new(true)))
-    } else {
-        Err(unauthorized("Insufficient permissions on Project"))
+    let project_id = access.project.id;
+    let restored = match msg.kind {
+        TrashKind::Task => restore_task(&mut conn, project_id, msg.id).await?,
+        TrashKind::Column => restore_column(&mut conn, project_id, msg.id).await?,
+        TrashKind::Label => restore_label(&mut conn, project_id, msg.id).await?,
+        TrashKind::State => restore_state(&mut conn, project_id, msg.id).await?,
+    };
+    if !restored {
+        return Err(not_found("Nothing to restore in the trash"));
     }
+    Ok(web::Json(SuccessMessage::new(true)))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{project_id}",
+    tag = "projects",
+    summary = "Delete a project",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn delete_project_route(
+    access: ProjectAccess<OwnerAccess>,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<web::Json<SuccessMessage>> {
+    delete_project(&mut *pool.acquire().await?, access.project.id).await?;
+    Ok(web::Json(SuccessMessage::new(true)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/projects/{project_id}",
+    tag = "projects",
+    summary = "Update a project",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    request_body = UpdateProjectMessage,
+    responses((status = 200, body = ProjectMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn update_project_route(
-    user: AuthenticatedUser,
+    access: ProjectAccess<EditAccess>,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<UpdateProjectMessage>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
 ) -> Result<web::Json<ProjectMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let project_id = path.into_inner();
-    let mut conn = pool.acquire().await?;
     let update = msg.0;
-    let mut project = get_project(&mut conn, project_id)
-        .await?
-        .ok_or_else(|| not_found("Project not found"))?;
-    let req = update.get_requirements();
-    let perms = help_get_project_permissions(&user, &project, &mut conn).await?;
-    if perms >= req {
-        update.update_project(&mut project);
-        update_project(&mut conn, &mut project).await?;
-        Ok(web::Json(project.into()))
-    } else {
-        Err(unauthorized("Not Authorized to update project"))
-    }
-}
-
-async fn help_get_project_permissions(
-    user: &AuthenticatedUser,
-    proj: &ProjectModel,
-    conn: &mut PgConnection,
-) -> Result<Permissions> {
-    if user.is_admin {
-        Ok(Permissions::Admin)
-    } else if let Some(perms) = proj.get_permissions(user.id) {
-        Ok(perms)
-    } else {
-        get_project_permission(conn, user.id, proj.id).await
+    if access.permissions < update.get_requirements() {
+        return Err(unauthorized("Not Authorized to update project"));
     }
+    let mut project = access.project;
+    update.update_project(&mut project);
+    update_project(&mut *pool.acquire().await?, &mut project).await?;
+    Ok(web::Json(project.into()))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/columns",
+    tag = "projects",
+    summary = "Create a column",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    request_body = CreateProjectColumnMessage,
+    responses((status = 200, body = ProjectColumnMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn create_column_route(
-    user: AuthenticatedUser,
+    access: ProjectAccess<EditAccess>,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<CreateProjectColumnMessage>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
 ) -> Result<web::Json<ProjectColumnMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let project_id = path.into_inner();
-    let mut conn = pool.acquire().await?;
-    if user.is_admin
-        || get_project_permission(&mut conn, user.id, project_id).await? >= Permissions::Editor
-    {
-        let mut new_column = msg.0.to_model(project_id);
-        create_column(&mut conn, &mut new_column).await?;
-        Ok(web::Json(new_column.into()))
-    } else {
-        Err(unauthorized("Insufficient permissions"))
-    }
+    let mut new = msg.0.to_model(access.project.id);
+    create_column(&mut *pool.acquire().await?, &mut new).await?;
+    Ok(web::Json(new.into()))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/labels",
+    tag = "projects",
+    summary = "Create a label",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    request_body = CreateLabelMessage,
+    responses((status = 200, body = LabelMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn create_label_route(
-    user: AuthenticatedUser,
+    access: ProjectAccess<EditAccess>,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<CreateLabelMessage>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
 ) -> Result<web::Json<LabelMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let project_id = path.into_inner();
-    let mut conn = pool.acquire().await?;
-    if user.is_admin
-        || get_project_permission(&mut conn, user.id, project_id).await? >= Permissions::Editor
-    {
-        let mut new_label = msg.0.to_model(project_id);
-        create_label(&mut conn, &mut new_label).await?;
-        Ok(web::Json(new_label.into()))
-    } else {
-        Err(unauthorized("Insufficient permissions"))
-    }
+    let mut new = msg.0.to_model(access.project.id);
+    create_label(&mut *pool.acquire().await?, &mut new).await?;
+    Ok(web::Json(new.into()))
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/projects/{project_id}/columns/{column_id}",
+    tag = "projects",
+    summary = "Update a column",
+    params(("project_id" = Uuid, Path, description = "Project id"), ("column_id" = Uuid, Path, description = "Column id")),
+    request_body = UpdateProjectColumnMessage,
+    responses((status = 200, body = ProjectColumnMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn update_column_route(
-    user: AuthenticatedUser,
+    access: ProjectAccess<EditAccess>,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<UpdateProjectColumnMessage>,
-    req: HttpRequest,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<web::Json<ProjectColumnMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let (project_id, column_id) = path.into_inner();
+    let (_, column_id) = path.into_inner();
     let mut conn = pool.acquire().await?;
-    if user.is_admin
-        || get_project_permission(&mut conn, user.id, project_id).await? >= Permissions::Editor
-    {
-        let mut col = get_column(&mut conn, column_id)
-            .await?
-            .ok_or_else(|| not_found("Column not found"))?;
-        msg.0.update_model(&mut col);
-        update_column(&mut conn, &col).await?;
-        Ok(web::Json(col.into()))
-    } else {
-        Err(unauthorized("Insufficient permissions"))
-    }
+    let mut column = get_column(&mut conn, access.project.id, column_id)
+        .await?
+        .ok_or_else(|| not_found("Column not found"))?;
+    msg.0.update_model(&mut column);
+    update_column(&mut conn, &column).await?;
+    Ok(web::Json(column.into()))
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/projects/{project_id}/labels/{label_id}",
+    tag = "projects",
+    summary = "Update a label",
+    params(("project_id" = Uuid, Path, description = "Project id"), ("label_id" = Uuid, Path, description = "Label id")),
+    request_body = UpdateLabelMessage,
+    responses((status = 200, body = LabelMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn update_label_route(
-    user: AuthenticatedUser,
+    access: ProjectAccess<EditAccess>,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<UpdateLabelMessage>,
-    req: HttpRequest,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<web::Json<LabelMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let (project_id, label_id) = path.into_inner();
+    let (_, label_id) = path.into_inner();
     let mut conn = pool.acquire().await?;
-    if user.is_admin
-        || get_project_permission(&mut conn, user.id, project_id).await? >= Permissions::Editor
-    {
-        let mut col = get_label(&mut conn, label_id)
-            .await?
-            .ok_or_else(|| not_found("label not found"))?;
-        msg.0.update_model(&mut col);
-        update_label(&mut conn, &col).await?;
-        Ok(web::Json(col.into()))
-    } else {
-        Err(unauthorized("Insufficient permissions"))
-    }
+    let mut label = get_label(&mut conn, access.project.id, label_id)
+        .await?
+        .ok_or_else(|| not_found("label not found"))?;
+    msg.0.update_model(&mut label);
+    update_label(&mut conn, &label).await?;
+    Ok(web::Json(label.into()))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{project_id}/columns/{column_id}",
+    tag = "projects",
+    summary = "Move a column to the trash",
+    params(("project_id" = Uuid, Path, description = "Project id"), ("column_id" = Uuid, Path, description = "Column id")),
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn delete_column_route(
-    user: AuthenticatedUser,
+    access: ProjectAccess<EditAccess>,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<web::Json<SuccessMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let (project_id, column_id) = path.into_inner();
-    let mut conn = pool.acquire().await?;
-    if user.is_admin
-        || get_project_permission(&mut conn, user.id, project_id).await? >= Permissions::Editor
-    {
-        delete_column(&mut conn, column_id).await?;
-        Ok(web::Json(SuccessMessage::new(true)))
-    } else {
-        Err(unauthorized("Insufficient permissions"))
+    let (_, column_id) = path.into_inner();
+    if !delete_column(&mut *pool.acquire().await?, access.project.id, column_id).await? {
+        return Err(not_found("Column not found"));
     }
+    Ok(web::Json(SuccessMessage::new(true)))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{project_id}/labels/{label_id}",
+    tag = "projects",
+    summary = "Move a label to the trash",
+    params(("project_id" = Uuid, Path, description = "Project id"), ("label_id" = Uuid, Path, description = "Label id")),
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn delete_label_route(
-    user: AuthenticatedUser,
+    access: ProjectAccess<EditAccess>,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<web::Json<SuccessMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let (project_id, label_id) = path.into_inner();
-    let mut conn = pool.acquire().await?;
-    if user.is_admin
-        || get_project_permission(&mut conn, user.id, project_id).await? >= Permissions::Editor
-    {
-        delete_label(&mut conn, label_id).await?;
-        Ok(web::Json(SuccessMessage::new(true)))
-    } else {
-        Err(unauthorized("Insufficient permissions"))
+    let (_, label_id) = path.into_inner();
+    if !delete_label(&mut *pool.acquire().await?, access.project.id, label_id).await? {
+        return Err(not_found("label not found"));
     }
+    Ok(web::Json(SuccessMessage::new(true)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/projects/{project_id}/states",
+    tag = "projects",
+    summary = "Create a state",
+    params(("project_id" = Uuid, Path, description = "Project id")),
+    request_body = CreateStateMessage,
+    responses((status = 200, body = StateMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn create_state_route(
-    user: AuthenticatedUser,
+    access: ProjectAccess<EditAccess>,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<CreateStateMessage>,
-    req: HttpRequest,
-    path: web::Path<Uuid>,
 ) -> Result<web::Json<StateMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let project_id = path.into_inner();
-    let mut conn = pool.acquire().await?;
-    if user.is_admin
-        || get_project_permission(&mut conn, user.id, project_id).await? >= Permissions::Editor
-    {
-        let mut new_state = msg.0.to_model(project_id);
-        create_state(&mut conn, &mut new_state).await?;
-        Ok(web::Json(new_state.into()))
-    } else {
-        Err(unauthorized("Insufficient permissions"))
-    }
+    let mut new = msg.0.to_model(access.project.id);
+    create_state(&mut *pool.acquire().await?, &mut new).await?;
+    Ok(web::Json(new.into()))
 }
+#[utoipa::path(
+    put,
+    path = "/api/projects/{project_id}/states/{state_id}",
+    tag = "projects",
+    summary = "Update a state",
+    params(("project_id" = Uuid, Path, description = "Project id"), ("state_id" = Uuid, Path, description = "State id")),
+    request_body = UpdateStateMessage,
+    responses((status = 200, body = StateMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn update_state_route(
-    user: AuthenticatedUser,
+    access: ProjectAccess<EditAccess>,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<UpdateStateMessage>,
-    req: HttpRequest,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<web::Json<StateMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let (project_id, state_id) = path.into_inner();
+    let (_, state_id) = path.into_inner();
     let mut conn = pool.acquire().await?;
-    if user.is_admin
-        || get_project_permission(&mut conn, user.id, project_id).await? >= Permissions::Editor
-    {
-        let mut state = get_state(&mut conn, state_id)
-            .await?
-            .ok_or_else(|| not_found("state not found"))?;
-        msg.0.update_model(&mut state);
-        update_state(&mut conn, &state).await?;
-        Ok(web::Json(state.into()))
-    } else {
-        Err(unauthorized("Insufficient permissions"))
-    }
+    let mut state = get_state(&mut conn, access.project.id, state_id)
+        .await?
+        .ok_or_else(|| not_found("state not found"))?;
+    msg.0.update_model(&mut state);
+    update_state(&mut conn, &state).await?;
+    Ok(web::Json(state.into()))
 }
+#[utoipa::path(
+    delete,
+    path = "/api/projects/{project_id}/states/{state_id}",
+    tag = "projects",
+    summary = "Move a state to the trash",
+    params(("project_id" = Uuid, Path, description = "Project id"), ("state_id" = Uuid, Path, description = "State id")),
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn delete_state_route(
-    user: AuthenticatedUser,
+    access: ProjectAccess<EditAccess>,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<web::Json<SuccessMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-    let (project_id, state_id) = path.into_inner();
-    let mut conn = pool.acquire().await?;
-    if user.is_admin
-        || get_project_permission(&mut conn, user.id, project_id).await? >= Permissions::Editor
-    {
-        delete_state(&mut conn, state_id).await?;
-        Ok(web::Json(SuccessMessage::new(true)))
-    } else {
-        Err(unauthorized("Insufficient permissions"))
+    let (_, state_id) = path.into_inner();
+    if !delete_state(&mut *pool.acquire().await?, access.project.id, state_id).await? {
+        return Err(not_found("state not found"));
     }
+    Ok(web::Json(SuccessMessage::new(true)))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CsvExportQuery {
+    /// Comma separated, e.g. "title,column,labels". The id always comes first, all columns by default
+    pub columns: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct CsvImportQuery {
+    /// Only report what would happen
+    #[serde(default)]
+    pub dry_run: bool,
 }
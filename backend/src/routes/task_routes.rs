@@ -1,138 +1,155 @@
-use actix_web::{web, HttpRequest};
+use actix_web::{http::Method, web};
 use sqlx::{Pool, Postgres};
+use utoipa::OpenApi;
 use uuid::Uuid;
 
 use crate::data::models::{AuthenticatedUser, Permissions};
-use crate::data::project_data::get_project_permission;
 use crate::data::task_data::{
-    add_task_label, create_task, get_task, get_task_labels, remove_task_label, update_task,
+    add_task_label, create_task, delete_task, get_task, get_task_labels, update_task,
 };
-use crate::error::{not_found, unauthorized, Result};
-use crate::handler::user_handler::handle_token_update;
+use crate::error::{not_found, Result};
+use crate::handler::project_handler::handle_project_access;
+use crate::handler::task_handler::{handle_set_task_labels, handle_validate_task_references};
+use crate::logging::ErrorMessage;
 use crate::messages::general_messages::SuccessMessage;
 use crate::messages::task_massages::{CreateOrUpdateTaskMessage, TaskMessage};
+use crate::routes::Routes;
 
-pub fn register_task_routes(cfg: &mut web::ServiceConfig) {
-    // cfg.service(web::scope("/user").route("/new", web::post().to(create_user)));
-    cfg.service(
-        web::scope("/tasks")
-            .route("", web::post().to(create_task_route))
-            .route("/{task_id}", web::post().to(update_task_route))
-            .route("/{task_id}", web::delete().to(delete_task_route))
-            .route("/{task_id}", web::get().to(get_task_route)),
-    );
+pub fn register_task_routes(routes: &mut Routes) {
+    routes
+        .scope("/tasks")
+        .route(Method::POST, "", create_task_route)
+        .route(Method::POST, "/{task_id}", update_task_route)
+        .route(Method::DELETE, "/{task_id}", delete_task_route)
+        .route(Method::GET, "/{task_id}", get_task_route);
 }
 
+#[derive(OpenApi)]
+#[openapi(paths(
+    create_task_route,
+    update_task_route,
+    delete_task_route,
+    get_task_route
+))]
+pub struct TaskApi;
+
+#[utoipa::path(
+    post,
+    path = "/api/tasks",
+    tag = "tasks",
+    summary = "Create a task",
+    request_body = CreateOrUpdateTaskMessage,
+    responses((status = 200, body = TaskMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn create_task_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     mut msg: web::Json<CreateOrUpdateTaskMessage>,
 ) -> Result<web::Json<TaskMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
-
-    let labels = msg.0.labels.take().unwrap_or_default();
+    let mut labels = msg.0.labels.take().unwrap_or_default();
+    labels.sort();
+    labels.dedup();
     let mut task = msg.0.to_model();
     let mut t = pool.begin().await?;
-    let perm = get_project_permission(&mut t, user.id, task.project_id).await?;
-
-    if perm >= Permissions::Editor {
-        create_task(&mut t, &mut task).await?;
-        for id in &labels {
-            add_task_label(&mut t, task.id, *id).await?;
-        }
-        let mut rmsg: TaskMessage = task.into();
-        rmsg.labels = Some(labels);
-        Ok(web::Json(rmsg))
-    } else {
-        Err(unauthorized("You shall not pass"))
+    handle_project_access(&mut t, &user, task.project_id, Permissions::Editor).await?;
+    handle_validate_task_references(&mut t, &task, &labels).await?;
+    create_task(&mut t, &mut task).await?;
+    for id in &labels {
+        add_task_label(&mut t, task.id, *id).await?;
     }
+    t.commit().await?;
+    let mut rmsg: TaskMessage = task.into();
+    rmsg.labels = Some(labels);
+    Ok(web::Json(rmsg))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/tasks/{task_id}",
+    tag = "tasks",
+    summary = "Update a task",
+    params(("task_id" = Uuid, Path, description = "Task id")),
+    request_body = CreateOrUpdateTaskMessage,
+    responses((status = 200, body = TaskMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn update_task_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     mut msg: web::Json<CreateOrUpdateTaskMessage>,
     path: web::Path<Uuid>,
 ) -> Result<web::Json<TaskMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
     let mut t = pool.begin().await?;
     let task_id = path.into_inner();
-    let new_labels = msg.0.labels.take().unwrap_or_default();
+    let mut new_labels = msg.0.labels.take().unwrap_or_default();
+    new_labels.sort();
+    new_labels.dedup();
     let mut task = get_task(&mut t, task_id)
         .await?
         .ok_or_else(|| not_found("Task not found"))?;
+    handle_project_access(&mut t, &user, task.project_id, Permissions::Editor).await?;
     msg.0.update_model(&mut task);
-    let perm = get_project_permission(&mut t, user.id, task.project_id).await?;
-
-    let old_labels = get_task_labels(&mut t, task.id).await?;
-    if perm >= Permissions::Editor {
-        update_task(&mut t, &mut task).await?;
-        for new_id in new_labels
-            .iter()
-            .filter(|x| !old_labels.iter().any(|y| y.id == **x))
-        {
-            add_task_label(&mut t, task.id, *new_id).await?;
-        }
+    handle_validate_task_references(&mut t, &task, &new_labels).await?;
 
-        for old_label in old_labels
-            .iter()
-            .filter(|x| !new_labels.iter().any(|y| x.id == *y))
-        {
-            remove_task_label(&mut t, task.id, old_label.id).await?;
-        }
-        let mut rmsg: TaskMessage = task.into();
-        rmsg.labels = Some(new_labels);
-        Ok(web::Json(rmsg))
-    } else {
-        Err(unauthorized("You shall not pass"))
-    }
+    update_task(&mut t, &mut task).await?;
+    handle_set_task_labels(&mut t, task.id, &new_labels).await?;
+    t.commit().await?;
+    let mut rmsg: TaskMessage = task.into();
+    rmsg.labels = Some(new_labels);
+    Ok(web::Json(rmsg))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/tasks/{task_id}",
+    tag = "tasks",
+    summary = "Move a task and its subtasks to the trash",
+    params(("task_id" = Uuid, Path, description = "Task id")),
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn delete_task_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     path: web::Path<Uuid>,
 ) -> Result<web::Json<SuccessMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
     let task_id = path.into_inner();
     let mut conn = pool.acquire().await?;
     let task = get_task(&mut conn, task_id)
         .await?
         .ok_or_else(|| not_found("task not found"))?;
-    let perms = get_project_permission(&mut conn, user.id, task.project_id).await?;
-    if perms >= Permissions::Editor {
-        Ok(web::Json(SuccessMessage::new(true)))
-    } else {
-        Err(unauthorized("You cannot do that"))
-    }
+    handle_project_access(&mut conn, &user, task.project_id, Permissions::Editor).await?;
+    delete_task(&mut conn, task_id).await?;
+    Ok(web::Json(SuccessMessage::new(true)))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/tasks/{task_id}",
+    tag = "tasks",
+    summary = "Get a task with its labels",
+    params(("task_id" = Uuid, Path, description = "Task id")),
+    responses((status = 200, body = TaskMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn get_task_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     path: web::Path<Uuid>,
 ) -> Result<web::Json<TaskMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
     let task_id = path.into_inner();
     let mut conn = pool.acquire().await?;
     let task = get_task(&mut conn, task_id)
         .await?
         .ok_or_else(|| not_found("task not found"))?;
-    let perms = get_project_permission(&mut conn, user.id, task.project_id).await?;
-    if perms >= Permissions::Reader {
-        let labels = get_task_labels(&mut conn, task.id)
-            .await?
-            .into_iter()
-            .map(|x| x.id)
-            .collect();
-        let mut rmsg: TaskMessage = task.into();
-        rmsg.labels = Some(labels);
-        Ok(web::Json(rmsg))
-    } else {
-        Err(unauthorized("You cannot do that"))
-    }
+    handle_project_access(&mut conn, &user, task.project_id, Permissions::Reader).await?;
+    let labels = get_task_labels(&mut conn, task.id)
+        .await?
+        .into_iter()
+        .map(|x| x.id)
+        .collect();
+    let mut rmsg: TaskMessage = task.into();
+    rmsg.labels = Some(labels);
+    Ok(web::Json(rmsg))
 }
@@ -0,0 +1,161 @@
+use actix_web::{http::Method, web};
+use sqlx::{Pool, Postgres};
+use utoipa::OpenApi;
+use uuid::Uuid;
+
+use crate::data::models::AuthenticatedUser;
+use crate::data::template_data::{delete_template, get_template, get_templates, update_template};
+use crate::error::{not_found, unauthorized, Result};
+use crate::handler::template_handler::handle_create_template;
+use crate::logging::ErrorMessage;
+use crate::messages::general_messages::SuccessMessage;
+use crate::messages::template_messages::{
+    CreateTemplateMessage, TemplateMessage, UpdateTemplateMessage,
+};
+use crate::routes::Routes;
+use crate::util::{Page, Pagination};
+
+pub fn register_template_routes(routes: &mut Routes) {
+    routes
+        .scope("/templates")
+        .route(Method::POST, "", create_template_route)
+        .route(Method::GET, "/list", get_templates_route)
+        .route(Method::GET, "/{template_id}", get_template_route)
+        .route(Method::PUT, "/{template_id}", update_template_route)
+        .route(Method::DELETE, "/{template_id}", delete_template_route);
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    create_template_route,
+    get_templates_route,
+    get_template_route,
+    update_template_route,
+    delete_template_route
+))]
+pub struct TemplateApi;
+
+#[utoipa::path(
+    post,
+    path = "/api/templates",
+    tag = "templates",
+    summary = "Save the structure of a project as a template",
+    request_body = CreateTemplateMessage,
+    responses((status = 200, body = TemplateMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn create_template_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<CreateTemplateMessage>,
+) -> Result<web::Json<TemplateMessage>> {
+    let template = handle_create_template(&mut *pool.acquire().await?, &user, msg.0).await?;
+    Ok(web::Json(template.try_into()?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/list",
+    tag = "templates",
+    summary = "List the published templates and your own",
+    params(("page" = Option<i32>, Query, description = "Page, starts at 1"), ("count" = Option<i32>, Query, description = "Items per page, at most 200")),
+    responses((status = 200, body = Page<TemplateMessage>), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn get_templates_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    pag: Pagination,
+) -> Result<web::Json<Page<TemplateMessage>>> {
+    let (est, templates) =
+        get_templates(&mut *pool.acquire().await?, user.id, user.is_admin, pag).await?;
+    Ok(web::Json(Page::new(
+        templates
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?,
+        pag,
+        est,
+    )))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/{template_id}",
+    tag = "templates",
+    summary = "Get a template",
+    params(("template_id" = Uuid, Path, description = "Template id")),
+    responses((status = 200, body = TemplateMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn get_template_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<TemplateMessage>> {
+    let template = get_template(
+        &mut *pool.acquire().await?,
+        path.into_inner(),
+        user.id,
+        user.is_admin,
+    )
+    .await?
+    .ok_or_else(|| not_found("Template not found"))?;
+    Ok(web::Json(template.try_into()?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/templates/{template_id}",
+    tag = "templates",
+    summary = "Rename a template, admins can publish it",
+    params(("template_id" = Uuid, Path, description = "Template id")),
+    request_body = UpdateTemplateMessage,
+    responses((status = 200, body = TemplateMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn update_template_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<UpdateTemplateMessage>,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<TemplateMessage>> {
+    let mut conn = pool.acquire().await?;
+    let mut template = get_template(&mut conn, path.into_inner(), user.id, user.is_admin)
+        .await?
+        .ok_or_else(|| not_found("Template not found"))?;
+    if !template.can_edit(&user) {
+        return Err(unauthorized("Not Authorized to update template"));
+    }
+    if msg.published.is_some() {
+        user.is_admin()?;
+    }
+    msg.0.update_model(&mut template);
+    update_template(&mut conn, &template).await?;
+    Ok(web::Json(template.try_into()?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/templates/{template_id}",
+    tag = "templates",
+    summary = "Delete a template",
+    params(("template_id" = Uuid, Path, description = "Template id")),
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn delete_template_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<SuccessMessage>> {
+    let mut conn = pool.acquire().await?;
+    let template = get_template(&mut conn, path.into_inner(), user.id, user.is_admin)
+        .await?
+        .ok_or_else(|| not_found("Template not found"))?;
+    if !template.can_edit(&user) {
+        return Err(unauthorized("Not Authorized to delete template"));
+    }
+    delete_template(&mut conn, template.id).await?;
+    Ok(web::Json(SuccessMessage::new(true)))
+}
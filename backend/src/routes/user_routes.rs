@@ -1,120 +1,498 @@
-use actix_web::{web, HttpRequest};
-use base64::prelude::BASE64_STANDARD;
-use base64::Engine;
-use rand::rngs::OsRng;
-use rand::RngCore;
+use actix_web::{http::Method, web, HttpRequest};
 
 use serde::Deserialize;
 use sqlx::{Pool, Postgres};
+use utoipa::{IntoParams, OpenApi};
 use uuid::Uuid;
 
-use crate::data::models::{AuthenticatedUser, Permissions, UserModel, UserSessionModel};
+use crate::config::Config;
+use crate::data::models::{AuthenticatedUser, LoginAttemptKind, Permissions, UserModel};
 use crate::data::user_data::{
-    check_username_available, create_user, create_user_session, delete_user, get_user_by_id,
-    get_user_by_name, get_users, update_user,
+    check_username_available, clear_login_failures, create_user, delete_personal_token,
+    delete_user, get_personal_tokens, get_user_by_id, get_users, set_feed_token, update_user,
 };
-use crate::error::{bad_request, not_found, unauthorized, Result};
-use crate::handler::user_handler::handle_token_update;
+use crate::error::{not_found, unauthorized, Result};
+use crate::handler::auth_provider::AuthProviders;
+use crate::handler::oidc_handler::{handle_oidc_callback, handle_oidc_start, OidcProvider};
+use crate::handler::user_handler::{
+    handle_create_feed_token, handle_create_personal_token, handle_login_second_factor,
+    handle_password_forgot, handle_password_login, handle_password_reset, handle_set_user_enabled,
+    handle_totp_confirm, handle_totp_disable, handle_totp_enroll,
+};
+use crate::logging::ErrorMessage;
+use crate::messages::feed_messages::FeedTokenMessage;
 use crate::messages::general_messages::SuccessMessage;
 use crate::messages::user_messages::{
-    CreateUserMessage, LoginMessage, LoginTokenMessage, UpdateUserMessage, UserMessage,
+    CreatePersonalTokenMessage, CreateUserMessage, ForgotPasswordMessage, LoginMessage,
+    LoginSecondFactorMessage, LoginTokenMessage, OidcCallbackMessage, OidcStartMessage,
+    PersonalTokenMessage, RecoveryCodesMessage, ResetPasswordMessage, TotpCodeMessage,
+    TotpEnrollMessage, UpdateUserMessage, UserMessage,
 };
+use crate::routes::Routes;
 use crate::util::{Page, Pagination};
-pub fn register_user_routes(cfg: &mut web::ServiceConfig) {
-    // cfg.service(web::scope("/user").route("/new", web::post().to(create_user)));
-    cfg.service(
-        web::scope("/users")
-            .route("", web::post().to(create_user_route))
-            .route("", web::put().to(update_user_route))
-            .route("/list", web::get().to(get_users_route))
-            .route(
-                "/available/{username}",
-                web::get().to(username_available_route),
-            )
-            .route("/{id}", web::delete().to(delete_user_route))
-            .route("", web::get().to(get_user_route)),
-    );
-    cfg.route("/login", web::post().to(login_route));
-}
-
-// #[post("user/new/{invite_code}")]
+pub fn register_user_routes(routes: &mut Routes) {
+    routes
+        .scope("/users")
+        .route(Method::POST, "", create_user_route)
+        .route(Method::PUT, "", update_user_route)
+        .route(Method::GET, "/list", get_users_route)
+        .route(
+            Method::GET,
+            "/available/{username}",
+            username_available_route,
+        )
+        .route(Method::POST, "/totp/enroll", totp_enroll_route)
+        .route(Method::POST, "/totp/confirm", totp_confirm_route)
+        .route(Method::POST, "/totp/disable", totp_disable_route)
+        .route(Method::POST, "/feed", create_feed_token_route)
+        .route(Method::DELETE, "/feed", delete_feed_token_route)
+        .route(Method::POST, "/tokens", create_personal_token_route)
+        .route(Method::GET, "/tokens", get_personal_tokens_route)
+        .route(
+            Method::DELETE,
+            "/tokens/{token_id}",
+            delete_personal_token_route,
+        )
+        .route(Method::DELETE, "/{id}", delete_user_route)
+        .route(Method::POST, "/{id}/unlock", unlock_user_route)
+        .route(Method::POST, "/{id}/disable", disable_user_route)
+        .route(Method::POST, "/{id}/enable", enable_user_route)
+        .route(Method::GET, "", get_user_route);
+    routes
+        .route(Method::POST, "/login", login_route)
+        .route(Method::POST, "/login/totp", login_second_factor_route)
+        .route(Method::GET, "/login/oidc", oidc_start_route)
+        .route(Method::POST, "/login/oidc/callback", oidc_callback_route);
+    routes
+        .scope("/password")
+        .route(Method::POST, "/forgot", forgot_password_route)
+        .route(Method::POST, "/reset", reset_password_route);
+}
+
+#[derive(OpenApi)]
+#[openapi(paths(
+    create_user_route,
+    login_route,
+    login_second_factor_route,
+    oidc_start_route,
+    oidc_callback_route,
+    unlock_user_route,
+    totp_enroll_route,
+    totp_confirm_route,
+    totp_disable_route,
+    create_feed_token_route,
+    delete_feed_token_route,
+    create_personal_token_route,
+    get_personal_tokens_route,
+    delete_personal_token_route,
+    forgot_password_route,
+    reset_password_route,
+    disable_user_route,
+    enable_user_route,
+    update_user_route,
+    get_users_route,
+    username_available_route,
+    delete_user_route,
+    get_user_route
+))]
+pub struct UserApi;
+
+#[utoipa::path(
+    post,
+    path = "/api/users",
+    tag = "users",
+    summary = "Create a user, admin only",
+    request_body = CreateUserMessage,
+    responses((status = 200, body = UserMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn create_user_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<CreateUserMessage>,
-    req: HttpRequest,
 ) -> Result<web::Json<UserMessage>> {
     log::info!("test");
-    handle_token_update(&pool, &user, &user.token, &req).await?;
     user.is_admin()?;
     let mut new_user = UserModel::from(msg.0);
     create_user(&mut *pool.acquire().await?, &mut new_user).await?;
     Ok(web::Json(new_user.into()))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/login",
+    tag = "auth",
+    summary = "Log in with username and password",
+    request_body = LoginMessage,
+    responses((status = 200, body = LoginTokenMessage), (status = "default", description = "Error", body = ErrorMessage))
+)]
 async fn login_route(
+    config: web::Data<Config>,
+    providers: web::Data<AuthProviders>,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<LoginMessage>,
     req: HttpRequest,
 ) -> Result<web::Json<LoginTokenMessage>> {
     let mut conn = pool.acquire().await?;
-    let user = get_user_by_name(&mut conn, &msg.username)
-        .await?
-        .unwrap_or_default();
-    let user_agent = req
-        .headers()
-        .get("User-Agent")
-        .ok_or(bad_request("Missing User Agent Header"))?
-        .to_str()?;
-    let ip_addr = req
-        .peer_addr()
-        .ok_or(bad_request("IP Address Missing"))?
-        .to_string();
-    let mut token_bin = [0u8; 64];
-    OsRng.fill_bytes(&mut token_bin);
-    let token = BASE64_STANDARD.encode(token_bin);
-    if user.check_password(&msg.password)? {
-        create_user_session(
+    Ok(web::Json(
+        handle_password_login(
+            &config,
+            &providers,
             &mut conn,
-            UserSessionModel::new(&token, user_agent, ip_addr, user.id),
+            &msg.username,
+            &msg.password,
+            &req,
         )
-        .await?;
-        Ok(web::Json(LoginTokenMessage::new(&token)))
-    } else {
-        Err(bad_request("username or password wrong"))
+        .await?,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/login/totp",
+    tag = "auth",
+    summary = "Finish a login with a TOTP or recovery code",
+    request_body = LoginSecondFactorMessage,
+    responses((status = 200, body = LoginTokenMessage), (status = "default", description = "Error", body = ErrorMessage))
+)]
+async fn login_second_factor_route(
+    config: web::Data<Config>,
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<LoginSecondFactorMessage>,
+    req: HttpRequest,
+) -> Result<web::Json<LoginTokenMessage>> {
+    Ok(web::Json(
+        handle_login_second_factor(&config, &pool, &msg.challenge, &msg.code, &req).await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/login/oidc",
+    tag = "auth",
+    summary = "Start an OIDC login",
+    responses((status = 200, body = OidcStartMessage), (status = "default", description = "Error", body = ErrorMessage))
+)]
+async fn oidc_start_route(
+    pool: web::Data<Pool<Postgres>>,
+    oidc: web::Data<Option<OidcProvider>>,
+) -> Result<web::Json<OidcStartMessage>> {
+    let provider = oidc
+        .as_ref()
+        .as_ref()
+        .ok_or_else(|| not_found("OIDC login not configured"))?;
+    Ok(web::Json(OidcStartMessage {
+        url: handle_oidc_start(&pool, provider).await?,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/login/oidc/callback",
+    tag = "auth",
+    summary = "Finish an OIDC login",
+    request_body = OidcCallbackMessage,
+    responses((status = 200, body = LoginTokenMessage), (status = "default", description = "Error", body = ErrorMessage))
+)]
+async fn oidc_callback_route(
+    pool: web::Data<Pool<Postgres>>,
+    oidc: web::Data<Option<OidcProvider>>,
+    msg: web::Json<OidcCallbackMessage>,
+    req: HttpRequest,
+) -> Result<web::Json<LoginTokenMessage>> {
+    let provider = oidc
+        .as_ref()
+        .as_ref()
+        .ok_or_else(|| not_found("OIDC login not configured"))?;
+    Ok(web::Json(
+        handle_oidc_callback(&pool, provider, &msg.code, &msg.state, &req).await?,
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/users/{id}/unlock",
+    tag = "users",
+    summary = "Clear failed logins of a user, admin only",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn unlock_user_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<SuccessMessage>> {
+    user.is_admin()?;
+    let mut conn = pool.acquire().await?;
+    let locked_user = get_user_by_id(&mut conn, path.into_inner())
+        .await?
+        .ok_or_else(|| not_found("User not found"))?;
+    clear_login_failures(&mut conn, LoginAttemptKind::User, &locked_user.username).await?;
+    Ok(web::Json(SuccessMessage::new(true)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/users/totp/enroll",
+    tag = "auth",
+    summary = "Start TOTP enrollment",
+    responses((status = 200, body = TotpEnrollMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn totp_enroll_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<web::Json<TotpEnrollMessage>> {
+    Ok(web::Json(handle_totp_enroll(&pool, user.user).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/users/totp/confirm",
+    tag = "auth",
+    summary = "Confirm TOTP enrollment",
+    request_body = TotpCodeMessage,
+    responses((status = 200, body = RecoveryCodesMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn totp_confirm_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<TotpCodeMessage>,
+) -> Result<web::Json<RecoveryCodesMessage>> {
+    let codes = handle_totp_confirm(&pool, user.user, &msg.code).await?;
+    Ok(web::Json(RecoveryCodesMessage { codes }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/users/totp/disable",
+    tag = "auth",
+    summary = "Disable TOTP",
+    request_body = TotpCodeMessage,
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn totp_disable_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<TotpCodeMessage>,
+) -> Result<web::Json<SuccessMessage>> {
+    handle_totp_disable(&pool, user.user, &msg.code).await?;
+    Ok(web::Json(SuccessMessage::new(true)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/users/feed",
+    tag = "users",
+    summary = "Create or replace your calendar feed token",
+    responses((status = 200, body = FeedTokenMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn create_feed_token_route(
+    user: AuthenticatedUser,
+    config: web::Data<Config>,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<web::Json<FeedTokenMessage>> {
+    Ok(web::Json(
+        handle_create_feed_token(&config, &pool, &user).await?,
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/users/feed",
+    tag = "users",
+    summary = "Revoke your calendar feed token",
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn delete_feed_token_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<web::Json<SuccessMessage>> {
+    set_feed_token(&mut *pool.acquire().await?, user.id, None).await?;
+    Ok(web::Json(SuccessMessage::new(true)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/users/tokens",
+    tag = "users",
+    summary = "Create a personal token",
+    description = "For clients that can't log in, like caldav apps. They send your username with the token as password.",
+    request_body = CreatePersonalTokenMessage,
+    responses((status = 200, body = PersonalTokenMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn create_personal_token_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<CreatePersonalTokenMessage>,
+) -> Result<web::Json<PersonalTokenMessage>> {
+    Ok(web::Json(
+        handle_create_personal_token(&pool, &user, &msg.name).await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/users/tokens",
+    tag = "users",
+    summary = "List your personal tokens",
+    responses((status = 200, body = Vec<PersonalTokenMessage>), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn get_personal_tokens_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+) -> Result<web::Json<Vec<PersonalTokenMessage>>> {
+    let tokens = get_personal_tokens(&mut *pool.acquire().await?, user.id).await?;
+    Ok(web::Json(tokens.into_iter().map(Into::into).collect()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/users/tokens/{token_id}",
+    tag = "users",
+    summary = "Revoke a personal token",
+    params(("token_id" = Uuid, Path, description = "Token id")),
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn delete_personal_token_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<SuccessMessage>> {
+    if !delete_personal_token(&mut *pool.acquire().await?, user.id, path.into_inner()).await? {
+        return Err(not_found("Token not found"));
     }
+    Ok(web::Json(SuccessMessage::new(true)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/password/forgot",
+    tag = "auth",
+    summary = "Send a password reset mail",
+    request_body = ForgotPasswordMessage,
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage))
+)]
+async fn forgot_password_route(
+    config: web::Data<Config>,
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<ForgotPasswordMessage>,
+) -> Result<web::Json<SuccessMessage>> {
+    // answering before the lookup and the mail keeps timing and smtp errors from telling
+    // whether the address has an account
+    let pool = pool.get_ref().clone();
+    actix_web::rt::spawn(async move {
+        if let Err(e) = handle_password_forgot(&config, &pool, &msg.email).await {
+            log::warn!("password reset mail failed: {e}");
+        }
+    });
+    Ok(web::Json(SuccessMessage::new(true)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/password/reset",
+    tag = "auth",
+    summary = "Set a new password with a reset token",
+    request_body = ResetPasswordMessage,
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage))
+)]
+async fn reset_password_route(
+    pool: web::Data<Pool<Postgres>>,
+    msg: web::Json<ResetPasswordMessage>,
+) -> Result<web::Json<SuccessMessage>> {
+    handle_password_reset(&pool, &msg.token, &msg.new_password).await?;
+    Ok(web::Json(SuccessMessage::new(true)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/users/{id}/disable",
+    tag = "users",
+    summary = "Disable a user",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses((status = 200, body = UserMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn disable_user_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<UserMessage>> {
+    let disabled = handle_set_user_enabled(&pool, &user, path.into_inner(), false).await?;
+    Ok(web::Json(disabled.into()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/users/{id}/enable",
+    tag = "users",
+    summary = "Enable a user",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses((status = 200, body = UserMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
+async fn enable_user_route(
+    user: AuthenticatedUser,
+    pool: web::Data<Pool<Postgres>>,
+    path: web::Path<Uuid>,
+) -> Result<web::Json<UserMessage>> {
+    let enabled = handle_set_user_enabled(&pool, &user, path.into_inner(), true).await?;
+    Ok(web::Json(enabled.into()))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/users",
+    tag = "users",
+    summary = "Update the current or, for admins, another user",
+    request_body = UpdateUserMessage,
+    responses((status = 200, body = UserMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn update_user_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
     msg: web::Json<UpdateUserMessage>,
-    req: HttpRequest,
 ) -> Result<web::Json<UserMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
     msg.validate(&user)?;
     let req = msg.get_requirements(user.id);
     let perms = get_user_permissions(&user, msg.id.unwrap_or(user.id));
-    if perms <= req {
+    if perms < req {
         Err(unauthorized("You cannot do this"))
     } else {
         let mut conn = pool.acquire().await?;
         let mut mod_user = get_user_by_id(&mut conn, msg.id.unwrap_or(user.id))
             .await?
             .ok_or_else(|| not_found("User not found"))?;
-        msg.0.update_user(&mut mod_user)?;
+        msg.0.update_user(&mut mod_user, user.is_admin)?;
         update_user(&mut conn, &mod_user).await?;
         Ok(web::Json(mod_user.into()))
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/users/list",
+    tag = "users",
+    summary = "List users, admin only",
+    params(("page" = Option<i32>, Query, description = "Page, starts at 1"), ("count" = Option<i32>, Query, description = "Items per page, at most 200")),
+    responses((status = 200, body = Page<UserMessage>), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn get_users_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
     pag: Pagination,
-    req: HttpRequest,
 ) -> Result<web::Json<Page<UserMessage>>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
     user.is_admin()?;
     let (estimate, users) = get_users(&mut *pool.acquire().await?, pag).await?;
     Ok(web::Json(Page::new(
@@ -124,26 +502,39 @@ async fn get_users_route(
     )))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/users/available/{username}",
+    tag = "users",
+    summary = "Check if a username is free",
+    params(("username" = String, Path, description = "Username")),
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn username_available_route(
-    user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     path: web::Path<String>,
 ) -> Result<web::Json<SuccessMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
     let username = path.into_inner();
     Ok(web::Json(SuccessMessage::new(
         check_username_available(&mut *pool.acquire().await?, &username).await?,
     )))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/users/{id}",
+    tag = "users",
+    summary = "Delete a user",
+    params(("id" = Uuid, Path, description = "User id")),
+    responses((status = 200, body = SuccessMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn delete_user_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     path: web::Path<Uuid>,
 ) -> Result<web::Json<SuccessMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
     let id = path.into_inner();
     if user.is_admin || user.id == id {
         delete_user(&mut *pool.acquire().await?, id).await?;
@@ -153,13 +544,20 @@ async fn delete_user_route(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/users",
+    tag = "users",
+    summary = "Get the current or, for admins, another user",
+    params(GetUserQuery),
+    responses((status = 200, body = UserMessage), (status = "default", description = "Error", body = ErrorMessage)),
+    security(("bearer" = []))
+)]
 async fn get_user_route(
     user: AuthenticatedUser,
     pool: web::Data<Pool<Postgres>>,
-    req: HttpRequest,
     query: web::Query<GetUserQuery>,
 ) -> Result<web::Json<UserMessage>> {
-    handle_token_update(&pool, &user, &user.token, &req).await?;
     let id = match query.user_id {
         Some(id) if user.is_admin().is_ok() => id,
         _ => user.id,
@@ -173,7 +571,8 @@ async fn get_user_route(
     ))
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 struct GetUserQuery {
     pub user_id: Option<Uuid>,
 }
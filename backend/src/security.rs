@@ -0,0 +1,52 @@
+use actix_cors::Cors;
+use actix_web::{
+    http::{header, Method},
+    middleware::DefaultHeaders,
+};
+
+use crate::config::Config;
+use crate::logging::REQUEST_ID_HEADER;
+
+/// Cross origin access for the configured origins, only wrapped if there are any
+pub fn cors(config: &Config) -> Cors {
+    let methods = config
+        .cors_methods
+        .iter()
+        .filter_map(|m| Method::from_bytes(m.trim().as_bytes()).ok());
+    let mut cors = Cors::default()
+        .allowed_methods(methods)
+        .allowed_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+        .allowed_header(REQUEST_ID_HEADER)
+        .expose_headers([REQUEST_ID_HEADER])
+        .max_age(3600);
+    for origin in &config.cors_origins {
+        cors = match origin.as_str() {
+            "*" => cors.allow_any_origin(),
+            o => cors.allowed_origin(o),
+        };
+    }
+    if config.cors_credentials {
+        cors = cors.supports_credentials();
+    }
+    cors
+}
+
+/// Headers added to every response that doesn't set them itself
+pub fn security_headers(config: &Config) -> DefaultHeaders {
+    let mut headers = DefaultHeaders::new()
+        .add((
+            header::CONTENT_SECURITY_POLICY,
+            config.content_security_policy.as_str(),
+        ))
+        .add((header::X_CONTENT_TYPE_OPTIONS, "nosniff"))
+        .add((header::X_FRAME_OPTIONS, "DENY"))
+        // reset and login links carry tokens in the url
+        .add((header::REFERRER_POLICY, "no-referrer"));
+    if config.tls_cert.is_some() {
+        headers = headers.add((
+            header::STRICT_TRANSPORT_SECURITY,
+            "max-age=31536000; includeSubDomains",
+        ));
+    }
+    headers
+}
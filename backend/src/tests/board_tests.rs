@@ -0,0 +1,105 @@
+use actix_web::http::{Method, StatusCode};
+use serde_json::json;
+
+use super::TestApp;
+
+#[actix_web::test]
+async fn board_text_export() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let stranger = app.create_user("stranger", false).await;
+    let project = app.create_project(&alice, false).await;
+    let base = format!("/api/projects/{project}");
+    let mut columns = Vec::new();
+    for (index, name) in ["To do", "Done", "Empty"].into_iter().enumerate() {
+        columns.push(app.create_column(&alice, project, name, index as i32).await);
+    }
+    let bug = app.create_label(&alice, project, "bug").await;
+    let wont_fix = app.create_label(&alice, project, "won't fix").await;
+    let done = app.create_state(&alice, project, "Done").await;
+    let docs = app
+        .create_task(
+            &alice,
+            project,
+            json!({
+                "title": "write *docs*",
+                "column_id": columns[0],
+                "assignee_id": alice.id,
+                "deadline": 1735689600000i64,
+                "labels": [bug, wont_fix],
+            }),
+        )
+        .await;
+    // subtasks stay below their parent, whatever their column
+    let outline = app
+        .create_task(
+            &alice,
+            project,
+            json!({
+                "title": "outline",
+                "column_id": columns[1],
+                "state_id": done,
+                "parent_id": docs,
+            }),
+        )
+        .await;
+    for task in [
+        json!({"title": "examples", "parent_id": outline}),
+        json!({"title": "ship", "column_id": columns[1], "state_id": done}),
+        json!({"title": "a loose end"}),
+    ] {
+        app.create_task(&alice, project, task).await;
+    }
+
+    let (status, _) = app
+        .request_text(
+            Method::GET,
+            &format!("{base}/export.md"),
+            Some(&stranger),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, md) = app
+        .request_text(
+            Method::GET,
+            &format!("{base}/export.md"),
+            Some(&alice),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{md}");
+    assert_eq!(
+        md,
+        "# test project\n\
+         \n## To do\n\n\
+         - [ ] write \\*docs\\* `bug` `won't fix` @alice (due 2025-01-01)\n  \
+           - [x] outline\n    \
+             - [ ] examples\n\
+         \n## Done\n\n\
+         - [x] ship\n\
+         \n## Empty\n\
+         \n## No column\n\n\
+         - [ ] a loose end\n"
+    );
+
+    let (status, txt) = app
+        .request_text(
+            Method::GET,
+            &format!("{base}/export.txt"),
+            Some(&alice),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{txt}");
+    assert_eq!(
+        txt,
+        "write *docs* +bug +won't_fix @To_do assignee:alice due:2025-01-01\n\
+         x outline @Done\n\
+         examples\n\
+         x ship @Done\n\
+         a loose end\n"
+    );
+}
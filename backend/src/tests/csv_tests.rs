@@ -0,0 +1,263 @@
+use actix_web::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use super::TestApp;
+use crate::data::models::Permissions;
+
+#[actix_web::test]
+async fn csv_export() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let stranger = app.create_user("stranger", false).await;
+    let project = app.create_project(&alice, false).await;
+    let base = format!("/api/projects/{project}");
+    let column = app.create_column(&alice, project, "todo", 0).await;
+    let bug = app.create_label(&alice, project, "bug").await;
+    let ui = app.create_label(&alice, project, "ui").await;
+    let task = app
+        .create_task(
+            &alice,
+            project,
+            json!({
+                "title": "fix, then \"ship\"",
+                "assignee_id": alice.id,
+                "column_id": column,
+                "deadline": 1735689600000i64,
+                "estimation": 3,
+                "labels": [bug, ui],
+            }),
+        )
+        .await;
+
+    let (status, _) = app
+        .request_text(
+            Method::GET,
+            &format!("{base}/tasks.csv"),
+            Some(&stranger),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, csv) = app
+        .request_text(
+            Method::GET,
+            &format!("{base}/tasks.csv?columns=title,labels,column,assignee,deadline"),
+            Some(&alice),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{csv}");
+    assert_eq!(
+        csv,
+        format!(
+            "id,title,labels,column,assignee,deadline\n\
+             {},\"fix, then \"\"ship\"\"\",bug; ui,todo,alice,2025-01-01T00:00:00Z\n",
+            task,
+        )
+    );
+
+    let (status, csv) = app
+        .request_text(
+            Method::GET,
+            &format!("{base}/tasks.csv"),
+            Some(&alice),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(csv.starts_with(
+        "id,title,description,column,state,labels,assignee,deadline,estimation,parent\n"
+    ));
+    let (status, body) = app
+        .request_text(
+            Method::GET,
+            &format!("{base}/tasks.csv?columns=title,color"),
+            Some(&alice),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("Unknown csv column 'color'"), "{body}");
+}
+
+#[actix_web::test]
+async fn csv_import() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let reader = app.create_user("reader", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.add_member(project, &reader, Permissions::Reader).await;
+    let base = format!("/api/projects/{project}");
+    let uri = format!("{base}/tasks.csv");
+    app.create_column(&alice, project, "Todo", 0).await;
+    app.create_label(&alice, project, "bug").await;
+    let import = |user, query: &str, csv: &str| {
+        let uri = format!("{uri}{query}");
+        let csv = csv.to_owned();
+        let app = &app;
+        async move {
+            let (status, body) = app
+                .request_text(Method::POST, &uri, Some(user), Some(("text/csv", &csv)))
+                .await;
+            (
+                status,
+                serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            )
+        }
+    };
+
+    let (status, _) = import(&reader, "", "title\nnope\n").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // one bad row rejects the whole file
+    let csv = "title,column,labels,deadline\n\
+               first,todo,BUG,2025-03-01\n\
+               second,done,,\n\
+               ,todo,,\n\
+               third,,,someday\n";
+    let (status, report) = import(&alice, "", csv).await;
+    assert_eq!(status, StatusCode::OK, "{report}");
+    assert_eq!(report["applied"], false);
+    assert_eq!(report["created"], 1);
+    assert_eq!(
+        report["errors"],
+        json!([
+            {"line": 3, "msg": "Unknown column 'done'"},
+            {"line": 4, "msg": "Title is empty"},
+            {"line": 5, "msg": "Deadline 'someday' is not a date"},
+        ])
+    );
+    let (_, tasks) = app
+        .request_text(
+            Method::GET,
+            &format!("{uri}?columns=title"),
+            Some(&alice),
+            None,
+        )
+        .await;
+    assert_eq!(tasks, "id,title\n");
+
+    let csv = "title,column,labels,deadline\n\
+               first,todo,BUG,2025-03-01\n\
+               second,,,\n";
+    let (_, report) = import(&alice, "?dry_run=true", csv).await;
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(report["applied"], false);
+    assert_eq!(report["created"], 2);
+    assert_eq!(report["errors"], json!([]));
+    let (_, tasks) = app
+        .request_text(
+            Method::GET,
+            &format!("{uri}?columns=title"),
+            Some(&alice),
+            None,
+        )
+        .await;
+    assert_eq!(tasks, "id,title\n");
+
+    let (_, report) = import(&alice, "", csv).await;
+    assert_eq!(report["applied"], true, "{report}");
+    assert_eq!(report["created"], 2);
+    let (_, tasks) = app
+        .request_text(
+            Method::GET,
+            &format!("{uri}?columns=title,column,labels,deadline"),
+            Some(&alice),
+            None,
+        )
+        .await;
+    let mut lines: Vec<_> = tasks.lines().skip(1).collect();
+    lines.sort_by_key(|l| l.split(',').nth(1));
+    let first = lines[0].split(',').next().unwrap();
+    let second = lines[1].split(',').next().unwrap();
+    assert_eq!(
+        lines,
+        [
+            format!("{first},first,Todo,bug,2025-03-01T00:00:00Z"),
+            format!("{second},second,,,"),
+        ]
+    );
+
+    // updates only touch the columns in the file
+    let csv = format!("id,labels,parent\n{first},,{second}\n");
+    let (_, report) = import(&alice, "", &csv).await;
+    assert_eq!(report["updated"], 1, "{report}");
+    let (_, task) = app.get(&alice, &format!("/api/tasks/{first}")).await;
+    assert_eq!(task["title"], "first");
+    assert_eq!(task["parent_id"], second);
+    assert_eq!(task["deadline"], 1740787200000i64);
+    assert_eq!(task["labels"], json!([]));
+
+    // rows that would make two tasks each other's parent
+    let a = app.create_task(&alice, project, json!({})).await;
+    let b = app.create_task(&alice, project, json!({})).await;
+    let (_, report) = import(&alice, "", &format!("id,parent\n{a},{b}\n{b},{a}\n")).await;
+    assert_eq!(report["applied"], false);
+    assert_eq!(
+        report["errors"],
+        json!([{"line": 3, "msg": "Parent task is a subtask of the task"}])
+    );
+    // so would making the task a subtask of its subtask
+    let (_, report) = import(&alice, "", &format!("id,parent\n{second},{first}\n")).await;
+    assert_eq!(report["errors"][0]["line"], 2, "{report}");
+    let (_, task) = app.get(&alice, &format!("/api/tasks/{a}")).await;
+    assert_eq!(task["parent_id"], Value::Null);
+
+    let csv = "id,title\n00000000-0000-0000-0000-000000000001,gone\n";
+    let (_, report) = import(&alice, "", csv).await;
+    assert_eq!(
+        report["errors"][0]["msg"],
+        "Task 00000000-0000-0000-0000-000000000001 not found in the project"
+    );
+}
+
+#[actix_web::test]
+async fn csv_formulas_are_quoted() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let project = app.create_project(&alice, false).await;
+    let uri = format!("/api/projects/{project}/tasks.csv?columns=title,description");
+    let task = app
+        .create_task(
+            &alice,
+            project,
+            json!({"title": "=HYPERLINK(\"http://evil\")", "description": "'quoted"}),
+        )
+        .await;
+
+    let (status, csv) = app
+        .request_text(Method::GET, &uri, Some(&alice), None)
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        csv,
+        format!("id,title,description\n{task},\"'=HYPERLINK(\"\"http://evil\"\")\",'quoted\n")
+    );
+
+    // importing the file again takes the quote off
+    let (status, body) = app
+        .request_text(Method::POST, &uri, Some(&alice), Some(("text/csv", &csv)))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let csv = "title\n'+1\n'@SUM(A1)\n";
+    let (status, body) = app
+        .request_text(Method::POST, &uri, Some(&alice), Some(("text/csv", csv)))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (_, task) = app.get(&alice, &format!("/api/tasks/{task}")).await;
+    assert_eq!(task["title"], "=HYPERLINK(\"http://evil\")");
+    assert_eq!(task["description"], "'quoted");
+    let titles: Vec<String> =
+        sqlx::query_scalar("select title from tasks where project_id=$1 order by title")
+            .bind(project)
+            .fetch_all(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(titles, ["+1", "=HYPERLINK(\"http://evil\")", "@SUM(A1)"]);
+}
@@ -0,0 +1,407 @@
+use actix_web::http::header::HeaderMap;
+use actix_web::http::{Method, StatusCode};
+use actix_web::test::TestRequest;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::json;
+
+use super::{id, TestApp, TestUser};
+use crate::data::models::Permissions;
+
+/// Sends a caldav request with basic auth, if credentials are given
+async fn dav(
+    app: &TestApp,
+    auth: Option<(&str, &str)>,
+    method: &str,
+    uri: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> (StatusCode, HeaderMap, String) {
+    let mut req = TestRequest::default()
+        .method(Method::from_bytes(method.as_bytes()).unwrap())
+        .uri(uri)
+        .set_payload(body.to_owned());
+    if let Some((username, token)) = auth {
+        let credentials = BASE64_STANDARD.encode(format!("{username}:{token}"));
+        req = req.insert_header(("Authorization", format!("Basic {credentials}")));
+    }
+    for header in headers {
+        req = req.insert_header(*header);
+    }
+    app.send(req, None).await
+}
+
+async fn personal_token(app: &TestApp, user: &TestUser) -> String {
+    let (status, body) = app
+        .post(user, "/api/users/tokens", json!({"name": "phone"}))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    body["token"].as_str().unwrap().to_owned()
+}
+
+fn vtodo(uid: &str, lines: &str) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//test//EN\r\nBEGIN:VTODO\r\nUID:{uid}\r\nDTSTAMP:20250101T000000Z\r\n{lines}END:VTODO\r\nEND:VCALENDAR\r\n"
+    )
+}
+
+#[actix_web::test]
+async fn personal_tokens() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let token = personal_token(&app, &alice).await;
+    let (status, _) = app
+        .post(&alice, "/api/users/tokens", json!({"name": " "}))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, headers, _) = dav(&app, None, "PROPFIND", "/dav/", &[], "").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(headers.contains_key("WWW-Authenticate"));
+    let (status, _, _) = dav(&app, Some(("bob", &token)), "PROPFIND", "/dav/", &[], "").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _, _) = dav(&app, Some(("alice", &token)), "PROPFIND", "/dav/", &[], "").await;
+    assert_eq!(status, StatusCode::MULTI_STATUS);
+
+    let (_, tokens) = app.get(&alice, "/api/users/tokens").await;
+    assert_eq!(tokens.as_array().unwrap().len(), 1);
+    assert_eq!(tokens[0]["name"], "phone");
+    assert!(tokens[0]["last_used"].is_i64());
+    assert!(tokens[0].get("token").is_none());
+    let uri = format!("/api/users/tokens/{}", id(&tokens[0]));
+    let (status, _) = app.delete(&bob, &uri).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = app.delete(&alice, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = dav(&app, Some(("alice", &token)), "PROPFIND", "/dav/", &[], "").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn caldav_sync() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.add_member(project, &bob, Permissions::Reader).await;
+    let base = format!("/api/projects/{project}");
+    let column = app.create_column(&alice, project, "todo", 0).await;
+    let label = app.create_label(&alice, project, "bug").await;
+    let parent = app
+        .create_task(
+            &alice,
+            project,
+            json!({
+                "title": "parent",
+                "column_id": column,
+                "deadline": 1735689600000i64,
+                "labels": [label],
+            }),
+        )
+        .await;
+    let child = app
+        .create_task(
+            &alice,
+            project,
+            json!({"title": "child", "parent_id": parent}),
+        )
+        .await;
+    let token = personal_token(&app, &alice).await;
+    let alice_auth = Some(("alice", token.as_str()));
+    let calendar = format!("/dav/projects/{project}/");
+    let parent_href = format!("{calendar}{parent}.ics");
+
+    let (status, headers, _) = dav(&app, None, "GET", "/.well-known/caldav", &[], "").await;
+    assert_eq!(status, StatusCode::MOVED_PERMANENTLY);
+    assert_eq!(headers.get("Location").unwrap(), "/dav/");
+    let (status, headers, _) = dav(&app, None, "OPTIONS", &calendar, &[], "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(headers
+        .get("DAV")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .contains("calendar-access"));
+
+    // discovery: principal, home and the project's calendar
+    let (status, _, body) = dav(
+        &app,
+        alice_auth,
+        "PROPFIND",
+        "/dav/",
+        &[("Depth", "0")],
+        r#"<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><d:prop><d:current-user-principal/><c:calendar-home-set/><d:unknown/></d:prop></d:propfind>"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::MULTI_STATUS, "{body}");
+    assert!(
+        body.contains("<c:calendar-home-set><d:href>/dav/projects/</d:href></c:calendar-home-set>")
+    );
+    assert!(body.contains("<d:unknown/></d:prop><d:status>HTTP/1.1 404 Not Found"));
+    let (_, _, body) = dav(
+        &app,
+        alice_auth,
+        "PROPFIND",
+        "/dav/projects/",
+        &[("Depth", "1")],
+        "",
+    )
+    .await;
+    assert!(
+        body.contains(&format!("<d:href>{calendar}</d:href>")),
+        "{body}"
+    );
+    assert!(body.contains("<d:collection/><c:calendar/>"));
+    assert!(body.contains("<d:displayname>test project</d:displayname>"));
+    assert!(body.contains("<c:comp name=\"VTODO\"/>"));
+    let (_, _, body) = dav(
+        &app,
+        alice_auth,
+        "PROPFIND",
+        &calendar,
+        &[("Depth", "1")],
+        "",
+    )
+    .await;
+    assert!(
+        body.contains(&format!("<d:href>{parent_href}</d:href>")),
+        "{body}"
+    );
+    assert!(body.contains(&format!("{child}.ics</d:href>")));
+    assert!(body.contains("<cs:getctag>"));
+
+    // reports
+    let (status, _, body) = dav(
+        &app,
+        alice_auth,
+        "REPORT",
+        &calendar,
+        &[("Depth", "1")],
+        r#"<c:calendar-query xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><d:prop><d:getetag/><c:calendar-data/></d:prop><c:filter><c:comp-filter name="VCALENDAR"><c:comp-filter name="VTODO"/></c:comp-filter></c:filter></c:calendar-query>"#,
+    )
+    .await;
+    assert_eq!(status, StatusCode::MULTI_STATUS, "{body}");
+    assert_eq!(body.matches("BEGIN:VTODO").count(), 2);
+    assert!(body.contains(&format!("RELATED-TO:{parent}@foxboard")));
+    assert!(body.contains("CATEGORIES:bug"));
+    let (_, _, body) = dav(
+        &app,
+        alice_auth,
+        "REPORT",
+        &calendar,
+        &[],
+        &format!(
+            r#"<c:calendar-multiget xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav"><d:prop><d:getetag/></d:prop><d:href>{parent_href}</d:href><d:href>{calendar}gone.ics</d:href></c:calendar-multiget>"#
+        ),
+    )
+    .await;
+    assert_eq!(body.matches("<d:getetag>").count(), 1, "{body}");
+    assert!(body.contains("gone.ics</d:href><d:status>HTTP/1.1 404 Not Found"));
+
+    // etags stay the same until the task changes
+    let (status, headers, ics) = dav(&app, alice_auth, "GET", &parent_href, &[], "").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(ics.contains("\r\nDUE:20250101T000000Z\r\n"), "{ics}");
+    assert!(ics.contains("\r\nSTATUS:NEEDS-ACTION\r\n"));
+    let etag = headers.get("ETag").unwrap().to_str().unwrap().to_owned();
+    let (_, headers, _) = dav(&app, alice_auth, "GET", &parent_href, &[], "").await;
+    assert_eq!(headers.get("ETag").unwrap(), etag.as_str());
+
+    // ticking it off, the project gets a done state
+    let update = vtodo(
+        &format!("{parent}@foxboard"),
+        "SUMMARY:parent\\, renamed\r\nSTATUS:COMPLETED\r\nCATEGORIES:BUG,urgent\r\n",
+    );
+    let (status, _, _) = dav(
+        &app,
+        alice_auth,
+        "PUT",
+        &parent_href,
+        &[("If-Match", "\"stale\"")],
+        &update,
+    )
+    .await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (status, headers, body) = dav(
+        &app,
+        alice_auth,
+        "PUT",
+        &parent_href,
+        &[("If-Match", &etag)],
+        &update,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT, "{body}");
+    assert_ne!(headers.get("ETag").unwrap(), etag.as_str());
+    let (_, task) = app.get(&alice, &format!("/api/tasks/{parent}")).await;
+    assert_eq!(task["title"], "parent, renamed");
+    assert_eq!(task["column_id"], json!(column));
+    assert_eq!(task["deadline"], json!(null));
+    assert_eq!(task["labels"].as_array().unwrap().len(), 2);
+    let (_, export) = app.get(&alice, &format!("{base}/export")).await;
+    assert_eq!(export["states"][0]["name"], "Done");
+    assert_eq!(task["state_id"], export["states"][0]["id"]);
+
+    // a task created on the phone keeps its name and uid
+    let phone_href = format!("{calendar}phone%201.ics");
+    let new = vtodo(
+        "phone-uid-1",
+        &format!("SUMMARY:from the phone\r\nDUE;VALUE=DATE:20250301\r\nRELATED-TO;RELTYPE=PARENT:{parent}@foxboard\r\n"),
+    );
+    let (status, headers, body) = dav(
+        &app,
+        alice_auth,
+        "PUT",
+        &phone_href,
+        &[("If-None-Match", "*")],
+        &new,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED, "{body}");
+    let etag = headers.get("ETag").unwrap().to_str().unwrap().to_owned();
+    let (status, _, _) = dav(
+        &app,
+        alice_auth,
+        "PUT",
+        &phone_href,
+        &[("If-None-Match", "*")],
+        &new,
+    )
+    .await;
+    assert_eq!(status, StatusCode::PRECONDITION_FAILED);
+    let (_, _, ics) = dav(&app, alice_auth, "GET", &phone_href, &[], "").await;
+    assert!(ics.contains("\r\nUID:phone-uid-1\r\n"), "{ics}");
+    assert!(ics.contains("\r\nDUE:20250301T000000Z\r\n"));
+    assert!(ics.contains(&format!("\r\nRELATED-TO:{parent}@foxboard\r\n")));
+    // the parent can't become a subtask of its subtask, or of itself
+    for related in ["phone-uid-1".to_owned(), format!("{parent}@foxboard")] {
+        let cycle = vtodo(
+            &format!("{parent}@foxboard"),
+            &format!("SUMMARY:parent\r\nRELATED-TO;RELTYPE=PARENT:{related}\r\n"),
+        );
+        let (status, _, _) = dav(&app, alice_auth, "PUT", &parent_href, &[], &cycle).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{related}");
+    }
+    let (_, task) = app.get(&alice, &format!("/api/tasks/{parent}")).await;
+    assert_eq!(task["parent_id"], json!(null));
+    let (_, _, body) = dav(
+        &app,
+        alice_auth,
+        "PROPFIND",
+        &calendar,
+        &[("Depth", "1")],
+        "",
+    )
+    .await;
+    assert!(body.contains("phone%201.ics</d:href>"), "{body}");
+    let (status, _, _) = dav(
+        &app,
+        alice_auth,
+        "PUT",
+        &format!("{calendar}event.ics"),
+        &[],
+        "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:e\r\nSUMMARY:meeting\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // local times are converted from their zone, unknown zones are refused
+    let berlin_href = format!("{calendar}berlin.ics");
+    let (status, _, _) = dav(
+        &app,
+        alice_auth,
+        "PUT",
+        &berlin_href,
+        &[],
+        &vtodo(
+            "berlin",
+            "SUMMARY:noon in berlin\r\nDUE;TZID=Europe/Berlin:20250301T120000\r\n",
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, _, ics) = dav(&app, alice_auth, "GET", &berlin_href, &[], "").await;
+    assert!(ics.contains("\r\nDUE:20250301T110000Z\r\n"), "{ics}");
+    let (status, _, body) = dav(
+        &app,
+        alice_auth,
+        "PUT",
+        &berlin_href,
+        &[],
+        &vtodo(
+            "berlin",
+            "SUMMARY:noon somewhere\r\nDUE;TZID=\"W. Europe Standard Time\":20250301T120000\r\n",
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body.contains("Unknown time zone"), "{body}");
+
+    // readers may look but not change
+    let bob_token = personal_token(&app, &bob).await;
+    let bob_auth = Some(("bob", bob_token.as_str()));
+    let (status, _, _) = dav(&app, bob_auth, "GET", &phone_href, &[], "").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = dav(&app, bob_auth, "DELETE", &phone_href, &[], "").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (_, _, body) = dav(&app, bob_auth, "PROPFIND", &calendar, &[("Depth", "0")], r#"<d:propfind xmlns:d="DAV:"><d:prop><d:current-user-privilege-set/></d:prop></d:propfind>"#).await;
+    assert!(
+        body.contains("<d:read/>") && !body.contains("<d:write/>"),
+        "{body}"
+    );
+
+    let (status, _, _) = dav(
+        &app,
+        alice_auth,
+        "DELETE",
+        &phone_href,
+        &[("If-Match", &etag)],
+        "",
+    )
+    .await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    let (status, _, _) = dav(&app, alice_auth, "GET", &phone_href, &[], "").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, trash) = app.get(&alice, &format!("{base}/trash")).await;
+    assert_eq!(trash[0]["name"], "from the phone");
+}
+
+#[actix_web::test]
+async fn caldav_archived_project() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let project = app.create_project(&alice, false).await;
+    let token = personal_token(&app, &alice).await;
+    let alice_auth = Some(("alice", token.as_str()));
+    let calendar = format!("/dav/projects/{project}/");
+    let (status, _) = app
+        .post(
+            &alice,
+            &format!("/api/projects/{project}/archive"),
+            json!({}),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _, _) = dav(
+        &app,
+        alice_auth,
+        "PUT",
+        &format!("{calendar}new.ics"),
+        &[],
+        &vtodo("new", "SUMMARY:too late\r\n"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (_, _, body) = dav(&app, alice_auth, "PROPFIND", &calendar, &[("Depth", "0")], r#"<d:propfind xmlns:d="DAV:"><d:prop><d:current-user-privilege-set/></d:prop></d:propfind>"#).await;
+    assert!(
+        body.contains("<d:read/>") && !body.contains("<d:write/>"),
+        "{body}"
+    );
+}
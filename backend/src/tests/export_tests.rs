@@ -0,0 +1,208 @@
+use actix_web::http::StatusCode;
+use serde_json::{json, Value};
+
+use super::{id, TestApp};
+use crate::data::models::Permissions;
+
+/// The export without ids, which change on every import
+fn shape(export: &Value) -> Value {
+    let names = |key: &str| -> Vec<Value> {
+        export[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["name"].clone())
+            .collect()
+    };
+    let mut tasks: Vec<_> = export["tasks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| {
+            json!([
+                t["title"],
+                t["assignee"],
+                t["parent_id"].is_string(),
+                t["labels"].as_array().unwrap().len()
+            ])
+        })
+        .collect();
+    tasks.sort_by_key(|t| t.to_string());
+    json!({
+        "columns": names("columns"),
+        "labels": names("labels"),
+        "states": names("states"),
+        "tasks": tasks,
+    })
+}
+
+#[actix_web::test]
+async fn export_and_import() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let carol = app.create_user("carol", false).await;
+    let stranger = app.create_user("stranger", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.add_member(project, &bob, Permissions::Editor).await;
+    app.add_member(project, &carol, Permissions::Reader).await;
+    let base = format!("/api/projects/{project}");
+    let column = app.create_column(&alice, project, "todo", 0).await;
+    let label = app.create_label(&alice, project, "bug").await;
+    let state = app.create_state(&alice, project, "open").await;
+    let parent = app
+        .create_task(
+            &alice,
+            project,
+            json!({
+                "title": "parent",
+                "assignee_id": bob.id,
+                "column_id": column,
+                "state_id": state,
+                "labels": [label],
+            }),
+        )
+        .await;
+    app.create_task(
+        &alice,
+        project,
+        json!({"title": "child", "parent_id": parent}),
+    )
+    .await;
+
+    let (status, _) = app.get(&stranger, &format!("{base}/export")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, export) = app.get(&carol, &format!("{base}/export")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(export["version"], 2);
+    assert_eq!(
+        export["members"],
+        json!([
+            {"username": "bob", "role": "editor"},
+            {"username": "carol", "role": "reader"},
+        ])
+    );
+
+    // only admins bring the members along
+    let (status, body) = app.post(&bob, "/api/projects/import", export.clone()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["skipped_members"], json!(["bob", "carol"]));
+    assert_eq!(body["project"]["owner_id"], bob.id.to_string());
+    let imported = format!("/api/projects/{}", id(&body["project"]));
+    let (status, again) = app.get(&bob, &format!("{imported}/export")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(shape(&again), shape(&export));
+    assert_eq!(again["members"], json!([]));
+    assert_ne!(again["columns"][0]["id"], export["columns"][0]["id"]);
+    for user in [&alice, &carol] {
+        let (status, _) = app.get(user, &imported).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    // the admin owns the import, members keep their role up to editor
+    let admin = app.create_user("admin", true).await;
+    let mut export = export;
+    export["members"][1]["role"] = json!("owner");
+    let (status, body) = app.post(&admin, "/api/projects/import", export).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["skipped_members"], json!([]));
+    let (_, again) = app
+        .get(
+            &carol,
+            &format!("/api/projects/{}/export", id(&body["project"])),
+        )
+        .await;
+    assert_eq!(
+        again["members"],
+        json!([
+            {"username": "bob", "role": "editor"},
+            {"username": "carol", "role": "editor"},
+        ])
+    );
+}
+
+#[actix_web::test]
+async fn import_validation() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let document = |version: i32, column_id: Value| {
+        json!({
+            "version": version,
+            "name": "imported",
+            "public": false,
+            "columns": [{"id": "00000000-0000-0000-0000-000000000001", "name": "todo", "card_limit": 0, "index": 0}],
+            "labels": [],
+            "states": [],
+            "tasks": [{
+                "id": "00000000-0000-0000-0000-000000000002",
+                "title": "a task",
+                "column_id": column_id,
+                "state_id": null,
+                "parent_id": null,
+                "assignee": "nobody",
+                "creator": null,
+                "deadline": null,
+                "estimation": null,
+                "task_type": 0,
+                "labels": [],
+            }],
+        })
+    };
+    let known = json!("00000000-0000-0000-0000-000000000001");
+
+    let (status, _) = app
+        .post(&alice, "/api/projects/import", document(99, known.clone()))
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = app
+        .post(
+            &alice,
+            "/api/projects/import",
+            document(2, json!("00000000-0000-0000-0000-000000000009")),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    // ids only resolve within their kind and are unique in it
+    let label = json!({"id": "00000000-0000-0000-0000-000000000003", "name": "bug"});
+    let mut label_as_column = document(2, label["id"].clone());
+    label_as_column["labels"] = json!([label]);
+    let mut column_as_label = document(2, Value::Null);
+    column_as_label["tasks"][0]["labels"] = json!([known]);
+    let mut duplicate_column = document(2, known.clone());
+    let column = duplicate_column["columns"][0].clone();
+    duplicate_column["columns"] = json!([column, column]);
+    let mut duplicate_task = document(2, known.clone());
+    let task = duplicate_task["tasks"][0].clone();
+    duplicate_task["tasks"] = json!([task, task]);
+    for (name, doc) in [
+        ("label as column", label_as_column),
+        ("column as label", column_as_label),
+        ("duplicate column", duplicate_column),
+        ("duplicate task", duplicate_task),
+    ] {
+        let (status, body) = app.post(&alice, "/api/projects/import", doc).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{name}: {body}");
+    }
+    // a failed import leaves nothing behind
+    let (_, body) = app.get(&alice, "/api/projects/list").await;
+    assert_eq!(body["total"], 0);
+
+    // version 1 had no members, unknown users are dropped
+    let (status, body) = app
+        .post(&alice, "/api/projects/import", document(1, known))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (_, export) = app
+        .get(
+            &alice,
+            &format!("/api/projects/{}/export", id(&body["project"])),
+        )
+        .await;
+    assert_eq!(export["tasks"][0]["assignee"], Value::Null);
+    assert_eq!(export["tasks"][0]["creator"], "alice");
+    assert_eq!(export["tasks"][0]["column_id"], export["columns"][0]["id"]);
+}
@@ -0,0 +1,122 @@
+use actix_web::http::{Method, StatusCode};
+use serde_json::json;
+
+use super::TestApp;
+
+#[actix_web::test]
+async fn calendar_feeds() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let project = app.create_project(&alice, false).await;
+    let label = app.create_label(&alice, project, "a,b").await;
+    let done = app.create_state(&alice, project, "Done").await;
+    let long_title = "x".repeat(120);
+    let mut tasks = Vec::new();
+    for (title, assignee, deadline) in [
+        ("plan; review", Some(alice.id), Some(1735689600000i64)),
+        (long_title.as_str(), Some(bob.id), Some(1735776000000)),
+        ("someday", Some(alice.id), None),
+    ] {
+        let task = app
+            .create_task(
+                &alice,
+                project,
+                json!({
+                    "title": title,
+                    "description": "first line\nsecond, line",
+                    "assignee_id": assignee,
+                    "deadline": deadline,
+                    "state_id": done,
+                    "labels": [label],
+                }),
+            )
+            .await;
+        tasks.push(task);
+    }
+
+    let (status, feed) = app.post(&alice, "/api/users/feed", json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{feed}");
+    let token = feed["token"].as_str().unwrap().to_owned();
+    assert!(feed["url"]
+        .as_str()
+        .unwrap()
+        .ends_with(&format!("/api/feeds/{token}")));
+
+    let (status, ics) = app
+        .request_text(
+            Method::GET,
+            &format!("/api/feeds/{token}/tasks.ics"),
+            None,
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{ics}");
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 1, "{ics}");
+    for line in [
+        format!("UID:{}@foxboard", tasks[0]),
+        "DTSTART:20250101T000000Z".to_owned(),
+        "SUMMARY:plan\\; review".to_owned(),
+        "DESCRIPTION:first line\\nsecond\\, line".to_owned(),
+        "CATEGORIES:a\\,b".to_owned(),
+        "X-WR-CALNAME:foxboard: alice".to_owned(),
+    ] {
+        assert!(
+            ics.contains(&format!("\r\n{line}\r\n")),
+            "{line} missing in {ics}"
+        );
+    }
+
+    // bob's task is in the project feed, alice's without deadline isn't
+    let project_feed = format!("/api/feeds/{token}/projects/{project}/tasks.ics?entry=todo");
+    let (status, ics) = app
+        .request_text(Method::GET, &project_feed, None, None)
+        .await;
+    assert_eq!(status, StatusCode::OK, "{ics}");
+    assert_eq!(ics.matches("BEGIN:VTODO").count(), 2, "{ics}");
+    assert!(!ics.contains(&tasks[2].to_string()));
+    assert!(ics.contains("\r\nDUE:20250102T000000Z\r\n"));
+    assert!(ics.contains("\r\nSTATUS:COMPLETED\r\n"));
+    assert!(ics.lines().all(|l| l.len() <= 75), "{ics}");
+    assert!(ics.contains(&format!("\r\n {}", "x".repeat(10))));
+
+    let (_, bob_feed) = app.post(&bob, "/api/users/feed", json!({})).await;
+    let bob_token = bob_feed["token"].as_str().unwrap();
+    let (status, _) = app
+        .request_text(
+            Method::GET,
+            &format!("/api/feeds/{bob_token}/projects/{project}/tasks.ics"),
+            None,
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // a new token replaces the old one, deleting revokes it
+    let (_, feed) = app.post(&alice, "/api/users/feed", json!({})).await;
+    let new_token = feed["token"].as_str().unwrap().to_owned();
+    let (status, _) = app
+        .request_text(
+            Method::GET,
+            &format!("/api/feeds/{token}/tasks.ics"),
+            None,
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app.delete(&alice, "/api/users/feed").await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .request_text(
+            Method::GET,
+            &format!("/api/feeds/{new_token}/tasks.ics"),
+            None,
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
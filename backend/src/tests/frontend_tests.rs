@@ -0,0 +1,63 @@
+use actix_web::{http::StatusCode, test};
+use rand::{rngs::OsRng, RngCore};
+
+use super::TestApp;
+
+const INDEX: &str = "<!doctype html><title>foxboard</title>";
+
+#[actix_web::test]
+async fn serves_the_frontend() {
+    let root = std::env::temp_dir().join(format!("foxboard_frontend_{:016x}", OsRng.next_u64()));
+    let dist = root.join("dist");
+    let Some(app) = TestApp::start_with(|c| c.frontend_dir = Some(dist.clone())).await else {
+        return;
+    };
+    std::fs::create_dir_all(dist.join("assets")).unwrap();
+    std::fs::write(dist.join("index.html"), INDEX).unwrap();
+    std::fs::write(dist.join("assets/app-1a2b3c.js"), "console.log(1)").unwrap();
+    // next to the served directory, must never be reachable
+    std::fs::write(root.join("secret.txt"), "secret").unwrap();
+    let get = |uri: &str| app.send(test::TestRequest::get().uri(uri), None);
+
+    // routes of the app get index.html, which is revalidated on every load
+    for uri in ["/", "/projects/1234", "/login"] {
+        let (status, headers, body) = get(uri).await;
+        assert_eq!(status, StatusCode::OK, "{uri}");
+        assert_eq!(body, INDEX, "{uri}");
+        assert_eq!(headers.get("Cache-Control").unwrap(), "no-cache", "{uri}");
+        assert!(headers
+            .get("Content-Type")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+    }
+
+    let (status, headers, body) = get("/assets/app-1a2b3c.js").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "console.log(1)");
+    assert_eq!(
+        headers.get("Cache-Control").unwrap(),
+        "public, max-age=31536000, immutable"
+    );
+
+    // missing files aren't answered with index.html, neither are api paths
+    for uri in ["/assets/gone-000000.js", "/favicon.ico", "/api/nothing"] {
+        let (status, _, body) = get(uri).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+        assert_ne!(body, INDEX, "{uri}");
+    }
+
+    for uri in ["/../secret.txt", "/assets/../../secret.txt"] {
+        let (status, _, body) = get(uri).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{uri}");
+        assert_ne!(body, "secret", "{uri}");
+    }
+
+    let (status, _, _) = app
+        .send(test::TestRequest::post().uri("/login"), None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    std::fs::remove_dir_all(root).unwrap();
+}
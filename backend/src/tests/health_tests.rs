@@ -0,0 +1,71 @@
+use actix_web::http::{Method, StatusCode};
+
+use uuid::Uuid;
+
+use super::{TestApp, TestUser};
+
+#[actix_web::test]
+async fn healthz_and_readyz() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let (status, body) = app.request(Method::GET, "/healthz", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["success"], true);
+    let (status, body) = app.request(Method::GET, "/readyz", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["success"], true);
+}
+
+#[actix_web::test]
+async fn readyz_fails_with_pending_migrations() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    sqlx::query(
+        "delete from _sqlx_migrations where version = (select max(version) from _sqlx_migrations)",
+    )
+    .execute(&app.pool)
+    .await
+    .unwrap();
+    let (status, body) = app.request(Method::GET, "/readyz", None, None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["success"], false);
+}
+
+#[actix_web::test]
+async fn metrics_open_without_token() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let (status, _) = app.request(Method::GET, "/metrics", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn metrics_require_configured_token() {
+    let Some(app) = TestApp::start_with(|c| c.metrics_token = Some("secret".to_owned())).await
+    else {
+        return;
+    };
+    let (status, _) = app.request(Method::GET, "/metrics", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let scraper = TestUser {
+        id: Uuid::nil(),
+        username: String::new(),
+        token: "secret".to_owned(),
+    };
+    let (status, _) = app.get(&scraper, "/metrics").await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn openapi_document() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let (status, body) = app.request(Method::GET, "/openapi.json", None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["paths"]["/api/projects"].is_object());
+}
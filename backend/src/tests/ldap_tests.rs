@@ -0,0 +1,110 @@
+use actix_web::http::{Method, StatusCode};
+use serde_json::json;
+
+use super::TestApp;
+use crate::data::models::PasswordResetModel;
+use crate::data::user_data::{create_password_reset, get_user_by_name};
+use crate::handler::auth_provider::LDAP_AUTH_PROVIDER;
+use crate::util::hash_token;
+
+/// A directory to log in against, e.g. ldap://localhost:389. It needs a user
+/// FOXB_TEST_LDAP_USER with the password FOXB_TEST_LDAP_PASSWORD below FOXB_TEST_LDAP_BASE_DN,
+/// found with the default filter. FOXB_TEST_LDAP_BIND_DN and FOXB_TEST_LDAP_BIND_PASSWORD are
+/// used for the search if set.
+pub const TEST_LDAP_URL: &str = "FOXB_TEST_LDAP_URL";
+
+fn ldap_env(name: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| panic!("{name} is required with {TEST_LDAP_URL}"))
+}
+
+#[actix_web::test]
+async fn ldap_login() {
+    let Ok(url) = std::env::var(TEST_LDAP_URL) else {
+        eprintln!("{TEST_LDAP_URL} not set, skipping");
+        return;
+    };
+    let username = ldap_env("FOXB_TEST_LDAP_USER");
+    let password = ldap_env("FOXB_TEST_LDAP_PASSWORD");
+    let Some(app) = TestApp::start_with(|c| {
+        c.auth_providers = vec!["local".to_owned(), "ldap".to_owned()];
+        c.ldap_url = Some(url);
+        c.ldap_base_dn = Some(ldap_env("FOXB_TEST_LDAP_BASE_DN"));
+        c.ldap_bind_dn = std::env::var("FOXB_TEST_LDAP_BIND_DN").ok();
+        c.ldap_bind_password = std::env::var("FOXB_TEST_LDAP_BIND_PASSWORD").ok();
+    })
+    .await
+    else {
+        return;
+    };
+    let login = |password: &str| {
+        let msg = json!({"username": username, "password": password});
+        app.request(Method::POST, "/api/login", None, Some(msg))
+    };
+
+    // an empty password would be an anonymous bind
+    for wrong in ["", "not the password"] {
+        let (status, _) = login(wrong).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{wrong:?}");
+    }
+    // the first login creates the account, the second one finds it
+    for _ in 0..2 {
+        let (status, body) = login(&password).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert!(body["token"].is_string());
+    }
+    let user = get_user_by_name(&mut app.pool.acquire().await.unwrap(), &username)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(user.auth_provider, LDAP_AUTH_PROVIDER);
+
+    // the directory owns the password
+    create_password_reset(
+        &mut app.pool.acquire().await.unwrap(),
+        &PasswordResetModel::new(hash_token("reset token"), user.id, 10),
+    )
+    .await
+    .unwrap();
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/password/reset",
+            None,
+            Some(json!({"token": "reset token", "new_password": "a new password"})),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = login("a new password").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn unreachable_ldap_is_skipped() {
+    let Some(app) = TestApp::start_with(|c| {
+        c.auth_providers = vec!["ldap".to_owned(), "local".to_owned()];
+        c.ldap_url = Some("ldap://127.0.0.1:1".to_owned());
+        c.ldap_base_dn = Some("dc=example,dc=org".to_owned());
+    })
+    .await
+    else {
+        return;
+    };
+    // the local account still gets in
+    app.create_user("alice", false).await;
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/login",
+            None,
+            Some(json!({"username": "alice", "password": "wrong"})),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let failures: i32 =
+        sqlx::query_scalar("select failures from login_attempts where kind='user' and key='alice'")
+            .fetch_one(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(failures, 1);
+}
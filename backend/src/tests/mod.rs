@@ -0,0 +1,319 @@
+//! Integration tests against the full app. Every test gets its own database, created on the
+//! server in FOXB_TEST_DATABASE_URL and dropped again afterwards. Without it the tests are skipped.
+mod board_tests;
+mod csv_tests;
+mod dav_tests;
+mod export_tests;
+mod feed_tests;
+mod frontend_tests;
+mod health_tests;
+mod ldap_tests;
+mod oidc_tests;
+mod permission_tests;
+mod project_tests;
+mod security_tests;
+mod task_tests;
+mod template_tests;
+mod trash_tests;
+mod trello_tests;
+mod user_tests;
+
+use std::str::FromStr;
+
+use actix_web::{
+    http::{header::HeaderMap, Method, StatusCode},
+    test,
+};
+use clap::Parser;
+use rand::{rngs::OsRng, RngCore};
+use serde_json::{json, Value};
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    Connection, PgConnection, Pool, Postgres,
+};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::data::models::{Permissions, UserModel};
+use crate::data::project_data::add_project_member;
+use crate::data::user_data::create_user;
+use crate::data::MIGRATOR;
+use crate::handler::auth_provider::AuthProviders;
+use crate::handler::oidc_handler::OidcProvider;
+use crate::messages::user_messages::CreateUserMessage;
+use crate::{build_app, AppState};
+
+pub const TEST_DATABASE_URL: &str = "FOXB_TEST_DATABASE_URL";
+pub const PASSWORD: &str = "correct horse battery staple";
+
+pub struct TestApp {
+    pub pool: Pool<Postgres>,
+    state: AppState,
+    url: String,
+    database: String,
+}
+
+/// A user with a session, created through [`TestApp::create_user`]
+pub struct TestUser {
+    pub id: Uuid,
+    pub username: String,
+    pub token: String,
+}
+
+impl TestApp {
+    pub async fn start() -> Option<Self> {
+        Self::start_with(|_| {}).await
+    }
+
+    /// Starts with a default config changed by `configure`
+    pub async fn start_with(configure: impl FnOnce(&mut Config)) -> Option<Self> {
+        let Ok(url) = std::env::var(TEST_DATABASE_URL) else {
+            eprintln!("{TEST_DATABASE_URL} not set, skipping");
+            return None;
+        };
+        let database = format!("foxboard_test_{:016x}", OsRng.next_u64());
+        let mut conn = PgConnection::connect(&url)
+            .await
+            .expect("test database server unreachable");
+        sqlx::query(&format!("create database {database}"))
+            .execute(&mut conn)
+            .await
+            .expect("could not create test database");
+        let options = PgConnectOptions::from_str(&url)
+            .expect("invalid test database url")
+            .database(&database);
+        let pool = PgPoolOptions::new()
+            .max_connections(4)
+            .connect_with(options)
+            .await
+            .expect("could not connect to test database");
+        MIGRATOR.run(&pool).await.expect("migrations failed");
+
+        let mut config = Config::try_parse_from(["foxboard"]).expect("invalid test config");
+        configure(&mut config);
+        let providers = AuthProviders::from_config(&config).expect("invalid auth providers");
+        let oidc = OidcProvider::from_config(&config)
+            .await
+            .expect("oidc discovery failed");
+        Some(Self {
+            state: AppState::new(config, pool.clone(), oidc, providers),
+            pool,
+            url,
+            database,
+        })
+    }
+
+    /// Sends a request through the app, with a session token if a user is given
+    pub async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        user: Option<&TestUser>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut req = test::TestRequest::default().method(method).uri(uri);
+        if let Some(body) = body {
+            req = req.set_json(body);
+        }
+        let (status, _, body) = self.send(req, user).await;
+        (status, serde_json::from_str(&body).unwrap_or(Value::Null))
+    }
+
+    /// Like [`TestApp::request`] for routes that send or take something other than json
+    pub async fn request_text(
+        &self,
+        method: Method,
+        uri: &str,
+        user: Option<&TestUser>,
+        body: Option<(&str, &str)>,
+    ) -> (StatusCode, String) {
+        let mut req = test::TestRequest::default().method(method).uri(uri);
+        if let Some((content_type, body)) = body {
+            req = req
+                .insert_header(("Content-Type", content_type))
+                .set_payload(body.to_owned());
+        }
+        let (status, _, body) = self.send(req, user).await;
+        (status, body)
+    }
+
+    /// Sends the request as is, besides a session token if a user is given
+    pub async fn send(
+        &self,
+        req: test::TestRequest,
+        user: Option<&TestUser>,
+    ) -> (StatusCode, HeaderMap, String) {
+        let app = test::init_service(build_app(&self.state)).await;
+        let mut req = req
+            .peer_addr("127.0.0.1:40000".parse().unwrap())
+            .insert_header(("User-Agent", "foxboard-tests"));
+        if let Some(user) = user {
+            req = req.insert_header(("Authorization", format!("Bearer {}", user.token)));
+        }
+        let res = test::call_service(&app, req.to_request()).await;
+        let status = res.status();
+        let headers = res.headers().clone();
+        let body = test::read_body(res).await;
+        (status, headers, String::from_utf8_lossy(&body).into_owned())
+    }
+
+    pub async fn get(&self, user: &TestUser, uri: &str) -> (StatusCode, Value) {
+        self.request(Method::GET, uri, Some(user), None).await
+    }
+
+    pub async fn post(&self, user: &TestUser, uri: &str, body: Value) -> (StatusCode, Value) {
+        self.request(Method::POST, uri, Some(user), Some(body))
+            .await
+    }
+
+    pub async fn put(&self, user: &TestUser, uri: &str, body: Value) -> (StatusCode, Value) {
+        self.request(Method::PUT, uri, Some(user), Some(body)).await
+    }
+
+    pub async fn delete(&self, user: &TestUser, uri: &str) -> (StatusCode, Value) {
+        self.request(Method::DELETE, uri, Some(user), None).await
+    }
+
+    /// Creates the user in the database and logs in through the api
+    pub async fn create_user(&self, username: &str, is_admin: bool) -> TestUser {
+        let mut user: UserModel =
+            CreateUserMessage::new(username.to_owned(), PASSWORD.to_owned(), is_admin).into();
+        create_user(&mut self.pool.acquire().await.unwrap(), &mut user)
+            .await
+            .unwrap();
+        let token = self.login(username).await;
+        TestUser {
+            id: user.id,
+            username: username.to_owned(),
+            token,
+        }
+    }
+
+    pub async fn login(&self, username: &str) -> String {
+        let (status, body) = self
+            .request(
+                Method::POST,
+                "/api/login",
+                None,
+                Some(json!({"username": username, "password": PASSWORD})),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "login failed: {body}");
+        body["token"].as_str().expect("no session token").to_owned()
+    }
+
+    pub async fn create_project(&self, owner: &TestUser, public: bool) -> Uuid {
+        let (status, body) = self
+            .post(
+                owner,
+                "/api/projects",
+                json!({"name": "test project", "public": public}),
+            )
+            .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        id(&body)
+    }
+
+    /// Creates a column at `index` and returns its id
+    pub async fn create_column(
+        &self,
+        user: &TestUser,
+        project_id: Uuid,
+        name: &str,
+        index: i32,
+    ) -> Uuid {
+        self.create_child(
+            user,
+            project_id,
+            "columns",
+            json!({"name": name, "card_limit": 0, "index": index}),
+        )
+        .await
+    }
+
+    pub async fn create_label(&self, user: &TestUser, project_id: Uuid, name: &str) -> Uuid {
+        self.create_child(user, project_id, "labels", json!({"name": name}))
+            .await
+    }
+
+    pub async fn create_state(&self, user: &TestUser, project_id: Uuid, name: &str) -> Uuid {
+        self.create_child(user, project_id, "states", json!({"name": name}))
+            .await
+    }
+
+    async fn create_child(
+        &self,
+        user: &TestUser,
+        project_id: Uuid,
+        kind: &str,
+        body: Value,
+    ) -> Uuid {
+        let (status, body) = self
+            .post(user, &format!("/api/projects/{project_id}/{kind}"), body)
+            .await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        id(&body)
+    }
+
+    /// Creates a task with the fields in `fields` over those of [`task`]
+    pub async fn create_task(&self, user: &TestUser, project_id: Uuid, fields: Value) -> Uuid {
+        let mut body = task(project_id, user, &[]);
+        for (key, value) in fields.as_object().expect("task fields are an object") {
+            body[key] = value.clone();
+        }
+        let (status, body) = self.post(user, "/api/tasks", body).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        id(&body)
+    }
+
+    /// Gives the user a role on the project, there is no route for that yet
+    pub async fn add_member(&self, project_id: Uuid, user: &TestUser, perms: Permissions) {
+        add_project_member(
+            &mut self.pool.acquire().await.unwrap(),
+            project_id,
+            user.id,
+            perms,
+        )
+        .await
+        .unwrap();
+    }
+}
+
+impl Drop for TestApp {
+    fn drop(&mut self) {
+        // the test's runtime is busy dropping us, so the database is dropped from a fresh one
+        let url = self.url.clone();
+        let database = self.database.clone();
+        let dropped = std::thread::spawn(move || {
+            actix_web::rt::System::new().block_on(async move {
+                let mut conn = PgConnection::connect(&url).await?;
+                sqlx::query(&format!("drop database if exists {database} with (force)"))
+                    .execute(&mut conn)
+                    .await
+            })
+        })
+        .join();
+        if !matches!(dropped, Ok(Ok(_))) {
+            eprintln!("could not drop test database {}", self.database);
+        }
+    }
+}
+
+/// A standard task called "a task", created by `creator`
+pub fn task(project_id: Uuid, creator: &TestUser, labels: &[Uuid]) -> Value {
+    json!({
+        "title": "a task",
+        "project_id": project_id,
+        "creator_id": creator.id,
+        "task_type": 0,
+        "labels": labels,
+    })
+}
+
+/// The `id` of a returned message
+pub fn id(body: &Value) -> Uuid {
+    body["id"]
+        .as_str()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .unwrap_or_else(|| panic!("no id in {body}"))
+}
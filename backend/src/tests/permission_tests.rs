@@ -0,0 +1,265 @@
+use actix_web::http::{Method, StatusCode};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::{id, TestApp, TestUser};
+use crate::data::models::Permissions;
+
+/// A fresh project with one of each child, so every operation finds what it needs
+struct Fixture {
+    project: Uuid,
+    column: Uuid,
+    label: Uuid,
+    state: Uuid,
+    task: Uuid,
+    owner: Uuid,
+}
+
+struct Operation {
+    name: &'static str,
+    required: Permissions,
+    method: Method,
+    uri: fn(&Fixture) -> String,
+    body: fn(&Fixture) -> Option<Value>,
+}
+
+fn task(f: &Fixture) -> Option<Value> {
+    Some(json!({
+        "title": "a task",
+        "project_id": f.project,
+        "creator_id": f.owner,
+        "task_type": 0,
+        "labels": [f.label],
+    }))
+}
+
+fn operations() -> Vec<Operation> {
+    use Permissions::{Editor, Owner, Reader};
+    let none = |_: &Fixture| None;
+    let rename = |_: &Fixture| Some(json!({"name": "renamed"}));
+    vec![
+        Operation {
+            name: "get project",
+            required: Reader,
+            method: Method::GET,
+            uri: |f| format!("/api/projects/{}", f.project),
+            body: none,
+        },
+        Operation {
+            name: "rename project",
+            required: Editor,
+            method: Method::PUT,
+            uri: |f| format!("/api/projects/{}", f.project),
+            body: rename,
+        },
+        Operation {
+            name: "publish project",
+            required: Owner,
+            method: Method::PUT,
+            uri: |f| format!("/api/projects/{}", f.project),
+            body: |_| Some(json!({"public": true})),
+        },
+        Operation {
+            name: "delete project",
+            required: Owner,
+            method: Method::DELETE,
+            uri: |f| format!("/api/projects/{}", f.project),
+            body: none,
+        },
+        Operation {
+            name: "archive project",
+            required: Owner,
+            method: Method::POST,
+            uri: |f| format!("/api/projects/{}/archive", f.project),
+            body: |_| Some(json!({})),
+        },
+        Operation {
+            name: "list trash",
+            required: Reader,
+            method: Method::GET,
+            uri: |f| format!("/api/projects/{}/trash", f.project),
+            body: none,
+        },
+        Operation {
+            name: "create column",
+            required: Editor,
+            method: Method::POST,
+            uri: |f| format!("/api/projects/{}/columns", f.project),
+            body: |_| Some(json!({"name": "done", "card_limit": null, "index": 1})),
+        },
+        Operation {
+            name: "update column",
+            required: Editor,
+            method: Method::PUT,
+            uri: |f| format!("/api/projects/{}/columns/{}", f.project, f.column),
+            body: rename,
+        },
+        Operation {
+            name: "delete column",
+            required: Editor,
+            method: Method::DELETE,
+            uri: |f| format!("/api/projects/{}/columns/{}", f.project, f.column),
+            body: none,
+        },
+        Operation {
+            name: "create label",
+            required: Editor,
+            method: Method::POST,
+            uri: |f| format!("/api/projects/{}/labels", f.project),
+            body: |_| Some(json!({"name": "feature"})),
+        },
+        Operation {
+            name: "update label",
+            required: Editor,
+            method: Method::PUT,
+            uri: |f| format!("/api/projects/{}/labels/{}", f.project, f.label),
+            body: rename,
+        },
+        Operation {
+            name: "delete label",
+            required: Editor,
+            method: Method::DELETE,
+            uri: |f| format!("/api/projects/{}/labels/{}", f.project, f.label),
+            body: none,
+        },
+        Operation {
+            name: "create state",
+            required: Editor,
+            method: Method::POST,
+            uri: |f| format!("/api/projects/{}/states", f.project),
+            body: |_| Some(json!({"name": "closed"})),
+        },
+        Operation {
+            name: "update state",
+            required: Editor,
+            method: Method::PUT,
+            uri: |f| format!("/api/projects/{}/states/{}", f.project, f.state),
+            body: rename,
+        },
+        Operation {
+            name: "delete state",
+            required: Editor,
+            method: Method::DELETE,
+            uri: |f| format!("/api/projects/{}/states/{}", f.project, f.state),
+            body: none,
+        },
+        Operation {
+            name: "create task",
+            required: Editor,
+            method: Method::POST,
+            uri: |_| "/api/tasks".to_owned(),
+            body: task,
+        },
+        Operation {
+            name: "get task",
+            required: Reader,
+            method: Method::GET,
+            uri: |f| format!("/api/tasks/{}", f.task),
+            body: none,
+        },
+        Operation {
+            name: "update task",
+            required: Editor,
+            method: Method::POST,
+            uri: |f| format!("/api/tasks/{}", f.task),
+            body: task,
+        },
+        Operation {
+            name: "delete task",
+            required: Editor,
+            method: Method::DELETE,
+            uri: |f| format!("/api/tasks/{}", f.task),
+            body: none,
+        },
+    ]
+}
+
+async fn fixture(
+    app: &TestApp,
+    owner: &TestUser,
+    editor: &TestUser,
+    reader: &TestUser,
+    public: bool,
+) -> Fixture {
+    let project = app.create_project(owner, public).await;
+    app.add_member(project, editor, Permissions::Editor).await;
+    app.add_member(project, reader, Permissions::Reader).await;
+    let child = |kind: &'static str, body: Value| {
+        let uri = format!("/api/projects/{project}/{kind}");
+        async move {
+            let (status, body) = app.post(owner, &uri, body).await;
+            assert_eq!(status, StatusCode::OK, "{body}");
+            id(&body)
+        }
+    };
+    let column = child(
+        "columns",
+        json!({"name": "todo", "card_limit": 3, "index": 0}),
+    )
+    .await;
+    let label = child("labels", json!({"name": "bug"})).await;
+    let state = child("states", json!({"name": "open"})).await;
+    let mut f = Fixture {
+        project,
+        column,
+        label,
+        state,
+        task: Uuid::nil(),
+        owner: owner.id,
+    };
+    let (status, body) = app.post(owner, "/api/tasks", task(&f).unwrap()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    f.task = id(&body);
+    f
+}
+
+/// Every role against every project operation, on private and public projects
+#[actix_web::test]
+async fn permission_matrix() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let admin = app.create_user("admin", true).await;
+    let owner = app.create_user("owner", false).await;
+    let editor = app.create_user("editor", false).await;
+    let reader = app.create_user("reader", false).await;
+    let stranger = app.create_user("stranger", false).await;
+
+    let mut failures = Vec::new();
+    for public in [false, true] {
+        let roles = [
+            (&admin, Permissions::Admin),
+            (&owner, Permissions::Owner),
+            (&editor, Permissions::Editor),
+            (&reader, Permissions::Reader),
+            (
+                &stranger,
+                match public {
+                    true => Permissions::Reader,
+                    false => Permissions::None,
+                },
+            ),
+        ];
+        for op in operations() {
+            for (user, role) in &roles {
+                let f = fixture(&app, &owner, &editor, &reader, public).await;
+                let (status, _) = app
+                    .request(op.method.clone(), &(op.uri)(&f), Some(user), (op.body)(&f))
+                    .await;
+                let expected = match *role >= op.required {
+                    true => StatusCode::OK,
+                    false => StatusCode::FORBIDDEN,
+                };
+                if status != expected {
+                    failures.push(format!(
+                        "{} on {} project: {} got {status}, expected {expected}",
+                        op.name,
+                        if public { "public" } else { "private" },
+                        user.username,
+                    ));
+                }
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
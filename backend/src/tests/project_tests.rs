@@ -0,0 +1,271 @@
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use super::{id, TestApp};
+use crate::data::models::Permissions;
+
+#[actix_web::test]
+async fn create_get_and_list_projects() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let private = app.create_project(&alice, false).await;
+    let public = app.create_project(&alice, true).await;
+
+    let (status, body) = app.get(&alice, &format!("/api/projects/{private}")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["owner_id"], alice.id.to_string());
+    assert!(body["columns"].is_array());
+
+    let (status, _) = app.get(&bob, &format!("/api/projects/{private}")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.get(&bob, &format!("/api/projects/{public}")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = app.get(&alice, "/api/projects/list?page=1&count=10").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 2);
+    let (_, body) = app.get(&bob, "/api/projects/list?page=1&count=10").await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["id"], public.to_string());
+}
+
+#[actix_web::test]
+async fn update_project() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let carol = app.create_user("carol", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.add_member(project, &bob, Permissions::Editor).await;
+    let uri = format!("/api/projects/{project}");
+
+    let (status, body) = app.put(&bob, &uri, json!({"name": "renamed"})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["name"], "renamed");
+    // changing visibility needs the owner
+    let (status, _) = app.put(&bob, &uri, json!({"public": true})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.put(&carol, &uri, json!({"name": "mine"})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = app.put(&alice, &uri, json!({"public": true})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["public"], true);
+}
+
+#[actix_web::test]
+async fn delete_project() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.add_member(project, &bob, Permissions::Editor).await;
+    let uri = format!("/api/projects/{project}");
+
+    let (status, _) = app.delete(&bob, &uri).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.delete(&alice, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.get(&alice, &uri).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn admins_manage_every_project() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let admin = app.create_user("admin", true).await;
+    let alice = app.create_user("alice", false).await;
+    let project = app.create_project(&alice, false).await;
+
+    let (_, body) = app.get(&admin, "/api/projects/list?page=1&count=10").await;
+    assert_eq!(body["total"], 1);
+    let uri = format!("/api/projects/{project}");
+    let (status, _) = app.put(&admin, &uri, json!({"public": true})).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.delete(&admin, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+/// Columns, labels and states are created, updated and deleted the same way
+#[actix_web::test]
+async fn project_children() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let editor = app.create_user("editor", false).await;
+    let reader = app.create_user("reader", false).await;
+    let stranger = app.create_user("stranger", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.add_member(project, &editor, Permissions::Editor).await;
+    app.add_member(project, &reader, Permissions::Reader).await;
+
+    let children = [
+        (
+            "columns",
+            json!({"name": "todo", "card_limit": 5, "index": 0}),
+        ),
+        ("labels", json!({"name": "bug"})),
+        ("states", json!({"name": "open"})),
+    ];
+    for (kind, create) in children {
+        let uri = format!("/api/projects/{project}/{kind}");
+        for user in [&reader, &stranger] {
+            let (status, _) = app.post(user, &uri, create.clone()).await;
+            assert_eq!(
+                status,
+                StatusCode::FORBIDDEN,
+                "{} creating {kind}",
+                user.username
+            );
+        }
+        for user in [&alice, &editor] {
+            let (status, body) = app.post(user, &uri, create.clone()).await;
+            assert_eq!(
+                status,
+                StatusCode::OK,
+                "{} creating {kind}: {body}",
+                user.username
+            );
+            assert_eq!(body["project_id"], project.to_string());
+            let child = format!("{uri}/{}", id(&body));
+
+            let rename = json!({"name": "renamed"});
+            for denied in [&reader, &stranger] {
+                let (status, _) = app.put(denied, &child, rename.clone()).await;
+                assert_eq!(
+                    status,
+                    StatusCode::FORBIDDEN,
+                    "{} updating {kind}",
+                    denied.username
+                );
+                let (status, _) = app.delete(denied, &child).await;
+                assert_eq!(
+                    status,
+                    StatusCode::FORBIDDEN,
+                    "{} deleting {kind}",
+                    denied.username
+                );
+            }
+            let (status, body) = app.put(user, &child, rename).await;
+            assert_eq!(status, StatusCode::OK, "{} updating {kind}", user.username);
+            assert_eq!(body["name"], "renamed");
+            let (status, _) = app.delete(user, &child).await;
+            assert_eq!(status, StatusCode::OK, "{} deleting {kind}", user.username);
+        }
+    }
+
+    let (_, body) = app.get(&alice, &format!("/api/projects/{project}")).await;
+    assert_eq!(body["columns"], json!([]));
+    assert_eq!(body["labels"], json!([]));
+}
+
+#[actix_web::test]
+async fn children_are_scoped_to_their_project() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let bobs = app.create_project(&bob, false).await;
+    let alices = app.create_project(&alice, false).await;
+
+    let children = [
+        (
+            "columns",
+            json!({"name": "todo", "card_limit": 5, "index": 0}),
+        ),
+        ("labels", json!({"name": "bug"})),
+        ("states", json!({"name": "open"})),
+    ];
+    for (kind, create) in children {
+        let (_, body) = app
+            .post(&alice, &format!("/api/projects/{alices}/{kind}"), create)
+            .await;
+        // bob edits his own project but names alice's child
+        let uri = format!("/api/projects/{bobs}/{kind}/{}", id(&body));
+        let (status, _) = app.put(&bob, &uri, json!({"name": "mine"})).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "updating {kind}");
+        let (status, _) = app.delete(&bob, &uri).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "deleting {kind}");
+    }
+
+    let (_, body) = app.get(&alice, &format!("/api/projects/{alices}")).await;
+    assert_eq!(body["columns"][0]["name"], "todo");
+    assert_eq!(body["labels"][0]["name"], "bug");
+}
+
+#[actix_web::test]
+async fn clone_project() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let carol = app.create_user("carol", false).await;
+    let stranger = app.create_user("stranger", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.add_member(project, &bob, Permissions::Editor).await;
+    app.add_member(project, &carol, Permissions::Reader).await;
+    let base = format!("/api/projects/{project}");
+    app.create_column(&alice, project, "todo", 0).await;
+    let label = app.create_label(&alice, project, "bug").await;
+    let state = app.create_state(&alice, project, "open").await;
+    app.create_task(
+        &alice,
+        project,
+        json!({"state_id": state, "labels": [label]}),
+    )
+    .await;
+    let pool = &app.pool;
+    let tasks = |project| async move {
+        sqlx::query_scalar::<_, i64>("select count(*) from tasks where project_id=$1")
+            .bind(project)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    };
+
+    let (status, _) = app
+        .post(&stranger, &format!("{base}/clone"), json!({}))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    // structure only
+    let (status, body) = app.post(&carol, &format!("{base}/clone"), json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["name"], "test project (copy)");
+    assert_eq!(body["owner_id"], carol.id.to_string());
+    let copy = id(&body);
+    let (_, body) = app.get(&carol, &format!("/api/projects/{copy}")).await;
+    assert_eq!(body["columns"][0]["name"], "todo");
+    assert_eq!(body["labels"], json!([]));
+    assert_eq!(tasks(copy).await, 0);
+    let (status, _) = app.get(&bob, &format!("/api/projects/{copy}")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let full =
+        json!({"name": "full", "labels": true, "states": true, "tasks": true, "members": true});
+    let (status, _) = app.post(&bob, &format!("{base}/clone"), full.clone()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = app.post(&alice, &format!("{base}/clone"), full).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["name"], "full");
+    assert_eq!(body["public"], false);
+    let copy = id(&body);
+    let (status, body) = app.get(&carol, &format!("/api/projects/{copy}")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["labels"][0]["name"], "bug");
+    assert_ne!(body["labels"][0]["id"], json!(label));
+    assert_eq!(tasks(copy).await, 1);
+    let (status, _) = app.get(&stranger, &format!("/api/projects/{copy}")).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
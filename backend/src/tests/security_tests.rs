@@ -0,0 +1,130 @@
+use actix_web::{
+    http::{Method, StatusCode},
+    test,
+};
+
+use super::TestApp;
+
+const ORIGIN: &str = "https://board.example";
+
+fn preflight(origin: &str, method: &str) -> test::TestRequest {
+    test::TestRequest::default()
+        .method(Method::OPTIONS)
+        .uri("/api/projects/list")
+        .insert_header(("Origin", origin))
+        .insert_header(("Access-Control-Request-Method", method))
+        .insert_header(("Access-Control-Request-Headers", "authorization"))
+}
+
+#[actix_web::test]
+async fn security_headers() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let default_policy = app.state.config.content_security_policy.clone();
+    // on errors as well as on successful responses
+    for uri in ["/healthz", "/api/nothing"] {
+        let (_, headers, _) = app.send(test::TestRequest::get().uri(uri), None).await;
+        assert_eq!(
+            headers.get("Content-Security-Policy").unwrap(),
+            default_policy.as_str(),
+            "{uri}"
+        );
+        assert_eq!(headers.get("X-Content-Type-Options").unwrap(), "nosniff");
+        assert_eq!(headers.get("X-Frame-Options").unwrap(), "DENY");
+        assert_eq!(headers.get("Referrer-Policy").unwrap(), "no-referrer");
+        // plain http, the header would be ignored or pin a broken setup
+        assert!(headers.get("Strict-Transport-Security").is_none());
+    }
+
+    let Some(app) = TestApp::start_with(|c| {
+        c.tls_cert = Some("cert.pem".into());
+        c.tls_key = Some("key.pem".into());
+        c.content_security_policy = "default-src 'none'".to_owned();
+    })
+    .await
+    else {
+        return;
+    };
+    let (_, headers, _) = app
+        .send(test::TestRequest::get().uri("/healthz"), None)
+        .await;
+    assert_eq!(
+        headers.get("Strict-Transport-Security").unwrap(),
+        "max-age=31536000; includeSubDomains"
+    );
+    assert_eq!(
+        headers.get("Content-Security-Policy").unwrap(),
+        "default-src 'none'"
+    );
+}
+
+#[actix_web::test]
+async fn cors() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    // without configured origins nothing is allowed cross origin
+    let (_, headers, _) = app.send(preflight(ORIGIN, "GET"), None).await;
+    assert!(headers.get("Access-Control-Allow-Origin").is_none());
+
+    let Some(app) = TestApp::start_with(|c| {
+        c.cors_origins = vec![ORIGIN.to_owned()];
+        c.cors_credentials = true;
+    })
+    .await
+    else {
+        return;
+    };
+    let (status, headers, _) = app.send(preflight(ORIGIN, "POST"), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers.get("Access-Control-Allow-Origin").unwrap(), ORIGIN);
+    assert_eq!(
+        headers.get("Access-Control-Allow-Credentials").unwrap(),
+        "true"
+    );
+
+    let (status, headers, _) = app
+        .send(
+            test::TestRequest::get()
+                .uri("/healthz")
+                .insert_header(("Origin", ORIGIN)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers.get("Access-Control-Allow-Origin").unwrap(), ORIGIN);
+    assert!(headers
+        .get("Access-Control-Expose-Headers")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_lowercase()
+        .contains("x-request-id"));
+    // the browser only hands errors to the page with the header
+    let (status, headers, _) = app
+        .send(
+            test::TestRequest::get()
+                .uri("/api/projects/list")
+                .insert_header(("Origin", ORIGIN)),
+            None,
+        )
+        .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(headers.get("Access-Control-Allow-Origin").unwrap(), ORIGIN);
+
+    for (origin, method) in [("https://evil.example", "GET"), (ORIGIN, "PATCH")] {
+        let (status, headers, _) = app.send(preflight(origin, method), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{origin} {method}");
+        assert!(headers.get("Access-Control-Allow-Origin").is_none());
+    }
+    let (_, headers, _) = app
+        .send(
+            test::TestRequest::get()
+                .uri("/healthz")
+                .insert_header(("Origin", "https://evil.example")),
+            None,
+        )
+        .await;
+    assert!(headers.get("Access-Control-Allow-Origin").is_none());
+}
@@ -0,0 +1,119 @@
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use super::{id, task, TestApp};
+use crate::data::models::Permissions;
+
+#[actix_web::test]
+async fn task_lifecycle() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let project = app.create_project(&alice, false).await;
+    let bug = app.create_label(&alice, project, "bug").await;
+    let feature = app.create_label(&alice, project, "feature").await;
+
+    // a label given twice is set once
+    let (status, body) = app
+        .post(&alice, "/api/tasks", task(project, &alice, &[bug, bug]))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["labels"], json!([bug]));
+    let uri = format!("/api/tasks/{}", id(&body));
+    let (status, body) = app.get(&alice, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["labels"], json!([bug]));
+
+    let mut update = task(project, &alice, &[feature, feature]);
+    update["title"] = json!("renamed");
+    update["description"] = json!("more to say");
+    let (status, body) = app.post(&alice, &uri, update).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let (_, body) = app.get(&alice, &uri).await;
+    assert_eq!(body["title"], "renamed");
+    assert_eq!(body["description"], "more to say");
+    assert_eq!(body["labels"], json!([feature]));
+
+    let (status, _) = app.delete(&alice, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.get(&alice, &uri).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[actix_web::test]
+async fn task_permissions() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let editor = app.create_user("editor", false).await;
+    let reader = app.create_user("reader", false).await;
+    let stranger = app.create_user("stranger", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.add_member(project, &editor, Permissions::Editor).await;
+    app.add_member(project, &reader, Permissions::Reader).await;
+
+    for user in [&reader, &stranger] {
+        let (status, _) = app.post(user, "/api/tasks", task(project, user, &[])).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{} creating", user.username);
+    }
+    let (status, body) = app
+        .post(&editor, "/api/tasks", task(project, &editor, &[]))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let uri = format!("/api/tasks/{}", id(&body));
+
+    let (status, _) = app.get(&reader, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.get(&stranger, &uri).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    for user in [&reader, &stranger] {
+        let (status, _) = app.post(user, &uri, task(project, user, &[])).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{} updating", user.username);
+        let (status, _) = app.delete(user, &uri).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{} deleting", user.username);
+    }
+    let (status, _) = app.get(&alice, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn task_references_stay_in_the_project() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let bobs = app.create_project(&bob, false).await;
+    let alices = app.create_project(&alice, false).await;
+
+    let column = app.create_column(&alice, alices, "todo", 0).await;
+    let state = app.create_state(&alice, alices, "open").await;
+    let label = app.create_label(&alice, alices, "bug").await;
+    let parent = app.create_task(&alice, alices, json!({})).await;
+
+    let mut foreign = Vec::new();
+    for (field, value) in [
+        ("column_id", json!(column)),
+        ("state_id", json!(state)),
+        ("parent_id", json!(parent)),
+        ("labels", json!([label])),
+    ] {
+        let mut msg = task(bobs, &bob, &[]);
+        msg[field] = value;
+        foreign.push((field, msg));
+    }
+    for (field, msg) in &foreign {
+        let (status, _) = app.post(&bob, "/api/tasks", msg.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "creating with {field}");
+    }
+
+    let own = app.create_task(&bob, bobs, json!({})).await;
+    let uri = format!("/api/tasks/{own}");
+    // updates keep the parent, so only the other references are checked
+    for (field, msg) in foreign.into_iter().filter(|(f, _)| *f != "parent_id") {
+        let (status, _) = app.post(&bob, &uri, msg).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "updating with {field}");
+    }
+}
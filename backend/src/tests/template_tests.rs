@@ -0,0 +1,80 @@
+use actix_web::http::StatusCode;
+use serde_json::json;
+
+use super::{id, TestApp};
+
+#[actix_web::test]
+async fn templates() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let admin = app.create_user("admin", true).await;
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.create_column(&alice, project, "todo", 0).await;
+    app.create_label(&alice, project, "bug").await;
+    app.create_task(&alice, project, json!({"title": "checklist"}))
+        .await;
+
+    let create = json!({"name": "board", "project_id": project, "tasks": true});
+    let (status, _) = app.post(&bob, "/api/templates", create.clone()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = app.post(&alice, "/api/templates", create).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["published"], false);
+    assert_eq!(body["structure"]["tasks"][0]["creator"], json!(null));
+    let uri = format!("/api/templates/{}", id(&body));
+
+    // unpublished templates are private to their owner
+    let (status, _) = app.get(&bob, &uri).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (_, body) = app.get(&bob, "/api/templates/list").await;
+    assert_eq!(body["total"], 0);
+    let (status, _) = app.put(&alice, &uri, json!({"published": true})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = app.put(&alice, &uri, json!({"name": "kanban"})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["name"], "kanban");
+    let (status, _) = app.put(&admin, &uri, json!({"published": true})).await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = app.get(&bob, "/api/templates/list").await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["name"], "kanban");
+
+    let (status, body) = app
+        .post(
+            &bob,
+            "/api/projects",
+            json!({"name": "bobs board", "public": false, "template_id": id(&body["items"][0])}),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["name"], "bobs board");
+    assert_eq!(body["owner_id"], bob.id.to_string());
+    let (_, body) = app.get(&bob, &format!("/api/projects/{}", id(&body))).await;
+    assert_eq!(body["columns"][0]["name"], "todo");
+    assert_eq!(body["labels"][0]["name"], "bug");
+    let tasks: Vec<(String, uuid::Uuid)> =
+        sqlx::query_as("select title, creator_id from tasks where project_id=$1")
+            .bind(id(&body))
+            .fetch_all(&app.pool)
+            .await
+            .unwrap();
+    assert_eq!(tasks, [("checklist".to_owned(), bob.id)]);
+
+    let (status, _) = app
+        .post(
+            &bob,
+            "/api/projects",
+            json!({"name": "nothing", "public": false, "template_id": project}),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = app.delete(&bob, &uri).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.delete(&alice, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.get(&alice, &uri).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,309 @@
+use actix_web::http::{Method, StatusCode};
+use serde_json::{json, Value};
+
+use super::{task, TestApp};
+use crate::data::models::Permissions;
+use crate::data::project_data::purge_trash;
+
+#[actix_web::test]
+async fn archive_and_unarchive() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.add_member(project, &bob, Permissions::Editor).await;
+    let uri = format!("/api/projects/{project}");
+    let column = app.create_column(&alice, project, "todo", 0).await;
+    let task_id = app.create_task(&alice, project, json!({})).await;
+    let task_msg = task(project, &alice, &[]);
+
+    let (status, _) = app.post(&bob, &format!("{uri}/archive"), json!({})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = app.post(&alice, &format!("{uri}/archive"), json!({})).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["archived"].is_i64());
+
+    let (_, body) = app.get(&alice, "/api/projects/list").await;
+    assert_eq!(body["total"], 0);
+    let (_, body) = app.get(&alice, "/api/projects/archived").await;
+    assert_eq!(body["total"], 1);
+    let (status, body) = app.get(&alice, &uri).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["archived"].is_i64());
+    let (status, _) = app.get(&alice, &format!("/api/tasks/{task_id}")).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // read-only until it is unarchived, for the owner as well
+    let writes = [
+        (Method::POST, "/api/tasks".to_owned(), task_msg.clone()),
+        (Method::POST, format!("/api/tasks/{task_id}"), task_msg),
+        (Method::DELETE, format!("/api/tasks/{task_id}"), Value::Null),
+        (Method::PUT, uri.clone(), json!({"name": "renamed"})),
+        (Method::POST, format!("{uri}/archive"), json!({})),
+        (
+            Method::POST,
+            format!("{uri}/columns"),
+            json!({"name": "done", "index": 1}),
+        ),
+        (
+            Method::PUT,
+            format!("{uri}/columns/{column}"),
+            json!({"name": "doing"}),
+        ),
+        (
+            Method::DELETE,
+            format!("{uri}/columns/{column}"),
+            Value::Null,
+        ),
+        (
+            Method::POST,
+            format!("{uri}/labels"),
+            json!({"name": "feature"}),
+        ),
+        (
+            Method::POST,
+            format!("{uri}/states"),
+            json!({"name": "closed"}),
+        ),
+        (
+            Method::POST,
+            format!("{uri}/trash/restore"),
+            json!({"kind": "task", "id": task_id}),
+        ),
+    ];
+    for (method, path, body) in writes {
+        let body = Some(body).filter(|b| !b.is_null());
+        let (status, _) = app.request(method.clone(), &path, Some(&alice), body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN, "{method} {path}");
+    }
+    let (status, _) = app
+        .request_text(
+            Method::POST,
+            &format!("{uri}/tasks.csv"),
+            Some(&alice),
+            Some(("text/csv", "title\nanother\n")),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (_, feed) = app.post(&alice, "/api/users/feed", json!({})).await;
+    let feed = format!(
+        "/api/feeds/{}/projects/{project}/tasks.ics",
+        feed["token"].as_str().unwrap()
+    );
+    let (status, _) = app.request_text(Method::GET, &feed, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = app
+        .post(&alice, &format!("{uri}/unarchive"), json!({}))
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["archived"], Value::Null);
+    let (_, body) = app.get(&alice, "/api/projects/list").await;
+    assert_eq!(body["total"], 1);
+    let (_, body) = app.get(&alice, "/api/projects/archived").await;
+    assert_eq!(body["total"], 0);
+    let (status, _) = app.delete(&alice, &format!("/api/tasks/{task_id}")).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.request_text(Method::GET, &feed, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn trash_and_restore() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let reader = app.create_user("reader", false).await;
+    let project = app.create_project(&alice, false).await;
+    app.add_member(project, &reader, Permissions::Reader).await;
+    let base = format!("/api/projects/{project}");
+
+    let column = app.create_column(&alice, project, "todo", 0).await;
+    let label = app.create_label(&alice, project, "bug").await;
+    let state = app.create_state(&alice, project, "open").await;
+    let parent = app
+        .create_task(&alice, project, json!({"labels": [label]}))
+        .await;
+    let sub = app
+        .create_task(&alice, project, json!({"parent_id": parent}))
+        .await;
+
+    for uri in [
+        format!("{base}/columns/{column}"),
+        format!("{base}/labels/{label}"),
+        format!("{base}/states/{state}"),
+        format!("/api/tasks/{parent}"),
+    ] {
+        let (status, _) = app.delete(&alice, &uri).await;
+        assert_eq!(status, StatusCode::OK, "deleting {uri}");
+        let (status, _) = app.delete(&alice, &uri).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "deleting {uri} again");
+    }
+    let (_, body) = app.get(&alice, &base).await;
+    assert_eq!(body["columns"], json!([]));
+    assert_eq!(body["labels"], json!([]));
+    let (status, _) = app.get(&alice, &format!("/api/tasks/{sub}")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // the subtask went with its parent and isn't listed on its own
+    let (status, trash) = app.get(&reader, &format!("{base}/trash")).await;
+    assert_eq!(status, StatusCode::OK);
+    let mut kinds: Vec<_> = trash
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["kind"].as_str().unwrap())
+        .collect();
+    kinds.sort();
+    assert_eq!(kinds, ["column", "label", "state", "task"]);
+
+    let restore = format!("{base}/trash/restore");
+    let (status, _) = app
+        .post(&reader, &restore, json!({"kind": "task", "id": parent}))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    for (kind, item) in [
+        ("task", &parent),
+        ("label", &label),
+        ("column", &column),
+        ("state", &state),
+    ] {
+        let msg = json!({"kind": kind, "id": item});
+        let (status, _) = app.post(&alice, &restore, msg.clone()).await;
+        assert_eq!(status, StatusCode::OK, "restoring {kind}");
+        let (status, _) = app.post(&alice, &restore, msg).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "restoring {kind} again");
+    }
+
+    let (status, body) = app.get(&alice, &format!("/api/tasks/{sub}")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["parent_id"], json!(parent));
+    let (_, body) = app.get(&alice, &format!("/api/tasks/{parent}")).await;
+    assert_eq!(body["labels"], json!([label]));
+    let (_, body) = app.get(&alice, &base).await;
+    assert_eq!(body["columns"][0]["name"], "todo");
+    let (_, trash) = app.get(&alice, &format!("{base}/trash")).await;
+    assert_eq!(trash, json!([]));
+}
+
+#[actix_web::test]
+async fn trashed_column_and_state_stay_on_tasks() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let project = app.create_project(&alice, false).await;
+    let base = format!("/api/projects/{project}");
+    let column = app.create_column(&alice, project, "todo", 0).await;
+    let state = app.create_state(&alice, project, "open").await;
+    let refs = json!({"column_id": column, "state_id": state});
+    let kept = app.create_task(&alice, project, refs.clone()).await;
+    for uri in [
+        format!("{base}/columns/{column}"),
+        format!("{base}/states/{state}"),
+    ] {
+        let (status, _) = app.delete(&alice, &uri).await;
+        assert_eq!(status, StatusCode::OK, "deleting {uri}");
+    }
+
+    // a task that has them can be saved as it is, they can't be given to others
+    let uri = format!("/api/tasks/{kept}");
+    let (_, mut msg) = app.get(&alice, &uri).await;
+    msg["title"] = json!("renamed");
+    let (status, body) = app.post(&alice, &uri, msg).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let mut msg = task(project, &alice, &[]);
+    msg["column_id"] = refs["column_id"].clone();
+    msg["state_id"] = refs["state_id"].clone();
+    let (status, _) = app.post(&alice, "/api/tasks", msg.clone()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let other = app.create_task(&alice, project, json!({})).await;
+    let (status, _) = app.post(&alice, &format!("/api/tasks/{other}"), msg).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let restore = format!("{base}/trash/restore");
+    for (kind, item) in [("column", column), ("state", state)] {
+        let (status, _) = app
+            .post(&alice, &restore, json!({"kind": kind, "id": item}))
+            .await;
+        assert_eq!(status, StatusCode::OK, "restoring {kind}");
+    }
+    let (_, body) = app.get(&alice, &uri).await;
+    assert_eq!(body["title"], "renamed");
+    assert_eq!(body["column_id"], refs["column_id"]);
+    assert_eq!(body["state_id"], refs["state_id"]);
+}
+
+#[actix_web::test]
+async fn purge_old_trash() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let project = app.create_project(&alice, false).await;
+    let base = format!("/api/projects/{project}");
+    let old = app.create_label(&alice, project, "old").await;
+    let recent = app.create_label(&alice, project, "recent").await;
+    for label in [old, recent] {
+        app.delete(&alice, &format!("{base}/labels/{label}")).await;
+    }
+    sqlx::query("update labels set deleted=now() - interval '40 days' where id=$1")
+        .bind(old)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let purged = purge_trash(&mut app.pool.acquire().await.unwrap(), 30)
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+    let (_, trash) = app.get(&alice, &format!("{base}/trash")).await;
+    assert_eq!(trash.as_array().unwrap().len(), 1);
+    assert_eq!(trash[0]["id"], json!(recent));
+}
+
+#[actix_web::test]
+async fn restored_subtask_outlives_its_parent() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let project = app.create_project(&alice, false).await;
+    let parent = app.create_task(&alice, project, json!({})).await;
+    let sub = app
+        .create_task(&alice, project, json!({"parent_id": parent}))
+        .await;
+    for task in [sub, parent] {
+        let (status, _) = app.delete(&alice, &format!("/api/tasks/{task}")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // the parent stays in the trash, the subtask comes back on its own
+    let (status, _) = app
+        .post(
+            &alice,
+            &format!("/api/projects/{project}/trash/restore"),
+            json!({"kind": "task", "id": sub}),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (_, body) = app.get(&alice, &format!("/api/tasks/{sub}")).await;
+    assert_eq!(body["parent_id"], Value::Null);
+
+    sqlx::query("update tasks set deleted=now() - interval '40 days' where id=$1")
+        .bind(parent)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+    let purged = purge_trash(&mut app.pool.acquire().await.unwrap(), 30)
+        .await
+        .unwrap();
+    assert_eq!(purged, 1);
+    let (status, _) = app.get(&alice, &format!("/api/tasks/{sub}")).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.get(&alice, &format!("/api/tasks/{parent}")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
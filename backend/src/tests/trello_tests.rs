@@ -0,0 +1,183 @@
+use actix_web::http::StatusCode;
+use serde_json::{json, Value};
+
+use super::{id, TestApp};
+
+fn board() -> Value {
+    json!({
+        "id": "5f0000000000000000000000",
+        "name": "Team board",
+        "desc": "not read",
+        "lists": [
+            {"id": "l2", "name": "Doing", "closed": false, "pos": 2048},
+            {"id": "l1", "name": "Todo", "closed": false, "pos": 1024},
+            {"id": "l3", "name": "Old", "closed": true, "pos": 4096},
+        ],
+        "labels": [
+            {"id": "b1", "name": "bug", "color": "red"},
+            {"id": "b2", "name": "", "color": "green"},
+        ],
+        "members": [
+            {"id": "m1", "username": "bob", "fullName": "Bob"},
+            {"id": "m2", "username": "ghost", "fullName": "Nobody Here"},
+        ],
+        "cards": [
+            {
+                "id": "c1",
+                "name": "Fix login",
+                "desc": "It breaks on mondays",
+                "closed": false,
+                "idList": "l1",
+                "idLabels": ["b1", "b2"],
+                "idMembers": ["m1", "m2"],
+                "due": "2024-12-24T18:00:00.000Z",
+                "dueComplete": false,
+                "attachments": [{"name": "screenshot.png"}],
+            },
+            {
+                "id": "c2",
+                "name": "Ship it",
+                "desc": "",
+                "closed": false,
+                "idList": "l2",
+                "idLabels": [],
+                "idMembers": [],
+                "due": null,
+                "dueComplete": true,
+            },
+            {"id": "c3", "name": "Forgotten", "closed": true, "idList": "l1"},
+        ],
+        "checklists": [
+            {
+                "id": "k1",
+                "idCard": "c1",
+                "name": "Steps",
+                "checkItems": [
+                    {"name": "second", "state": "incomplete", "pos": 2},
+                    {"name": "first", "state": "complete", "pos": 1},
+                ],
+            },
+        ],
+        "actions": [
+            {"type": "commentCard", "data": {"text": "hi"}},
+            {"type": "updateCard"},
+        ],
+    })
+}
+
+#[actix_web::test]
+async fn import_trello_board() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let admin = app.create_user("admin", true).await;
+    let bob = app.create_user("bob", false).await;
+
+    let (status, body) = app
+        .post(&admin, "/api/projects/import/trello", board())
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["project"]["name"], "Team board");
+    let mut unmapped: Vec<_> = body["unmapped"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|u| u.as_str().unwrap().to_owned())
+        .collect();
+    unmapped.sort();
+    assert_eq!(
+        unmapped,
+        [
+            "1 comments aren't imported",
+            "card 'Fix login': 1 attachments aren't imported",
+            "member ghost: no user with that name",
+        ]
+    );
+    let project = id(&body["project"]);
+
+    // bob was found by username and became a member
+    let (status, export) = app
+        .get(&bob, &format!("/api/projects/{project}/export"))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let names = |key: &str| -> Vec<Value> {
+        export[key]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["name"].clone())
+            .collect()
+    };
+    assert_eq!(names("columns"), [json!("Todo"), json!("Doing")]);
+    assert_eq!(names("labels"), [json!("bug"), json!("green")]);
+    assert_eq!(names("states"), [json!("Complete")]);
+
+    let tasks = export["tasks"].as_array().unwrap();
+    let task = |title: &str| {
+        tasks
+            .iter()
+            .find(|t| t["title"] == title)
+            .unwrap_or_else(|| panic!("no task {title}"))
+    };
+    let login = task("Fix login");
+    assert_eq!(login["description"], "It breaks on mondays");
+    assert_eq!(login["column_id"], export["columns"][0]["id"]);
+    assert_eq!(login["assignee"], "bob");
+    assert_eq!(login["creator"], "admin");
+    assert_eq!(login["labels"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        login["deadline"],
+        chrono::DateTime::parse_from_rfc3339("2024-12-24T18:00:00Z")
+            .unwrap()
+            .timestamp_millis()
+    );
+    assert_eq!(task("Ship it")["state_id"], export["states"][0]["id"]);
+    assert_eq!(task("first")["parent_id"], login["id"]);
+    assert_eq!(task("first")["state_id"], export["states"][0]["id"]);
+    assert_eq!(task("second")["state_id"], Value::Null);
+    assert_eq!(tasks.len(), 4);
+
+    // archived lists and cards wait in the trash
+    let (_, trash) = app
+        .get(&admin, &format!("/api/projects/{project}/trash"))
+        .await;
+    let mut trashed: Vec<_> = trash
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| (t["kind"].as_str().unwrap(), t["name"].as_str().unwrap()))
+        .collect();
+    trashed.sort();
+    assert_eq!(trashed, [("column", "Old"), ("task", "Forgotten")]);
+}
+
+#[actix_web::test]
+async fn import_trello_board_without_members() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+
+    // bob didn't agree to join, only an admin adds him
+    let (status, body) = app
+        .post(&alice, "/api/projects/import/trello", board())
+        .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["unmapped"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("member bob: only admins can add members")));
+    let uri = format!("/api/projects/{}/export", id(&body["project"]));
+    let (status, _) = app.get(&bob, &uri).await;
+    assert_ne!(status, StatusCode::OK);
+    let (_, export) = app.get(&alice, &uri).await;
+    assert!(export["members"].as_array().unwrap().is_empty());
+    let login = export["tasks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|t| t["title"] == "Fix login")
+        .unwrap();
+    assert_eq!(login["assignee"], Value::Null);
+}
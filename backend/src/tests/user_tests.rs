@@ -0,0 +1,559 @@
+use actix_web::{
+    http::{Method, StatusCode},
+    test,
+};
+use serde_json::json;
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use super::{TestApp, PASSWORD};
+use crate::data::models::PasswordResetModel;
+use crate::data::user_data::create_password_reset;
+use crate::util::hash_token;
+
+/// The code `steps` time steps from now
+fn totp_code(secret: &str, steps: i64) -> String {
+    let secret = Secret::Encoded(secret.to_owned()).to_bytes().unwrap();
+    let time = chrono::Utc::now().timestamp() + steps * 30;
+    TOTP::new(Algorithm::SHA1, 6, 1, 30, secret, None, "test".to_owned())
+        .unwrap()
+        .generate(time as u64)
+}
+
+#[actix_web::test]
+async fn login() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let (status, body) = app.get(&alice, "/api/users").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["username"], "alice");
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/login",
+            None,
+            Some(json!({"username": "alice", "password": "wrong"})),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[actix_web::test]
+async fn requests_need_a_valid_session() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let (status, _) = app.request(Method::GET, "/api/users", None, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let mut alice = app.create_user("alice", false).await;
+    alice.token = "not a session".to_owned();
+    let (status, _) = app.get(&alice, "/api/users").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn requests_refresh_the_session() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let project = app.create_project(&alice, false).await;
+    let session = || async {
+        sqlx::query_as::<_, (String, String)>(
+            "select user_agent, ip_addr from user_sessions where token=$1",
+        )
+        .bind(&alice.token)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap()
+    };
+    // through the plain user extractor and through the project access one
+    for uri in ["/api/users".to_owned(), format!("/api/projects/{project}")] {
+        sqlx::query("update user_sessions set user_agent='old', ip_addr='10.0.0.1:1'")
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let (status, _) = app.get(&alice, &uri).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            session().await,
+            ("foxboard-tests".to_owned(), "127.0.0.1:40000".to_owned()),
+            "{uri}"
+        );
+    }
+}
+
+#[actix_web::test]
+async fn create_user_needs_admin() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let admin = app.create_user("admin", true).await;
+    let alice = app.create_user("alice", false).await;
+    let new_user =
+        json!({"username": "bob", "password": PASSWORD, "email": null, "is_admin": false});
+    let (status, _) = app.post(&alice, "/api/users", new_user.clone()).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = app.post(&admin, "/api/users", new_user).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["username"], "bob");
+    app.login("bob").await;
+}
+
+#[actix_web::test]
+async fn update_user() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let admin = app.create_user("admin", true).await;
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+
+    let (status, body) = app
+        .put(&alice, "/api/users", json!({"email": "alice@example.com"}))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["email"], "alice@example.com");
+
+    let (status, _) = app
+        .put(
+            &alice,
+            "/api/users",
+            json!({"id": bob.id, "email": "x@example.com"}),
+        )
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .put(&alice, "/api/users", json!({"is_admin": true}))
+        .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = app
+        .put(
+            &admin,
+            "/api/users",
+            json!({"id": bob.id, "is_admin": true}),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["is_admin"], true);
+}
+
+#[actix_web::test]
+async fn list_and_get_users() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let admin = app.create_user("admin", true).await;
+    let alice = app.create_user("alice", false).await;
+
+    let (status, body) = app.get(&admin, "/api/users/list?page=1&count=10").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+    let (status, _) = app.get(&alice, "/api/users/list?page=1&count=10").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = app
+        .get(&admin, &format!("/api/users?user_id={}", alice.id))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["username"], "alice");
+    // non admins always get themselves
+    let (status, body) = app
+        .get(&alice, &format!("/api/users?user_id={}", admin.id))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["username"], "alice");
+}
+
+#[actix_web::test]
+async fn username_available() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let (status, body) = app.get(&alice, "/api/users/available/bob").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["success"], true);
+    let (_, body) = app.get(&alice, "/api/users/available/alice").await;
+    assert_eq!(body["success"], false);
+}
+
+#[actix_web::test]
+async fn delete_user() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let admin = app.create_user("admin", true).await;
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+
+    let (status, _) = app.delete(&alice, &format!("/api/users/{}", bob.id)).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app
+        .delete(&alice, &format!("/api/users/{}", alice.id))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.delete(&admin, &format!("/api/users/{}", bob.id)).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app.get(&bob, "/api/users").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn disable_and_enable_user() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let admin = app.create_user("admin", true).await;
+    let alice = app.create_user("alice", false).await;
+    let bob = app.create_user("bob", false).await;
+
+    let uri = format!("/api/users/{}/disable", bob.id);
+    let (status, _) = app.post(&alice, &uri, json!({})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = app.post(&admin, &uri, json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["enabled"], false);
+    let (status, _) = app.get(&bob, "/api/users").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let uri = format!("/api/users/{}/enable", bob.id);
+    let (status, _) = app.post(&alice, &uri, json!({})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, body) = app.post(&admin, &uri, json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["enabled"], true);
+    app.login("bob").await;
+}
+
+#[actix_web::test]
+async fn unlock_user() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let admin = app.create_user("admin", true).await;
+    let alice = app.create_user("alice", false).await;
+    let uri = format!("/api/users/{}/unlock", alice.id);
+    let (status, _) = app.post(&alice, &uri, json!({})).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = app.post(&admin, &uri, json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .post(
+            &admin,
+            &format!("/api/users/{}/unlock", Uuid::nil()),
+            json!({}),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+async fn login_from(app: &TestApp, username: &str, password: &str, ip: &str) -> StatusCode {
+    let req = test::TestRequest::post()
+        .uri("/api/login")
+        .insert_header(("X-Forwarded-For", ip))
+        .set_json(json!({"username": username, "password": password}));
+    app.send(req, None).await.0
+}
+
+#[actix_web::test]
+async fn login_lockout() {
+    let Some(app) = TestApp::start_with(|c| {
+        c.login_max_attempts = 3;
+        c.login_lockout_seconds = 30;
+        c.trusted_proxies = vec!["127.0.0.1".parse().unwrap()];
+    })
+    .await
+    else {
+        return;
+    };
+    app.create_user("alice", false).await;
+    app.create_user("bob", false).await;
+    let lockout = |key: &'static str| {
+        sqlx::query_scalar::<_, f64>(
+            "select extract(epoch from locked_until - now())::float8 from login_attempts where key=$1",
+        )
+        .bind(key)
+        .fetch_one(&app.pool)
+    };
+
+    // the username locks, no matter where the attempts come from
+    for ip in ["10.0.0.1", "10.0.0.2", "10.0.0.3"] {
+        let status = login_from(&app, "alice", "wrong", ip).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let status = login_from(&app, "alice", PASSWORD, "10.0.0.4").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let locked = lockout("alice").await.unwrap();
+    assert!((25.0..=30.0).contains(&locked), "{locked}");
+
+    // every further failure doubles the lockout
+    for expected in [60.0, 120.0, 240.0] {
+        sqlx::query("update login_attempts set locked_until=now() where key='alice'")
+            .execute(&app.pool)
+            .await
+            .unwrap();
+        let status = login_from(&app, "alice", "wrong", "10.0.0.5").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let locked = lockout("alice").await.unwrap();
+        assert!((expected - 5.0..=expected).contains(&locked), "{locked}");
+    }
+
+    // the ip locks across usernames, addresses a client puts in front of the proxy's don't count
+    for username in ["carol", "dave", "erin"] {
+        let status = login_from(&app, username, "wrong", "10.0.1.1").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let status = login_from(&app, "bob", PASSWORD, "10.0.1.1").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let status = login_from(&app, "bob", PASSWORD, "192.0.2.1, 10.0.1.1").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let status = login_from(&app, "bob", PASSWORD, "10.0.1.2").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // a successful login doesn't reset the failures of its ip
+    for username in ["carol", "dave"] {
+        let status = login_from(&app, username, "wrong", "10.0.2.1").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+    let status = login_from(&app, "bob", PASSWORD, "10.0.2.1").await;
+    assert_eq!(status, StatusCode::OK);
+    let status = login_from(&app, "erin", "wrong", "10.0.2.1").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let status = login_from(&app, "bob", PASSWORD, "10.0.2.1").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn totp_login() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    let (status, body) = app.post(&alice, "/api/users/totp/enroll", json!({})).await;
+    assert_eq!(status, StatusCode::OK);
+    let secret = body["secret"].as_str().unwrap().to_owned();
+
+    let (status, _) = app
+        .post(
+            &alice,
+            "/api/users/totp/confirm",
+            json!({"code": "000000x"}),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let confirmed = totp_code(&secret, 0);
+    let (status, body) = app
+        .post(
+            &alice,
+            "/api/users/totp/confirm",
+            json!({"code": confirmed}),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let recovery = body["codes"][0].as_str().unwrap().to_owned();
+
+    // the confirmed code is used up, the next one and a recovery code work once
+    let login = json!({"username": "alice", "password": PASSWORD});
+    let next = totp_code(&secret, 1);
+    for (code, expected) in [
+        (confirmed, StatusCode::UNAUTHORIZED),
+        (next.clone(), StatusCode::OK),
+        (next, StatusCode::UNAUTHORIZED),
+        (recovery.clone(), StatusCode::OK),
+        (recovery, StatusCode::UNAUTHORIZED),
+    ] {
+        let (status, body) = app
+            .request(Method::POST, "/api/login", None, Some(login.clone()))
+            .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body["token"].is_null());
+        let (status, body) = app
+            .request(
+                Method::POST,
+                "/api/login/totp",
+                None,
+                Some(json!({"challenge": body["challenge"], "code": code})),
+            )
+            .await;
+        assert_eq!(status, expected, "{code}");
+        assert_eq!(body["token"].is_string(), expected == StatusCode::OK);
+    }
+
+    let (status, _) = app
+        .post(
+            &alice,
+            "/api/users/totp/disable",
+            json!({"code": totp_code(&secret, 0)}),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    app.login("alice").await;
+}
+
+#[actix_web::test]
+async fn password_reset() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    // no smtp host configured, the mail is only logged
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/password/forgot",
+            None,
+            Some(json!({"email": "nobody@example.com"})),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // the token is created after answering, emails match ignoring case
+    let (status, _) = app
+        .put(&alice, "/api/users", json!({"email": "Alice@Example.com"}))
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/password/forgot",
+            None,
+            Some(json!({"email": "alice@example.com"})),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let mut tokens = 0;
+    for _ in 0..50 {
+        tokens = sqlx::query_scalar::<_, i64>(
+            "select count(*) from password_reset_tokens where user_id=$1",
+        )
+        .bind(alice.id)
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+        if tokens > 0 {
+            break;
+        }
+        actix_web::rt::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(tokens, 1);
+
+    let reset = |token: &str| json!({"token": token, "new_password": "a new password"});
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/password/reset",
+            None,
+            Some(reset("wrong")),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    create_password_reset(
+        &mut app.pool.acquire().await.unwrap(),
+        &PasswordResetModel::new(hash_token("reset token"), alice.id, 10),
+    )
+    .await
+    .unwrap();
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/password/reset",
+            None,
+            Some(reset("reset token")),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    // resetting revokes all sessions
+    let (status, _) = app.get(&alice, "/api/users").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/login",
+            None,
+            Some(json!({"username": "alice", "password": "a new password"})),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn external_accounts_have_no_password() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let alice = app.create_user("alice", false).await;
+    sqlx::query("update users set auth_provider='ldap', email='alice@example.com' where id=$1")
+        .bind(alice.id)
+        .execute(&app.pool)
+        .await
+        .unwrap();
+
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/login",
+            None,
+            Some(json!({"username": "alice", "password": PASSWORD})),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // answered like for any other address, but without a reset token
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/password/forgot",
+            None,
+            Some(json!({"email": "alice@example.com"})),
+        )
+        .await;
+    assert_eq!(status, StatusCode::OK);
+    let tokens: i64 = sqlx::query_scalar("select count(*) from password_reset_tokens")
+        .fetch_one(&app.pool)
+        .await
+        .unwrap();
+    assert_eq!(tokens, 0);
+
+    create_password_reset(
+        &mut app.pool.acquire().await.unwrap(),
+        &PasswordResetModel::new(hash_token("reset token"), alice.id, 10),
+    )
+    .await
+    .unwrap();
+    let (status, body) = app
+        .request(
+            Method::POST,
+            "/api/password/reset",
+            None,
+            Some(json!({"token": "reset token", "new_password": "a new password"})),
+        )
+        .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(body["msg"].as_str().unwrap().contains("ldap"), "{body}");
+}
+
+#[actix_web::test]
+async fn oidc_not_configured() {
+    let Some(app) = TestApp::start().await else {
+        return;
+    };
+    let (status, _) = app
+        .request(Method::GET, "/api/login/oidc", None, None)
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = app
+        .request(
+            Method::POST,
+            "/api/login/oidc/callback",
+            None,
+            Some(json!({"code": "code", "state": "state"})),
+        )
+        .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
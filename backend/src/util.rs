@@ -4,8 +4,15 @@ use std::{
 };
 
 use actix_web::{web::Query, FromRequest};
+use base64::{
+    prelude::{BASE64_STANDARD, BASE64_URL_SAFE_NO_PAD},
+    Engine,
+};
+use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use sqlx::{postgres::PgRow, ColumnIndex, Postgres, Row};
+use utoipa::ToSchema;
 
 use crate::error::{bad_request, AppError, Result};
 
@@ -25,7 +32,7 @@ impl Pagination {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct Page<T> {
     pub page: i32,
     pub count: i32,
@@ -85,3 +92,15 @@ where
         .collect();
     Ok((scalar, projects?))
 }
+
+/// Generates a random token of `len` bytes, url safe base64 encoded
+pub fn generate_token(len: usize) -> String {
+    let mut token_bin = vec![0u8; len];
+    OsRng.fill_bytes(&mut token_bin);
+    BASE64_URL_SAFE_NO_PAD.encode(token_bin)
+}
+
+/// Hashes a token for storage, so a leaked table doesn't leak usable tokens
+pub fn hash_token(token: &str) -> String {
+    BASE64_STANDARD.encode(Sha256::digest(token.as_bytes()))
+}
@@ -13,6 +13,9 @@ export default defineConfig({
   ],
   server: {
     port: 3000,
+    proxy: {
+      '/api': 'http://localhost:8080',
+    },
   },
   build: {
     target: 'esnext',